@@ -0,0 +1,314 @@
+//! This file contains type aliases: reading their declarations, and
+//! expanding them in queries and definitions, so that a type can be found
+//! under any of its names (io::Result<()> is Result<(), io::Error>)
+
+use types::*;
+use parse::*;
+
+// the most aliases expanded inside of each other. real aliases can't be
+// recursive, but ones with the same name can look like they are.
+const MAX_DEPTH: uint = 8;
+
+// parse_alias parses the declaration of an alias in a module, like
+// type Result<T> = Result<T, Error>. type parameters can have defaults
+// (T = u8), which are used when fewer arguments are given, and the
+// trailing semicolon is allowed.
+pub fn parse_alias(decl: &~str, path: &~str) -> Result<@Alias, ParseError> {
+    let s = str::trim(*decl);
+    if !str::starts_with(s, "type ") {
+        return Err(ParseError { pos: 0, msg: ~"expected type" });
+    }
+    let mut i = 5;
+    while i < s.len() && (char::is_alphanumeric(s.char_at(i)) ||
+                          s.char_at(i) == '_') {
+        i += 1;
+    }
+    let name = str::trim(str::slice(s, 5, i));
+    if name.len() == 0 {
+        return Err(ParseError { pos: 5, msg: ~"expected a name" });
+    }
+    let mut params = ~[];
+    let mut defaults = ~[];
+    if i < s.len() && s.char_at(i) == '<' {
+        let close = matching_angle(&s, i);
+        for split_arguments(&str::slice(s, i + 1, close)).each |g| {
+            let parts = str::splitn_char(*g, '=', 1);
+            // bounds don't matter to what the alias stands for
+            let mut p = str::trim(str::splitn_char(parts[0], ':', 1)[0]);
+            if p.len() == 0 || str::starts_with(p, "'") {
+                loop;
+            }
+            if str::starts_with(p, "const ") {
+                p = str::trim(str::slice(p, 6, p.len()));
+            }
+            params.push(p);
+            if parts.len() == 1 {
+                defaults.push(None);
+                loop;
+            }
+            let d = str::trim(parts[1]);
+            match parse_arg(&d) {
+                Ok(ty) => defaults.push(Some(ty)),
+                Err(e) => return Err(relocate(e, &s, &d))
+            }
+        }
+        i = uint::min(close + 1, s.len());
+    }
+    let rest = str::slice(s, i, s.len());
+    let eq = match str::find_char(rest, '=') {
+        Some(e) if str::trim(str::slice(rest, 0, e)).len() == 0 => e,
+        _ => return Err(ParseError { pos: i,
+                                     msg: ~"expected = and a type" })
+    };
+    let mut target = str::trim(str::slice(rest, eq + 1, rest.len()));
+    if str::ends_with(target, ";") {
+        target = str::trim(str::slice(target, 0, target.len() - 1));
+    }
+    match parse_arg(&target) {
+        Ok(ty) => Ok(@Alias { name: name, path: copy *path,
+                              params: copy params,
+                              defaults: vec::map(defaults, |d| {
+                                  d.map(|t| { declare(*t, &params) })
+                              }),
+                              ty: declare(ty, &params) }),
+        Err(e) => Err(relocate(e, &s, &target))
+    }
+}
+
+// add_alias adds an alias to the aliases, under its name
+pub fn add_alias(aliases: &Aliases, a: @Alias) {
+    let mut als = option::get_default(&aliases.find(copy a.name), ~[]);
+    als.push(a);
+    aliases.insert(copy a.name, als);
+}
+
+// expand replaces every alias in a type with the type it stands for.
+// module is the module the type was written in, which decides between
+// aliases with the same name (queries aren't written in one, so for them
+// it is empty).
+pub fn expand(a: @Arg, module: &~str, aliases: &Aliases) -> @Arg {
+    expand_to(a, module, aliases, MAX_DEPTH)
+}
+
+fn expand_to(a: @Arg, module: &~str, aliases: &Aliases, depth: uint)
+        -> @Arg {
+    fn each(args: &~[@Arg], module: &~str, aliases: &Aliases, depth: uint)
+            -> ~[@Arg] {
+        vec::map(*args, |a| { expand_to(*a, module, aliases, depth) })
+    }
+    match *a {
+        Basic(ref n) => {
+            option::get_default(&apply(n, ~[], module, aliases, depth), a)
+        }
+        Parametric(base, ref args) => {
+            let args = each(args, module, aliases, depth);
+            match *base {
+                Basic(ref n) => {
+                    match apply(n, copy args, module, aliases, depth) {
+                        Some(t) => t,
+                        None => @Parametric(base, args)
+                    }
+                }
+                _ => @Parametric(expand_to(base, module, aliases, depth),
+                                 args)
+            }
+        }
+        Tuple(ref args) => @Tuple(each(args, module, aliases, depth)),
+        Vec(inner) => @Vec(expand_to(inner, module, aliases, depth)),
+        Function(ref args, ret) => {
+            @Function(each(args, module, aliases, depth),
+                      expand_to(ret, module, aliases, depth))
+        }
+        Ptr(p, inner) => @Ptr(p, expand_to(inner, module, aliases, depth)),
+        Array(elem, len) => {
+            @Array(expand_to(elem, module, aliases, depth), len)
+        }
+        Closure(ref tr, ref args, ret) => {
+            @Closure(copy *tr, each(args, module, aliases, depth),
+                     expand_to(ret, module, aliases, depth))
+        }
+        Projection(base, ref tr, ref item) => {
+            @Projection(expand_to(base, module, aliases, depth), copy *tr,
+                        copy *item)
+        }
+        QualifiedFn(ref qs, ref args, ret) => {
+            @QualifiedFn(copy *qs, each(args, module, aliases, depth),
+                         expand_to(ret, module, aliases, depth))
+        }
+        Dyn(ref cs, ref bs) => {
+            @Dyn(copy *cs, vec::map(*bs, |b| {
+                b.map(|t| { expand_to(t, module, aliases, depth) })
+            }))
+        }
+        Impl(ref cs, ref bs) => {
+            @Impl(copy *cs, vec::map(*bs, |b| {
+                b.map(|t| { expand_to(t, module, aliases, depth) })
+            }))
+        }
+        Constrained(_, _) | Never | ConstArg(_) | Hole => a
+    }
+}
+
+// apply gives what a name stands for, with the arguments given, if it is
+// an alias. arguments left off the end are filled in from the alias's
+// defaults. any path in front of the name (io::Result) has to be the end
+// of the alias's module. if that leaves several aliases, the one declared
+// in the module the type was written in is used - otherwise there is no
+// telling which was meant, and the type is left alone.
+fn apply(name: &~str, args: ~[@Arg], module: &~str, aliases: &Aliases,
+         depth: uint) -> Option<@Arg> {
+    if depth == 0 {
+        return None;
+    }
+    let segs = str::split_str(*name, "::");
+    let qual = vec::slice(segs, 0, segs.len() - 1);
+    let found = match aliases.find(copy segs[segs.len() - 1]) {
+        Some(als) => als,
+        None => return None
+    };
+    let mut cands = vec::filter(found, |al| {
+        args.len() <= al.params.len() &&
+            vec::all(vec::slice(al.defaults, args.len(), al.params.len()),
+                     |d| { d.is_some() }) &&
+            ends_with(&al.path, qual)
+    });
+    if cands.len() > 1 {
+        cands = vec::filter(cands, |al| { al.path == *module });
+    }
+    if cands.len() != 1 {
+        return None;
+    }
+    let al = cands[0];
+    // a default can use the parameters before it (type M<K, V = K>)
+    let mut args = args;
+    while args.len() < al.params.len() {
+        args.push(fill(al.defaults[args.len()].get(), &al.params, &args));
+    }
+    let ty = fill(al.ty, &al.params, &args);
+    // the alias's type is written in its own module
+    return Some(expand_to(ty, &al.path, aliases, depth - 1));
+}
+
+// fill puts the arguments given in place of an alias's parameters in a
+// type. parameters without an argument are left as they are.
+fn fill(a: @Arg, params: &~[~str], args: &~[@Arg]) -> @Arg {
+    map_constrained(a, |n, cs| {
+        match vec::position_elem(*params, n) {
+            Some(i) if i < args.len() => args[i],
+            _ => @Constrained(copy *n, copy *cs)
+        }
+    })
+}
+
+// ends_with says whether a module path ends with the segments given
+fn ends_with(path: &~str, segs: &[~str]) -> bool {
+    let ps = str::split_str(*path, "::");
+    segs.len() <= ps.len() &&
+        vec::slice(ps, ps.len() - segs.len(), ps.len()) ==
+            vec::from_slice(segs)
+}
+
+// expand_query gives a query with its aliases expanded, or None if it
+// doesn't use any
+pub fn expand_query(q: &Query, aliases: &Aliases) -> Option<Query> {
+    if aliases.size() == 0 {
+        return None;
+    }
+    let args = vec::map(q.args, |a| { expand(*a, &~"", aliases) });
+    let ret = expand(q.ret, &~"", aliases);
+    if args == q.args && ret == q.ret { None }
+    else { Some(Query { args: args, ret: ret, rest: q.rest }) }
+}
+
+// expand_def gives a definition with the aliases in its signature
+// expanded, or None if it doesn't use any
+pub fn expand_def(d: @Definition, aliases: &Aliases) -> Option<@Definition> {
+    if aliases.size() == 0 {
+        return None;
+    }
+    let args = vec::map(d.args, |a| { expand(*a, &d.path, aliases) });
+    let ret = expand(d.ret, &d.path, aliases);
+    if args == d.args && ret == d.ret { None }
+    else { Some(@Definition { args: args, ret: ret, ..copy *d }) }
+}
+
+#[cfg(test)]
+mod tests {
+
+    fn p(s: &str) -> @Arg {
+        parse_arg(&str::from_slice(s)).get()
+    }
+
+    fn std_aliases() -> Aliases {
+        let aliases = HashMap();
+        add_alias(&aliases, parse_alias(&~"type Result<T> = Result<T, Error>;",
+                                        &~"std::io").get());
+        add_alias(&aliases, parse_alias(&~"type Result = Result<(), Error>",
+                                        &~"std::fmt").get());
+        add_alias(&aliases, parse_alias(&~"type Result<T> = Result<T, Box>",
+                                        &~"std::thread").get());
+        add_alias(&aliases, parse_alias(&~"type Id = u64", &~"demo").get());
+        return aliases;
+    }
+
+    #[test]
+    fn test_parse_alias() {
+        let a = parse_alias(&~"type Map<Key, V = int> = HashMap<Key, V>;",
+                            &~"demo").get();
+        assert a.name == ~"Map";
+        assert a.params == ~[~"Key", ~"V"];
+        assert a.ty == @Parametric(@Basic(~"HashMap"),
+                                   ~[@Constrained(~"Key", ~[]),
+                                     @Constrained(~"V", ~[])]);
+        assert a.defaults == ~[None, Some(@Basic(~"int"))];
+        let b = parse_alias(&~"type Pair<T: Clone, const N: usize, U = T> \
+                               = ([T; N], U)", &~"demo").get();
+        assert b.params == ~[~"T", ~"N", ~"U"];
+        assert b.defaults == ~[None, None, Some(@Constrained(~"T", ~[]))];
+        assert parse_alias(&~"type = int", &~"demo").is_err();
+        assert parse_alias(&~"type Id int", &~"demo").is_err();
+        assert parse_alias(&~"type Id = Vec<int", &~"demo").get_err().pos ==
+            13;
+    }
+
+    #[test]
+    fn test_expand() {
+        let aliases = std_aliases();
+        let none = ~"";
+        assert expand(p("io::Result<()>"), &none, &aliases) ==
+            p("Result<(), Error>");
+        assert expand(p("fmt::Result"), &none, &aliases) ==
+            p("Result<(), Error>");
+        assert expand(p("[Id]"), &none, &aliases) == p("[u64]");
+        // which Result<T> is meant depends on where it was written
+        assert expand(p("Result<int>"), &none, &aliases) == p("Result<int>");
+        assert expand(p("Result<int>"), &~"std::io", &aliases) ==
+            p("Result<int, Error>");
+        // the aliased type isn't expanded again
+        assert expand(p("Result<int, Error>"), &~"std::io", &aliases) ==
+            p("Result<int, Error>");
+        // arguments left off are filled in from the defaults, which can
+        // use the ones before them
+        add_alias(&aliases, parse_alias(&~"type Map<K, V = K> = HashMap<K, V>",
+                                        &~"demo").get());
+        assert expand(p("Map<str>"), &none, &aliases) ==
+            p("HashMap<str, str>");
+        assert expand(p("Map<str, int>"), &none, &aliases) ==
+            p("HashMap<str, int>");
+        assert expand(p("Map"), &none, &aliases) == p("Map");
+    }
+
+    #[test]
+    fn test_expand_def() {
+        let aliases = std_aliases();
+        let d = @Definition { name: ~"flush", path: ~"std::io", desc: ~"",
+                              anchor: ~"", args: ~[p("A")],
+                              ret: p("Result<()>"), signature: ~"",
+                              trait_name: None, crate_name: ~"std",
+                              receiver: false };
+        assert expand_def(d, &aliases).get().ret == p("Result<(), Error>");
+        let plain = @Definition { ret: p("int"), ..copy *d };
+        assert expand_def(plain, &aliases).is_none();
+        assert expand_def(d, &HashMap()).is_none();
+    }
+}
@@ -0,0 +1,280 @@
+//! This file contains the coercions used in matching: the ways a value of
+//! one type can be passed where a definition expects another, without
+//! being converted by hand. they let a query be written with the type that
+//! is at hand (String -> uint) when the function takes another (&str).
+
+use types::*;
+use unify::*;
+
+// a Coerced is a substitution, along with the coercions that were needed
+// to get it, as they are described in results
+struct Coerced { subst: Subst, used: ~[~str] }
+
+// rule variables are named so they can't collide with the variables of
+// queries or (renamed apart) definitions
+fn var(n: ~str) -> @Arg {
+    @Constrained(~"^" + n, ~[])
+}
+
+// deref_rules are the deref and unsizing coercions, from the type behind a
+// reference to the type it can be used as. Box<T> is parsed as the owned
+// pointer it is (~T).
+fn deref_rules() -> ~[(@Arg, @Arg)] {
+    fn boxed(b: ~str, t: @Arg) -> @Arg {
+        @Parametric(@Basic(b), ~[t])
+    }
+    let t = var(~"T");
+    ~[(@Basic(~"String"), @Basic(~"str")),
+      (boxed(~"Vec", t), @Vec(t)),
+      (@Array(t, var(~"N")), @Vec(t)),
+      (@Ptr(Owned, t), t),
+      (boxed(~"Rc", t), t),
+      (boxed(~"Arc", t), t),
+      (@Basic(~"PathBuf"), @Basic(~"Path")),
+      (@Basic(~"OsString"), @Basic(~"OsStr")),
+      (@Basic(~"CString"), @Basic(~"CStr"))]
+}
+
+// conversions are the conversion traits, with the types known to convert
+// through them. trait parameters aren't kept (see parse_bounds), so all
+// that is known is that a type converts, not to what.
+fn conversions() -> ~[(~str, ~[~str])] {
+    ~[(~"AsRef", ~[~"String", ~"str", ~"[]", ~"Vec", ~"Box", ~"Rc", ~"Arc",
+                   ~"PathBuf", ~"Path", ~"OsString", ~"OsStr"]),
+      (~"Borrow", ~[~"String", ~"Vec", ~"Box", ~"Rc", ~"Arc", ~"PathBuf",
+                    ~"OsString", ~"CString"]),
+      (~"Into", ~[~"str", ~"String", ~"[]", ~"Vec", ~"Box", ~"PathBuf",
+                  ~"OsString", ~"CString"])]
+}
+
+// is_var says whether a type is (or is bound to) a variable or a hole. they
+// would match the rules for any type, so they are never coerced.
+fn is_var(a: @Arg, s: &Subst) -> bool {
+    match resolve(a, s) {
+        @Constrained(_, _) | @Hole => true,
+        _ => false
+    }
+}
+
+// derefs gives what a type can be used as through the deref rules
+fn derefs(a: @Arg, s: &Subst) -> ~[@Arg] {
+    let mut found = ~[];
+    if is_var(a, s) {
+        return found;
+    }
+    for deref_rules().each |r| {
+        let (from, to) = *r;
+        // the rule's variables are bound to parts of the type, and only
+        // used to fill in what it becomes
+        match unify(from, a, s) {
+            Some(s2) => found.push(map_constrained(to, |n, cs| {
+                resolve(@Constrained(copy *n, copy *cs), &s2)
+            })),
+            None => {}
+        }
+    }
+    return found;
+}
+
+// coercions gives what a type can be coerced to, each with its description.
+// with references ignored the rules apply to the types themselves (String
+// is used as str), while with strict references they only apply behind &
+// and &mut, and &mut T can also be used as &T.
+pub fn coercions(a: @Arg, s: &Subst, opts: &SearchOpts)
+        -> ~[(~str, @Arg)] {
+    let a = resolve(a, s);
+    let mut found = ~[];
+    if !opts.strict_refs {
+        found = derefs(a, s);
+    } else {
+        match a {
+            @Ptr(RefMut(ref l), inner) => {
+                found.push(@Ptr(Ref(copy *l), inner));
+                for derefs(inner, s).each |t| {
+                    found.push(@Ptr(RefMut(copy *l), *t));
+                }
+            }
+            @Ptr(Ref(ref l), inner) => {
+                for derefs(inner, s).each |t| {
+                    found.push(@Ptr(Ref(copy *l), *t));
+                }
+            }
+            _ => {}
+        }
+    }
+    return vec::map(found, |t| {
+        (fmt!("%s to %s", a.to_str(), t.to_str()), *t)
+    });
+}
+
+// converts says whether a type is known to convert through all of the
+// traits of an impl Trait type, either from the table of conversions or
+// from the implementations that were loaded
+fn converts(a: @Arg, cs: &~[Constraint], impls: &Impls) -> bool {
+    let head = match head_name(a) {
+        Some(h) => h,
+        None => return false
+    };
+    fn known(head: &~str, tr: &~str, impls: &Impls) -> bool {
+        if converts_through(head, tr) {
+            return true;
+        }
+        match impls.find(copy *tr) {
+            Some(tys) => vec::any(tys, |t| { same_name(t, head) }),
+            None => false
+        }
+    }
+    cs.len() > 0 && cs.all(|c| { known(&head, &**c, impls) })
+}
+
+// converts_through says whether the table of conversions has a type (by
+// its head_name) converting through a trait
+pub fn converts_through(head: &~str, tr: &~str) -> bool {
+    for conversions().each |c| {
+        let (name, tys) = copy *c;
+        if name == *tr && vec::any(tys, |t| { same_name(t, head) }) {
+            return true;
+        }
+    }
+    return false;
+}
+
+// coerce_to tries to match a type against another only by coercing it,
+// recording the coercion used
+pub fn coerce_to(from: @Arg, to: @Arg, c: &Coerced, opts: &SearchOpts,
+                 impls: &Impls) -> Option<Coerced> {
+    if is_var(from, &c.subst) {
+        return None;
+    }
+    for coercions(from, &c.subst, opts).each |p| {
+        let (desc, t) = copy *p;
+        match unify(t, to, &c.subst) {
+            Some(s2) => {
+                return Some(Coerced { subst: s2,
+                                      used: vec::append_one(copy c.used,
+                                                            desc) });
+            }
+            None => {}
+        }
+    }
+    // a definition taking impl AsRef<str> (say) takes anything that
+    // converts to it
+    match resolve(to, &c.subst) {
+        @Impl(ref cs, _) if converts(resolve(from, &c.subst), cs, impls) => {
+            let desc = fmt!("%s to %s", resolve(from, &c.subst).to_str(),
+                            resolve(to, &c.subst).to_str());
+            Some(Coerced { subst: copy c.subst,
+                           used: vec::append_one(copy c.used, desc) })
+        }
+        _ => None
+    }
+}
+
+// coerce_arg matches a query argument against a definition argument,
+// coercing it if they don't match as they are
+pub fn coerce_arg(q: @Arg, d: @Arg, c: &Coerced, opts: &SearchOpts,
+              impls: &Impls) -> Option<Coerced> {
+    match unify(q, d, &c.subst) {
+        Some(s2) => Some(Coerced { subst: s2, used: copy c.used }),
+        None => coerce_to(q, d, c, opts, impls)
+    }
+}
+
+// coerce_all matches query arguments against definition arguments
+// pairwise, in order, coercing them where needed
+pub fn coerce_all(qs: &[@Arg], ds: &[@Arg], c: &Coerced, opts: &SearchOpts,
+                  impls: &Impls) -> Option<Coerced> {
+    if qs.len() != ds.len() {
+        return None;
+    }
+    let mut cur = Coerced { subst: copy c.subst, used: copy c.used };
+    let mut i = 0;
+    while i < qs.len() {
+        match coerce_arg(qs[i], ds[i], &cur, opts, impls) {
+            Some(c2) => cur = c2,
+            None => return None
+        }
+        i += 1;
+    }
+    return Some(cur);
+}
+
+// coerce_unordered is unify_unordered, coercing arguments where needed
+pub fn coerce_unordered(qs: &[@Arg], ds: &[@Arg], c: &Coerced,
+                        opts: &SearchOpts, impls: &Impls)
+        -> Option<(Coerced, uint, ~[uint])> {
+    place_args(qs, ds, c, |q, d, c| { coerce_arg(q, d, c, opts, impls) })
+}
+
+#[cfg(test)]
+mod tests {
+
+    fn none() -> Coerced {
+        Coerced { subst: ~[], used: ~[] }
+    }
+
+    #[test]
+    fn test_coercions() {
+        let a = @Constrained(~"A", ~[]);
+        let opts = default_opts();
+        assert coercions(@Basic(~"String"), &~[], &opts) ==
+            ~[(~"String to str", @Basic(~"str"))];
+        assert coercions(@Parametric(@Basic(~"Vec"), ~[a]), &~[], &opts) ==
+            ~[(~"Vec<A> to [A]", @Vec(a))];
+        assert coercions(@Basic(~"int"), &~[], &opts) == ~[];
+        // variables would coerce to anything
+        assert coercions(a, &~[], &opts) == ~[];
+        let strict = SearchOpts { strict_refs: true, ..default_opts() };
+        assert coercions(@Basic(~"String"), &~[], &strict) == ~[];
+        assert coercions(@Ptr(RefMut(None), @Basic(~"String")), &~[],
+                         &strict) ==
+            ~[(~"&mut String to &String",
+               @Ptr(Ref(None), @Basic(~"String"))),
+              (~"&mut String to &mut str",
+               @Ptr(RefMut(None), @Basic(~"str")))];
+    }
+
+    #[test]
+    fn test_coerce_to() {
+        let opts = default_opts();
+        let impls = HashMap();
+        let c = coerce_to(@Basic(~"String"), @Basic(~"str"), &none(), &opts,
+                          &impls).get();
+        assert c.used == ~[~"String to str"];
+        assert coerce_to(@Basic(~"str"), @Basic(~"String"), &none(), &opts,
+                         &impls).is_none();
+        // the types inside are still matched
+        let boxed = parse::parse_arg(&~"Box<int>").get();
+        assert coerce_to(boxed, @Constrained(~"'A", ~[]), &none(), &opts,
+                         &impls).get().subst ==
+            ~[(~"'A", @Basic(~"int"))];
+        // strictly, a box is only dereferenced behind a reference
+        let strict = SearchOpts { strict_refs: true, ..default_opts() };
+        assert coerce_to(parse::parse_arg(&~"&Box<int>").get(),
+                         parse::parse_arg(&~"&int").get(), &none(), &strict,
+                         &impls).get().used == ~[~"&~int to &int"];
+        assert coerce_to(boxed, @Basic(~"int"), &none(), &strict,
+                         &impls).is_none();
+        let as_ref = @Impl(~[Constraint(~"AsRef")], ~[]);
+        assert coerce_to(@Basic(~"String"), as_ref, &none(), &opts,
+                         &impls).get().used == ~[~"String to impl AsRef"];
+        assert coerce_to(@Basic(~"int"), as_ref, &none(), &opts,
+                         &impls).is_none();
+        impls.insert(~"AsRef", ~[~"int"]);
+        assert coerce_to(@Basic(~"int"), as_ref, &none(), &opts,
+                         &impls).is_some();
+    }
+
+    #[test]
+    fn test_coerce_unordered() {
+        let opts = default_opts();
+        let qs = ~[@Basic(~"int"), @Basic(~"String")];
+        let ds = ~[@Basic(~"str"), @Basic(~"int")];
+        let (c, swaps, places) = coerce_unordered(qs, ds, &none(), &opts,
+                                                  &HashMap()).get();
+        assert c.used == ~[~"String to str"];
+        assert swaps == 1;
+        assert places == ~[1, 0];
+        assert coerce_all(qs, ds, &none(), &opts, &HashMap()).is_none();
+    }
+}
@@ -0,0 +1,722 @@
+//! This file contains the compiled index format: a binary form of Data,
+//! with signatures already parsed and the buckets and name index already
+//! built, so that loading it doesn't have to redo any of that work. the
+//! standard library can't memory map files, so the whole index is read in
+//! at once, which is still far quicker than parsing the json.
+
+use types::*;
+
+// every index starts with the magic bytes and the format version, and
+// then the files it was compiled from. indexes written with a different
+// version are rejected, rather than misread - the version should be bumped
+// whenever Data (or anything in it) changes.
+const VERSION: uint = 11;
+
+fn magic() -> ~[u8] {
+    str::to_bytes("RUSTLEIX")
+}
+
+// tags for the variants of Arg
+const BASIC: u8 = 0;
+const PARAMETRIC: u8 = 1;
+const TUPLE: u8 = 2;
+const VEC: u8 = 3;
+const CONSTRAINED: u8 = 4;
+const FUNCTION: u8 = 5;
+const PTR: u8 = 6;
+const ARRAY: u8 = 7;
+const DYN: u8 = 8;
+const IMPL: u8 = 9;
+const CLOSURE: u8 = 10;
+const NEVER: u8 = 11;
+const PROJECTION: u8 = 12;
+const CONST_ARG: u8 = 13;
+const QUALIFIED_FN: u8 = 14;
+const HOLE: u8 = 15;
+
+// tags for the kinds of Pointer
+const REF: u8 = 0;
+const REF_MUT: u8 = 1;
+const OWNED: u8 = 2;
+const MANAGED: u8 = 3;
+const RAW_CONST: u8 = 4;
+const RAW_MUT: u8 = 5;
+
+// is_index says whether a file is a compiled index (as opposed to json)
+pub fn is_index(path: &path::Path) -> bool {
+    match io::file_reader(path) {
+        Ok(r) => r.read_bytes(magic().len()) == magic(),
+        Err(_) => false
+    }
+}
+
+// a Source is a file an index was compiled from, with a hash of what was
+// in it at the time. rustdoc json is loaded differently from data files,
+// so which it was is kept too.
+pub struct Source { path: ~str, rustdoc: bool, hash: uint }
+
+impl Source : Eq {
+    pure fn eq(other: &Source) -> bool {
+        self.path == other.path && self.rustdoc == other.rustdoc &&
+            self.hash == other.hash
+    }
+    pure fn ne(other: &Source) -> bool {
+        !self.eq(other)
+    }
+}
+
+// source records a file that an index is being compiled from
+pub fn source(path: &path::Path, rustdoc: bool) -> Source {
+    Source { path: path.to_str(), rustdoc: rustdoc, hash: hash_file(path) }
+}
+
+// changed gives the first of the files an index was compiled from that
+// is different now. one that can't be read any more isn't counted, as
+// the index is then the only copy of what was in it.
+pub fn changed(sources: &~[Source]) -> Option<~str> {
+    for sources.each |s| {
+        let p = path::from_str(s.path);
+        if os::path_exists(&p) && hash_file(&p) != s.hash {
+            return Some(copy s.path);
+        }
+    }
+    return None;
+}
+
+// hash_file hashes the contents of a file (with 32 bit FNV-1a, which is
+// all that is needed to notice that it changed), giving 0 if it can't be
+// read
+fn hash_file(path: &path::Path) -> uint {
+    match io::read_whole_file(path) {
+        Ok(bytes) => hash_bytes(bytes),
+        Err(_) => 0
+    }
+}
+
+fn hash_bytes(bytes: &[u8]) -> uint {
+    let mut h = 2166136261;
+    for bytes.each |b| {
+        h = ((h ^ (*b as uint)) * 16777619) & 0xffffffff;
+    }
+    return h;
+}
+
+// write compiles data into an index file, recording the files it came
+// from
+pub fn write(data: &Data, sources: &~[Source], path: &path::Path) {
+    match io::file_writer(path, ~[io::Create, io::Truncate]) {
+        Ok(w) => w.write(encode(data, sources)),
+        Err(e) => {
+            io::println(fmt!("can't write %s: %s", path.to_str(), e));
+            libc::exit(1);
+        }
+    }
+}
+
+// load reads a compiled index, along with the files it was compiled from
+// (see changed). files that aren't indexes, or were written by a different
+// version of rustle, give an error.
+pub fn load(path: &path::Path) -> Result<(Data, ~[Source]), ~str> {
+    match io::read_whole_file(path) {
+        Ok(bytes) => decode(bytes),
+        Err(e) => Err(e)
+    }
+}
+
+// encode gives the bytes of the index for some data. definitions are
+// written once, in bucket order, and the name index refers to them by
+// their position.
+pub fn encode(data: &Data, sources: &~[Source]) -> ~[u8] {
+    let mut buf = magic();
+    put_uint(&mut buf, VERSION);
+    put_uint(&mut buf, sources.len());
+    for sources.each |s| {
+        put_str(&mut buf, &s.path);
+        put_uint(&mut buf, if s.rustdoc { 1 } else { 0 });
+        put_uint(&mut buf, s.hash);
+    }
+    let buckets = [&data.ar0, &data.ar1, &data.ar2, &data.ar3, &data.ar4,
+                   &data.ar5, &data.arn];
+    let ids = HashMap();
+    for buckets.each |b| {
+        put_uint(&mut buf, b.defs.len());
+        for b.defs.each |d| {
+            ids.insert(def_key(*d), ids.size());
+            put_def(&mut buf, *d);
+        }
+    }
+    put_names(&mut buf, &data.names, &ids);
+    put_uint(&mut buf, data.modules.size());
+    for data.modules.each |m, rank| {
+        put_str(&mut buf, &m);
+        put_uint(&mut buf, rank);
+    }
+    put_uint(&mut buf, data.impls.size());
+    for data.impls.each |tr, tys| {
+        put_str(&mut buf, &tr);
+        put_strs(&mut buf, &tys);
+    }
+    put_aliases(&mut buf, &data.aliases);
+    put_uint(&mut buf, data.types.size());
+    for data.types.each |name, paths| {
+        put_str(&mut buf, &name);
+        put_strs(&mut buf, &paths);
+    }
+    return buf;
+}
+
+// decode reads back what encode wrote
+pub fn decode(bytes: ~[u8]) -> Result<(Data, ~[Source]), ~str> {
+    let d = Decoder { bytes: bytes, pos: 0, error: None };
+    if d.bytes.len() < magic().len() + 4 ||
+        vec::slice(d.bytes, 0, magic().len()) != magic() {
+        return Err(~"not a rustle index");
+    }
+    d.pos = magic().len();
+    let version = d.read_uint();
+    if version != VERSION {
+        return Err(fmt!("index is version %u, but this rustle reads \
+                         version %u", version, VERSION));
+    }
+    let mut sources = ~[];
+    for d.read_count().times {
+        let path = d.read_str();
+        let rustdoc = d.read_uint() == 1;
+        sources.push(Source { path: path, rustdoc: rustdoc,
+                              hash: d.read_uint() });
+    }
+    let mut defs = ~[];
+    let mut buckets = ~[];
+    for 7.times {
+        let n = d.read_count();
+        let mut b = ~[];
+        for n.times {
+            let def = d.read_def();
+            defs.push(def);
+            b.push(def);
+        }
+        buckets.push(Bucket { defs: b });
+    }
+    let names = d.read_names(&defs);
+    let modules = HashMap();
+    for d.read_count().times {
+        let m = d.read_str();
+        modules.insert(m, d.read_uint());
+    }
+    let impls = HashMap();
+    for d.read_count().times {
+        let tr = d.read_str();
+        impls.insert(tr, d.read_strs());
+    }
+    let aliases = d.read_aliases();
+    let types = HashMap();
+    for d.read_count().times {
+        let name = d.read_str();
+        types.insert(name, d.read_strs());
+    }
+    match copy d.error {
+        Some(e) => return Err(e),
+        None => {}
+    }
+    if d.pos != d.bytes.len() {
+        return Err(~"index has trailing data");
+    }
+    Ok((Data { ar0: copy buckets[0], ar1: copy buckets[1],
+               ar2: copy buckets[2], ar3: copy buckets[3],
+               ar4: copy buckets[4], ar5: copy buckets[5],
+               arn: copy buckets[6], names: names, modules: modules,
+               impls: impls, aliases: aliases, types: types }, sources))
+}
+
+// def_key identifies a definition by where it is in memory, as the same
+// definition is shared between its bucket and the name index
+fn def_key(d: @Definition) -> uint {
+    ptr::addr_of(&(*d)) as uint
+}
+
+// numbers are written as four bytes, big endian
+fn put_uint(buf: &mut ~[u8], n: uint) {
+    buf.push((n >> 24) as u8);
+    buf.push((n >> 16) as u8);
+    buf.push((n >> 8) as u8);
+    buf.push(n as u8);
+}
+
+fn put_str(buf: &mut ~[u8], s: &~str) {
+    put_uint(buf, s.len());
+    buf.push_all(str::to_bytes(*s));
+}
+
+fn put_strs(buf: &mut ~[u8], ss: &~[~str]) {
+    put_uint(buf, ss.len());
+    for ss.each |s| {
+        put_str(buf, s);
+    }
+}
+
+fn put_args(buf: &mut ~[u8], args: &~[@Arg]) {
+    put_uint(buf, args.len());
+    for args.each |a| {
+        put_arg(buf, *a);
+    }
+}
+
+fn put_bindings(buf: &mut ~[u8], bs: &~[Binding]) {
+    put_uint(buf, bs.len());
+    for bs.each |b| {
+        put_str(buf, &b.tr);
+        put_str(buf, &b.item);
+        put_arg(buf, b.ty);
+    }
+}
+
+fn put_arg(buf: &mut ~[u8], a: @Arg) {
+    match *a {
+        Basic(ref n) => {
+            buf.push(BASIC);
+            put_str(buf, n);
+        }
+        Parametric(base, ref args) => {
+            buf.push(PARAMETRIC);
+            put_arg(buf, base);
+            put_args(buf, args);
+        }
+        Tuple(ref args) => {
+            buf.push(TUPLE);
+            put_args(buf, args);
+        }
+        Vec(inner) => {
+            buf.push(VEC);
+            put_arg(buf, inner);
+        }
+        Constrained(ref n, ref cs) => {
+            buf.push(CONSTRAINED);
+            put_str(buf, n);
+            put_strs(buf, &vec::map(*cs, |c| { copy **c }));
+        }
+        Function(ref args, ret) => {
+            buf.push(FUNCTION);
+            put_args(buf, args);
+            put_arg(buf, ret);
+        }
+        Ptr(p, inner) => {
+            buf.push(PTR);
+            put_pointer(buf, p);
+            put_arg(buf, inner);
+        }
+        Array(elem, len) => {
+            buf.push(ARRAY);
+            put_arg(buf, elem);
+            put_arg(buf, len);
+        }
+        Dyn(ref cs, ref bs) => {
+            buf.push(DYN);
+            put_strs(buf, &vec::map(*cs, |c| { copy **c }));
+            put_bindings(buf, bs);
+        }
+        Impl(ref cs, ref bs) => {
+            buf.push(IMPL);
+            put_strs(buf, &vec::map(*cs, |c| { copy **c }));
+            put_bindings(buf, bs);
+        }
+        Closure(ref tr, ref args, ret) => {
+            buf.push(CLOSURE);
+            put_str(buf, tr);
+            put_args(buf, args);
+            put_arg(buf, ret);
+        }
+        Never => buf.push(NEVER),
+        Hole => buf.push(HOLE),
+        Projection(base, ref tr, ref item) => {
+            buf.push(PROJECTION);
+            put_arg(buf, base);
+            // like a trait name of a definition, a list of zero or one
+            put_strs(buf, &tr.map_default(~[], |t| { ~[copy *t] }));
+            put_str(buf, item);
+        }
+        ConstArg(ref c) => {
+            buf.push(CONST_ARG);
+            put_str(buf, c);
+        }
+        QualifiedFn(ref qs, ref args, ret) => {
+            buf.push(QUALIFIED_FN);
+            put_str(buf, qs);
+            put_args(buf, args);
+            put_arg(buf, ret);
+        }
+    }
+}
+
+// a pointer is its tag, then its lifetime as a list of zero or one names
+fn put_pointer(buf: &mut ~[u8], p: Pointer) {
+    buf.push(match p {
+        Ref(_) => REF,
+        RefMut(_) => REF_MUT,
+        Owned => OWNED,
+        Managed => MANAGED,
+        RawConst => RAW_CONST,
+        RawMut => RAW_MUT
+    });
+    put_strs(buf, &p.lifetime().map_default(~[], |l| { ~[copy *l] }));
+}
+
+fn put_def(buf: &mut ~[u8], d: @Definition) {
+    put_str(buf, &d.name);
+    put_str(buf, &d.path);
+    put_str(buf, &d.anchor);
+    put_str(buf, &d.desc);
+    put_args(buf, &d.args);
+    put_arg(buf, d.ret);
+    put_str(buf, &d.signature);
+    // a trait name is written as a list of zero or one names
+    put_strs(buf, &d.trait_name.map_default(~[], |t| { ~[copy *t] }));
+    put_str(buf, &d.crate_name);
+    put_uint(buf, if d.receiver { 1 } else { 0 });
+}
+
+// the name index is written as each name, followed by the positions of
+// its definitions
+fn put_names(buf: &mut ~[u8], names: &NameIndex, ids: &HashMap<uint,uint>) {
+    put_uint(buf, names.size());
+    for names.each |name, defs| {
+        put_str(buf, &name);
+        put_uint(buf, defs.len());
+        for defs.each |d| {
+            put_uint(buf, ids.get(def_key(*d)));
+        }
+    }
+}
+
+// aliases are written one after another, each with its name and module,
+// whatever name they are kept under
+fn put_aliases(buf: &mut ~[u8], aliases: &Aliases) {
+    let mut all = ~[];
+    for aliases.each |_name, als| {
+        all.push_all(als);
+    }
+    put_uint(buf, all.len());
+    for all.each |a| {
+        put_str(buf, &a.name);
+        put_str(buf, &a.path);
+        put_strs(buf, &a.params);
+        // each default is a flag, then the type if there is one
+        for a.defaults.each |d| {
+            match *d {
+                Some(t) => {
+                    put_uint(buf, 1);
+                    put_arg(buf, t);
+                }
+                None => put_uint(buf, 0)
+            }
+        }
+        put_arg(buf, a.ty);
+    }
+}
+
+// a Decoder reads through the bytes of an index. a truncated or corrupt
+// file is noted in error (the first problem found), and reading goes on
+// with zeros, which decode then gives up on, rather than failing.
+struct Decoder { bytes: ~[u8], mut pos: uint, mut error: Option<~str> }
+
+impl Decoder {
+    fn corrupt(msg: ~str) {
+        if self.error.is_none() {
+            self.error = Some(msg);
+        }
+    }
+
+    fn read_byte() -> u8 {
+        if self.pos >= self.bytes.len() {
+            self.corrupt(~"index is truncated");
+            return 0;
+        }
+        let b = self.bytes[self.pos];
+        self.pos += 1;
+        return b;
+    }
+
+    fn read_uint() -> uint {
+        let mut n = 0;
+        for 4.times {
+            n = (n << 8) | (self.read_byte() as uint);
+        }
+        return n;
+    }
+
+    // read_count reads the number of things that follow. each takes at
+    // least a byte, so a count bigger than what is left is corrupt, and is
+    // read as none, rather than looped over.
+    fn read_count() -> uint {
+        let n = self.read_uint();
+        if n > self.bytes.len() - self.pos {
+            self.corrupt(~"index is truncated");
+            return 0;
+        }
+        return n;
+    }
+
+    fn read_str() -> ~str {
+        let n = self.read_count();
+        let s = str::from_bytes(vec::slice(self.bytes, self.pos,
+                                           self.pos + n));
+        self.pos += n;
+        return s;
+    }
+
+    fn read_strs() -> ~[~str] {
+        let mut ss = ~[];
+        for self.read_count().times {
+            ss.push(self.read_str());
+        }
+        return ss;
+    }
+
+    fn read_args() -> ~[@Arg] {
+        let mut args = ~[];
+        for self.read_count().times {
+            args.push(self.read_arg());
+        }
+        return args;
+    }
+
+    fn read_bindings() -> ~[Binding] {
+        let mut bs = ~[];
+        for self.read_count().times {
+            let tr = self.read_str();
+            let item = self.read_str();
+            bs.push(Binding { tr: tr, item: item, ty: self.read_arg() });
+        }
+        return bs;
+    }
+
+    fn read_arg() -> @Arg {
+        let tag = self.read_byte();
+        if tag == BASIC {
+            @Basic(self.read_str())
+        } else if tag == PARAMETRIC {
+            let base = self.read_arg();
+            @Parametric(base, self.read_args())
+        } else if tag == TUPLE {
+            @Tuple(self.read_args())
+        } else if tag == VEC {
+            @Vec(self.read_arg())
+        } else if tag == CONSTRAINED {
+            let n = self.read_str();
+            let cs = vec::map(self.read_strs(), |c| { Constraint(copy *c) });
+            @Constrained(n, cs)
+        } else if tag == FUNCTION {
+            let args = self.read_args();
+            @Function(args, self.read_arg())
+        } else if tag == PTR {
+            let p = self.read_pointer();
+            @Ptr(p, self.read_arg())
+        } else if tag == ARRAY {
+            let elem = self.read_arg();
+            @Array(elem, self.read_arg())
+        } else if tag == DYN {
+            let cs = vec::map(self.read_strs(), |c| { Constraint(copy *c) });
+            @Dyn(cs, self.read_bindings())
+        } else if tag == IMPL {
+            let cs = vec::map(self.read_strs(), |c| { Constraint(copy *c) });
+            @Impl(cs, self.read_bindings())
+        } else if tag == CLOSURE {
+            let tr = self.read_str();
+            let args = self.read_args();
+            @Closure(tr, args, self.read_arg())
+        } else if tag == NEVER {
+            @Never
+        } else if tag == HOLE {
+            @Hole
+        } else if tag == PROJECTION {
+            let base = self.read_arg();
+            let trs = self.read_strs();
+            let tr = if trs.len() == 1 { Some(copy trs[0]) } else { None };
+            @Projection(base, tr, self.read_str())
+        } else if tag == CONST_ARG {
+            @ConstArg(self.read_str())
+        } else if tag == QUALIFIED_FN {
+            let qs = self.read_str();
+            let args = self.read_args();
+            @QualifiedFn(qs, args, self.read_arg())
+        } else {
+            self.corrupt(fmt!("bad tag %u in index", tag as uint));
+            @Hole
+        }
+    }
+
+    fn read_pointer() -> Pointer {
+        let tag = self.read_byte();
+        let lts = self.read_strs();
+        let lifetime = if lts.len() == 1 { Some(copy lts[0]) } else { None };
+        if tag == REF {
+            Ref(lifetime)
+        } else if tag == REF_MUT {
+            RefMut(lifetime)
+        } else if tag == OWNED {
+            Owned
+        } else if tag == MANAGED {
+            Managed
+        } else if tag == RAW_CONST {
+            RawConst
+        } else if tag == RAW_MUT {
+            RawMut
+        } else {
+            self.corrupt(fmt!("bad pointer tag %u in index", tag as uint));
+            Ref(None)
+        }
+    }
+
+    fn read_def() -> @Definition {
+        let name = self.read_str();
+        let path = self.read_str();
+        let anchor = self.read_str();
+        let desc = self.read_str();
+        let args = self.read_args();
+        let ret = self.read_arg();
+        let signature = self.read_str();
+        let traits = self.read_strs();
+        let trait_name = if traits.len() == 1 { Some(copy traits[0]) }
+                         else { None };
+        let crate_name = self.read_str();
+        @Definition { name: name, path: path, anchor: anchor, desc: desc,
+                      args: args, ret: ret, signature: signature,
+                      trait_name: trait_name, crate_name: crate_name,
+                      receiver: self.read_uint() == 1 }
+    }
+
+    fn read_aliases() -> Aliases {
+        let aliases = HashMap();
+        for self.read_count().times {
+            let name = self.read_str();
+            let path = self.read_str();
+            let params = self.read_strs();
+            let defaults = vec::map(params, |_p| {
+                if self.read_uint() == 1 { Some(self.read_arg()) }
+                else { None }
+            });
+            alias::add_alias(&aliases, @Alias { name: name, path: path,
+                                                params: params,
+                                                defaults: defaults,
+                                                ty: self.read_arg() });
+        }
+        return aliases;
+    }
+
+    fn read_names(defs: &~[@Definition]) -> NameIndex {
+        let names = HashMap();
+        for self.read_count().times {
+            let name = self.read_str();
+            let mut ds = ~[];
+            for self.read_count().times {
+                let i = self.read_uint();
+                if i < defs.len() {
+                    ds.push(defs[i]);
+                } else {
+                    self.corrupt(fmt!("bad definition %u in index", i));
+                }
+            }
+            names.insert(name, ds);
+        }
+        return names;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn test_round_trip() {
+        let recs = ~[json::from_str(
+            ~"{\"name\": \"unwrap\", \"path\": \"core::option\", \
+               \"anchor\": \"function-unwrap\", \"desc\": \"\", \
+               \"self\": \"\", \
+               \"type\": \"fn unwrap<T: Copy>(opt: &'a mut Option<T>) \
+                           -> T\"}").get(),
+                    json::from_str(
+            ~"{\"kind\": \"impl\", \"trait\": \"Ord\", \"for\": \"int\"}")
+                    .get(),
+                    json::from_str(
+            ~"{\"kind\": \"type\", \"name\": \"Id\", \"path\": \"core\", \
+               \"type\": \"type Id = uint\"}").get(),
+                    json::from_str(
+            ~"{\"kind\": \"enum\", \"name\": \"Option\", \
+               \"path\": \"core::option\"}").get()];
+        let data = load::from_records(&recs);
+        let sources = ~[Source { path: ~"rustle.data", rustdoc: false,
+                                 hash: 7 }];
+        let (back, back_sources) = decode(encode(&data, &sources)).get();
+        assert back_sources == sources;
+        assert back.ar1.defs == data.ar1.defs;
+        assert back.ar0.defs == ~[];
+        assert back.modules.get(~"core::option") == 0;
+        assert back.impls.get(~"Ord") == ~[~"int"];
+        assert back.aliases.get(~"Id") == data.aliases.get(~"Id");
+        assert back.types.get(~"Option") == ~[~"core::option::Option"];
+        // the name index shares the definitions in the buckets
+        let ds = back.names.get(~"unwrap");
+        assert ds.len() == 1;
+        assert box::ptr_eq(ds[0], back.ar1.defs[0]);
+    }
+
+    #[test]
+    fn test_round_trip_args() {
+        let a = parse::parse_arg(&~"fn(&'a mut [T; 3], Box<dyn Fn(A) -> !>, \
+                                    dyn Send + Sync, unsafe extern fn(), _, \
+                                    impl Iterator<Item = u8>) \
+                                    -> <T as Iterator>::Item").get();
+        let mut buf = ~[];
+        put_arg(&mut buf, a);
+        let d = Decoder { bytes: buf, pos: 0, error: None };
+        assert d.read_arg() == a;
+        assert d.pos == d.bytes.len();
+    }
+
+    #[test]
+    fn test_version() {
+        let mut bytes = encode(&empty_data(), &~[]);
+        assert decode(copy bytes).is_ok();
+        bytes[magic().len() + 3] = (VERSION + 1) as u8;
+        assert decode(copy bytes).is_err();
+        assert decode(str::to_bytes("[]")).is_err();
+    }
+
+    #[test]
+    fn test_corrupt() {
+        let recs = ~[json::from_str(
+            ~"{\"name\": \"len\", \"path\": \"core::str\", \
+               \"anchor\": \"\", \"desc\": \"\", \"self\": \"\", \
+               \"type\": \"fn len(s: &str) -> uint\"}").get()];
+        let bytes = encode(&load::from_records(&recs), &~[]);
+        // cut off in the middle of the definition
+        let short = vec::slice(bytes, 0, bytes.len() / 2);
+        match decode(short) {
+            Err(e) => assert e == ~"index is truncated",
+            Ok(_) => fail
+        }
+        // a tag that isn't one of Arg's
+        let mut buf = ~[];
+        buf.push(200u8);
+        let d = Decoder { bytes: buf, pos: 0, error: None };
+        assert d.read_arg() == @Hole;
+        assert d.error == Some(~"bad tag 200 in index");
+    }
+
+    #[test]
+    fn test_changed() {
+        assert hash_bytes(str::to_bytes("")) == 2166136261;
+        assert hash_bytes(str::to_bytes("a")) == 0xe40c292c;
+        let p = path::from_str("test_changed.data");
+        io::file_writer(&p, ~[io::Create, io::Truncate]).get()
+            .write(str::to_bytes("[]"));
+        let now = source(&p, false);
+        assert now.hash == hash_bytes(str::to_bytes("[]"));
+        assert changed(&~[copy now]) == None;
+        let then = Source { hash: now.hash + 1, ..copy now };
+        assert changed(&~[then]) == Some(~"test_changed.data");
+        os::remove_file(&p);
+        // a file that is gone doesn't make the index out of date
+        let gone = Source { path: ~"no-such-file.data", rustdoc: false,
+                            hash: 1 };
+        assert changed(&~[gone]) == None;
+    }
+}
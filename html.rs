@@ -0,0 +1,89 @@
+//! This file contains html escaping, and the tiny templates the web
+//! frontend renders its pages with
+
+// escape makes text safe to put anywhere in a page, including inside of
+// quoted attributes
+pub fn escape(s: &~str) -> ~str {
+    let mut out = ~"";
+    for str::each_char(*s) |c| {
+        match c {
+            '&' => str::push_str(&mut out, "&amp;"),
+            '<' => str::push_str(&mut out, "&lt;"),
+            '>' => str::push_str(&mut out, "&gt;"),
+            '"' => str::push_str(&mut out, "&quot;"),
+            '\'' => str::push_str(&mut out, "&#39;"),
+            _ => str::push_char(&mut out, c)
+        }
+    }
+    return out;
+}
+
+// a Value is what a template variable is filled in with. Text is escaped,
+// while Html is markup that was already rendered (from a template, or with
+// everything in it escaped) and is put in as is.
+pub enum Value {
+    Text(~str),
+    Html(~str)
+}
+
+// render fills in the {{name}} variables in a template. variables that
+// aren't given are left empty, so a typo can't leak template syntax into a
+// page.
+pub fn render(template: &str, vars: &~[(~str, Value)]) -> ~str {
+    let mut out = ~"";
+    let mut rest = str::from_slice(template);
+    loop {
+        match str::find_str(rest, "{{") {
+            None => break,
+            Some(open) => {
+                str::push_str(&mut out, str::slice(rest, 0, open));
+                let after = str::slice(rest, open + 2, rest.len());
+                match str::find_str(after, "}}") {
+                    None => {
+                        rest = str::slice(rest, open, rest.len());
+                        break;
+                    }
+                    Some(close) => {
+                        let name = str::trim(str::slice(after, 0, close));
+                        str::push_str(&mut out, lookup(vars, &name));
+                        rest = str::slice(after, close + 2, after.len());
+                    }
+                }
+            }
+        }
+    }
+    str::push_str(&mut out, rest);
+    return out;
+}
+
+fn lookup(vars: &~[(~str, Value)], name: &~str) -> ~str {
+    for vars.each |v| {
+        match *v {
+            (ref n, Text(ref s)) if *n == *name => return escape(s),
+            (ref n, Html(ref s)) if *n == *name => return copy *s,
+            _ => {}
+        }
+    }
+    return ~"";
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn test_escape() {
+        assert escape(&~"<script>alert('x & y')</script>") ==
+            ~"&lt;script&gt;alert(&#39;x &amp; y&#39;)&lt;/script&gt;";
+        assert escape(&~"a=\"b\"") == ~"a=&quot;b&quot;";
+        assert escape(&~"Option<A> -> A") == ~"Option&lt;A&gt; -&gt; A";
+    }
+
+    #[test]
+    fn test_render() {
+        let vars = ~[(~"q", Text(~"<b>")), (~"r", Html(~"<i>x</i>"))];
+        assert render("q: {{q}}, r: {{ r }}", &vars) ==
+            ~"q: &lt;b&gt;, r: <i>x</i>";
+        assert render("{{missing}}!", &vars) == ~"!";
+        assert render("no {{ end", &vars) == ~"no {{ end";
+    }
+}
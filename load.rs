@@ -1,12 +1,33 @@
 //! This file contains code related to loading data from disk
 
 use parse::*;
+use io::WriterUtil;
 
 // load parses a json file with all the data into the in-memory
 // representation above
 pub fn load(path: path::Path) -> Data {
+    from_records(&read_records(&path))
+}
+
+// read_records reads the list of records in a data file. records from
+// several files (for different crates, say) can be put together and loaded
+// at once.
+pub fn read_records(path: &path::Path) -> ~[Json] {
+    match read_json(path) {
+        List(lst) => lst,
+        _ => {
+            io::println(fmt!("json in %s not correctly formatted",
+                             path.to_str()));
+            libc::exit(1);
+            fail;
+        }
+    }
+}
+
+// read_json reads and parses a json file, exiting if it can't
+pub fn read_json(path: &path::Path) -> Json {
     let res;
-    match io::file_reader(&path) {
+    match io::file_reader(path) {
         Err(msg) => {
             io::println(fmt!("file_reader err: %s", msg));
             libc::exit(1);
@@ -18,35 +39,289 @@ pub fn load(path: path::Path) -> Data {
     }
 
     match res {
-        Ok(json) => {
-            match json {
-                List(lst) => {
-                    let defs = vec::concat(vec::map(lst, load_obj));
-                    bucket_sort(defs)
+        Ok(json) => json,
+        Err(err) => {
+            io::println(fmt!("parsing error in data on line %u, col %u",
+                             err.line, err.col));
+            libc::exit(1);
+            fail;
+        }
+    }
+}
+
+// from_records builds the in-memory representation out of a list of records
+// (functions, traits, impls, type aliases and the declarations of types),
+// wherever they came from
+pub fn from_records(lst: &~[Json]) -> Data {
+    let impls = HashMap();
+    let aliases = HashMap();
+    // methods of impls of traits that are declared in the data are found
+    // through the declaration, so we need to know which those are before
+    // loading any impls
+    let traits = HashMap();
+    // the names of types in signatures (and the types impls are for) are
+    // resolved to the full paths of the types declared in the data, except
+    // for the names of aliases (see paths.rs)
+    let types = HashMap();
+    for lst.each |obj| {
+        match kind(obj) {
+            ~"trait" => {
+                traits.insert(field(obj, ~"name"), ());
+                paths::add_type(&types, declared(obj));
+            }
+            ~"struct" | ~"enum" | ~"union" => {
+                paths::add_type(&types, declared(obj));
+            }
+            ~"type" => load_alias(obj, &aliases),
+            _ => {}
+        }
+    }
+    let mut defs = ~[];
+    for lst.each |obj| {
+        let ds = match kind(obj) {
+            ~"impl" => load_impl(obj, &impls, &traits, &types, &aliases),
+            ~"trait" => load_trait(obj),
+            ~"type" | ~"struct" | ~"enum" | ~"union" => ~[],
+            _ => load_obj(obj)
+        };
+        defs.push_all_move(ds);
+    }
+    let defs = vec::map(defs, |d| {
+        paths::canonical_def(*d, &types, &aliases)
+    });
+    return bucket_sort(defs, impls, aliases, types);
+}
+
+// declared gives the full path of the item a record declares
+fn declared(obj: &Json) -> ~str {
+    let path = field(obj, ~"path");
+    let name = field(obj, ~"name");
+    if path.len() == 0 { name } else { path + ~"::" + name }
+}
+
+// record builds a record for the data out of its fields. this is used by
+// the loaders for other sources, which produce records of the same form.
+pub fn record(fields: ~[(~str, Json)]) -> Json {
+    let mut obj = send_map::linear::LinearMap();
+    for fields.each |f| {
+        let (k, v) = copy *f;
+        obj.insert(k, v);
+    }
+    Object(~obj)
+}
+
+fn str_cast(j: Json) -> ~str {
+    match j { String(s) => copy s,
+              _ => fail ~"non-string" }
+}
+
+fn list_cast(j: Json) -> ~[Json] {
+    match j { List(l) => copy l,
+              _ => fail ~"non-list" }
+}
+
+// field gets a string field out of an object
+fn field(obj: &Json, name: ~str) -> ~str {
+    match *obj {
+        Object(object) => str_cast(object.get(&name)),
+        _ => fail ~"non-object"
+    }
+}
+
+// crate_of gives the crate a record is from. records that don't say are
+// from the crate their path starts with.
+fn crate_of(obj: &Json, path: &~str) -> ~str {
+    match *obj {
+        Object(object) => {
+            match object.find(&~"crate") {
+                Some(String(c)) => copy c,
+                _ => copy str::split_str(*path, "::")[0]
+            }
+        }
+        _ => copy str::split_str(*path, "::")[0]
+    }
+}
+
+// kind gives the kind of record an object in the data is. data from before
+// there were other kinds of records only has functions (and methods).
+fn kind(obj: &Json) -> ~str {
+    match *obj {
+        Object(object) => {
+            match object.find(&~"kind") {
+                Some(String(k)) => copy k,
+                _ => ~"fn"
+            }
+        }
+        _ => ~"fn"
+    }
+}
+
+// load_impl records that a type implements a trait, under the type's full
+// path if it is one of the types in the data. if the trait isn't declared
+// in the data, the methods of the impl are loaded as methods on the type
+// itself, as there is no declaration to find them through.
+fn load_impl(obj: &Json, impls: &Impls, traits: &HashMap<~str,()>,
+             types: &Types, aliases: &Aliases) -> ~[@Definition] {
+    match *obj {
+        Object(object) => {
+            let tr = last_segment(&str_cast(object.get(&~"trait")));
+            let for_ty = str_cast(object.get(&~"for"));
+            let path = match object.find(&~"path") {
+                Some(String(p)) => copy p,
+                _ => ~""
+            };
+            let ty = match parse_arg(&for_ty) {
+                Ok(ty) => ty,
+                Err(e) => {
+                    skip(&fmt!("impl %s for %s", tr, for_ty), &for_ty, &e);
+                    return ~[];
                 }
-                _ => {
-                    io::println("json not correctly formatted");
-                    libc::exit(1);
-                    fail;
+            };
+            match head_name(paths::canonical(ty, &path, types, aliases)) {
+                Some(n) => add_impl(impls, copy tr, n),
+                // blanket impls (for T) would need bounds to be useful
+                None => {}
+            }
+            match object.find(&~"methods") {
+                Some(List(ms)) if !traits.contains_key(copy tr) => {
+                    let krate = crate_of(obj, &path);
+                    vec::filter_map(ms, |m| {
+                        load_method(m, &path, &krate, &tr, ty)
+                    })
                 }
+                _ => ~[]
             }
         }
-        Err(err) => {
-            io::println(fmt!("parsing error in data on line %u, col %u",
-                             err.line, err.col));
+        _ => {
+            io::println("json impls must be objects");
             libc::exit(1);
             fail;
         }
     }
 }
 
-// load_obj loads a single object into a set of Definitions, or fails if the json
-// is not well formed
-fn load_obj(obj: &Json) -> ~[(@Definition, bool)] {
-    fn str_cast(j: Json) -> ~str {
-        match j { String(s) => copy s,
-                  _ => fail ~"non-string" }
+// load_alias records a type alias, which is given by its declaration (type
+// Result<T> = Result<T, Error>) and the module it is in. aliases that can't
+// be parsed are skipped.
+fn load_alias(obj: &Json, aliases: &Aliases) {
+    match *obj {
+        Object(object) => {
+            let decl = str_cast(object.get(&~"type"));
+            let path = str_cast(object.get(&~"path"));
+            match alias::parse_alias(&decl, &path) {
+                Ok(a) => alias::add_alias(aliases, a),
+                Err(e) => skip(&str_cast(object.get(&~"name")), &decl, &e)
+            }
+        }
+        _ => {
+            io::println("json type aliases must be objects");
+            libc::exit(1);
+            fail;
+        }
+    }
+}
+
+// load_trait loads the methods that a trait declares. their self type is a
+// variable constrained by the trait, so they will match any type that
+// implements it.
+fn load_trait(obj: &Json) -> ~[@Definition] {
+    match *obj {
+        Object(object) => {
+            let name = str_cast(object.get(&~"name"));
+            let path = str_cast(object.get(&~"path"));
+            let krate = crate_of(obj, &path);
+            let self = @Constrained(~"Self", ~[Constraint(copy name)]);
+            vec::filter_map(list_cast(object.get(&~"methods")), |m| {
+                load_method(m, &path, &krate, &name, self)
+            })
+        }
+        _ => {
+            io::println("json traits must be objects");
+            libc::exit(1);
+            fail;
+        }
+    }
+}
+
+// load_method loads a method of a trait or of a trait impl. Self in the
+// signature stands for the type the method is on, which is also the first
+// argument, unless the method is static. methods whose signatures can't be
+// parsed are skipped.
+fn load_method(m: &Json, path: &~str, krate: &~str, tr: &~str, self: @Arg)
+        -> Option<@Definition> {
+    match *m {
+        Object(object) => {
+            let ty = str_cast(object.get(&~"type"));
+            let is_static = str::starts_with(ty, "static ");
+            let sig = if is_static { str::slice(ty, 7, ty.len()) }
+                      else { copy ty };
+            let name = str_cast(object.get(&~"name"));
+            // the receiver is parsed in terms of Self, like the rest of
+            // the signature, so that &mut self is kept as &mut Self
+            let self_name = if is_static { None } else { Some(~"Self") };
+            let (args, rv, _l) = match parse_signature(copy sig, self_name,
+                                                       false) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    skip(&name, &sig, &e);
+                    return None;
+                }
+            };
+            // the type an impl is for is written with the impl's generics,
+            // which are declared in the signatures of its methods
+            let (bounds, _s) = split_bounds(&sig);
+            let self = generalize(self, &bounds);
+            let self_ty = @Basic(~"Self");
+            let args = vec::map(args, |a| { replace_arg(*a, self_ty, self) });
+            let rv = replace_arg(rv, self_ty, self);
+            let (args, rv, _l) = canonicalize_args(args, rv);
+            Some(@Definition { name: name,
+                               path: copy *path,
+                               anchor: str_cast(object.get(&~"anchor")),
+                               desc: str_cast(object.get(&~"desc")),
+                               args: args,
+                               ret: rv,
+                               signature: ty,
+                               trait_name: Some(copy *tr),
+                               crate_name: copy *krate,
+                               receiver: !is_static })
+        }
+        _ => {
+            io::println("json methods must be objects");
+            libc::exit(1);
+            fail;
+        }
+    }
+}
+
+// add_impl adds a type to the implementations of a trait
+pub fn add_impl(impls: &Impls, tr: ~str, ty: ~str) {
+    let mut tys = option::get_default(&impls.find(copy tr), ~[]);
+    if !vec::contains(tys, &ty) {
+        tys.push(ty);
     }
+    impls.insert(tr, tys);
+}
+
+// last_segment drops the module path from a name (ie, cmp::Ord is Ord),
+// as constraints in signatures are almost always written unqualified
+fn last_segment(s: &~str) -> ~str {
+    let segs = str::split_str(*s, "::");
+    return str::trim(segs[segs.len()-1]);
+}
+
+// skip says that a definition is being left out of the data, because its
+// signature couldn't be parsed. it goes to stderr, so that it doesn't end
+// up in the middle of --json output.
+fn skip(name: &~str, sig: &~str, e: &ParseError) {
+    io::stderr().write_line(fmt!("skipping %s, as its signature can't be \
+                                  parsed:\n%s", *name, e.show(sig)));
+}
+
+// load_obj loads a single object into a set of Definitions, or fails if the json
+// is not well formed. definitions with signatures that can't be parsed are
+// left out.
+fn load_obj(obj: &Json) -> ~[@Definition] {
     let mut definitions;
     match *obj {
         Object(object) => {
@@ -54,38 +329,28 @@ fn load_obj(obj: &Json) -> ~[(@Definition, bool)] {
             let self = match str_cast(object.get(&~"self")) {
                 ~"" => None, s => Some(copy s)
             };
-            let (args, rv, l) = parse_signature(copy ty,self,true);
-            let canonical =
-                @Definition { name: str_cast(object.get(&~"name")),
-                              path: str_cast(object.get(&~"path")),
-                              anchor: str_cast(object.get(&~"anchor")),
-                              desc: str_cast(object.get(&~"desc")),
-                              args: args,
-                              ret: rv,
-                              signature: ty };
-            definitions = ~[(canonical,true)];
-            if l > 1 {
-                // generate variants. for now, we just generate one where
-                // all the type variables are the same. the general case has
-                // exponential variations, and furthermore this type of
-                // solution wouldn't make sense. This should cover most
-                // of the cases without getting too crazy.
-                let mut n = 1;
-                let mut vargs = copy args;
-                let mut ret = copy rv;
-                while n < l {
-                    let zl = @Constrained(*letters(0), ~[]);
-                    let nl = @Constrained(*letters(n), ~[]);
-                    vargs = vec::map(vargs, |a| {
-                        replace_arg(*a, nl, zl)
-                    });
-                    ret = replace_arg(ret, nl, zl);
-                    n += 1;
+            let name = str_cast(object.get(&~"name"));
+            // only methods that take self are given a self type
+            let receiver = self.is_some();
+            let (args, rv, _l) = match parse_signature(copy ty,self,true) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    skip(&name, &ty, &e);
+                    return ~[];
                 }
-                definitions.push((@Definition {args: vargs,
-                                               ret: ret,
-                                             ..*canonical}, false));
-            }
+            };
+            let path = str_cast(object.get(&~"path"));
+            definitions =
+                ~[@Definition { name: name,
+                                crate_name: crate_of(obj, &path),
+                                path: path,
+                                anchor: str_cast(object.get(&~"anchor")),
+                                desc: str_cast(object.get(&~"desc")),
+                                args: args,
+                                ret: rv,
+                                signature: ty,
+                                trait_name: None,
+                                receiver: receiver }];
         }
         _ => {
             io::println("json definitions must be objects");
@@ -98,10 +363,12 @@ fn load_obj(obj: &Json) -> ~[(@Definition, bool)] {
 
 // bucket_sort takes definitions and builds the Data structure, by putting
 // them into the appropriate buckets
-fn bucket_sort(ds: ~[(@Definition, bool)]) -> Data {
-    let mut data = empty_data();
-    for vec::each(ds) |dc| {
-        let (d, canonical) = *dc;
+fn bucket_sort(ds: ~[@Definition], impls: Impls, aliases: Aliases,
+               types: Types) -> Data {
+    let mut data = Data { impls: impls, aliases: aliases, types: types,
+                          ..empty_data() };
+    for vec::each(ds) |d| {
+        let d = *d;
         match vec::len(d.args) {
             0 => bucket_drop(&mut data.ar0, d),
             1 => bucket_drop(&mut data.ar1, d),
@@ -111,9 +378,9 @@ fn bucket_sort(ds: ~[(@Definition, bool)]) -> Data {
             5 => bucket_drop(&mut data.ar5, d),
             _ => bucket_drop(&mut data.arn, d)
         }
-        if canonical {
-            let mut name = copy d.name;
-            add_name(data.names, &mut name, d);
+        names::add_name(&data.names, d);
+        if !data.modules.contains_key(copy d.path) {
+            data.modules.insert(copy d.path, data.modules.size());
         }
     }
     return data;
@@ -125,30 +392,170 @@ fn bucket_drop(b: &mut Bucket, d: @Definition) {
     b.defs.push(d);
 }
 
-// add_name adds a definition to the trie, for prefix searching
-fn add_name(t: @Trie, n: &mut ~str, d: @Definition) {
-    if n.len() == 0 {
-        // found where to put it
-        t.defs.push(d);
-    } else {
-        // move down a level
-        let c = str::from_char(str::shift_char(n));
-        let mut v;
-        match t.children.find(copy c) {
-            None => {
-                // add a new branch
-                v = @Trie { children: HashMap(), defs: ~[] };
-                t.children.insert(c, v);
-            }
-            Some(child) => {
-                v = child;
-            }
-        }
-        add_name(v, n, d);
-    }
-}
-
 #[cfg(test)]
 mod tests {
 
+    #[test]
+    fn test_load_impl() {
+        let impls = HashMap();
+        let obj = json::from_str(
+            ~"{\"kind\": \"impl\", \"trait\": \"cmp::Ord\", \"for\": \"~str\"}");
+        assert kind(&obj.get()) == ~"impl";
+        assert load_impl(&obj.get(), &impls, &HashMap(), &HashMap(),
+                         &HashMap()) == ~[];
+        assert impls.get(~"Ord") == ~[~"str"];
+    }
+
+    #[test]
+    fn test_load_impl_paths() {
+        // impls for types with the same name are kept apart by their paths
+        let recs = vec::map(~[
+            ~"{\"kind\": \"struct\", \"name\": \"Error\", \
+               \"path\": \"std::io\"}",
+            ~"{\"kind\": \"struct\", \"name\": \"Error\", \
+               \"path\": \"std::fmt\"}",
+            ~"{\"kind\": \"impl\", \"trait\": \"Hash\", \
+               \"for\": \"Error\", \"path\": \"std::io\"}",
+            ~"{\"kind\": \"impl\", \"trait\": \"Ord\", \
+               \"for\": \"fmt::Error\", \"path\": \"std::io\"}",
+            ~"{\"kind\": \"impl\", \"trait\": \"Ord\", \
+               \"for\": \"Other\", \"path\": \"std::io\"}"],
+            |r| { json::from_str(copy *r).get() });
+        let data = from_records(&recs);
+        assert data.impls.get(~"Hash") == ~[~"std::io::Error"];
+        assert data.impls.get(~"Ord") == ~[~"std::fmt::Error", ~"Other"];
+        let io = @Basic(~"std::io::Error");
+        assert unify::satisfies(io, &Constraint(~"Hash"), &data.impls);
+        assert !unify::satisfies(io, &Constraint(~"Ord"), &data.impls);
+        assert unify::satisfies(@Basic(~"std::fmt::Error"),
+                                &Constraint(~"Ord"), &data.impls);
+    }
+
+    #[test]
+    fn test_load_alias() {
+        let aliases = HashMap();
+        let obj = json::from_str(
+            ~"{\"kind\": \"type\", \"name\": \"Result\", \
+               \"path\": \"std::io\", \
+               \"type\": \"type Result<T> = Result<T, Error>\"}");
+        assert kind(&obj.get()) == ~"type";
+        load_alias(&obj.get(), &aliases);
+        let als = aliases.get(~"Result");
+        assert als.len() == 1;
+        assert als[0].path == ~"std::io";
+        assert als[0].params == ~[~"T"];
+    }
+
+    #[test]
+    fn test_from_records_paths() {
+        let recs = ~[json::from_str(
+            ~"{\"kind\": \"struct\", \"name\": \"Error\", \
+               \"path\": \"std::io\"}").get(),
+                     json::from_str(
+            ~"{\"kind\": \"enum\", \"name\": \"Error\", \
+               \"path\": \"std::fmt\"}").get(),
+                     json::from_str(
+            ~"{\"name\": \"kind\", \"path\": \"std::io\", \
+               \"anchor\": \"\", \"desc\": \"\", \"self\": \"\", \
+               \"type\": \"fn kind(e: &Error) -> int\"}").get()];
+        let data = from_records(&recs);
+        assert data.types.get(~"Error") ==
+            ~[~"std::io::Error", ~"std::fmt::Error"];
+        // the Error in std::io is that one
+        assert data.ar1.defs[0].args ==
+            ~[@Ptr(Ref(None), @Basic(~"std::io::Error"))];
+    }
+
+    #[test]
+    fn test_load_trait() {
+        let obj = json::from_str(
+            ~"{\"kind\": \"trait\", \"name\": \"ToStr\", \
+               \"path\": \"core::to_str\", \"methods\": [\
+               {\"name\": \"to_str\", \"type\": \"fn to_str() -> ~str\", \
+                \"anchor\": \"method-to_str\", \"desc\": \"\"}]}");
+        let defs = load_trait(&obj.get());
+        assert defs.len() == 1;
+        assert defs[0].trait_name == Some(~"ToStr");
+        assert defs[0].args ==
+            ~[@Constrained(~"A", ~[Constraint(~"ToStr")])];
+        assert defs[0].ret == @Ptr(Owned, @Basic(~"str"));
+        assert defs[0].receiver;
+    }
+
+    #[test]
+    fn test_load_impl_methods() {
+        let obj = json::from_str(
+            ~"{\"kind\": \"impl\", \"trait\": \"Eq\", \"for\": \"int\", \
+               \"path\": \"core::int\", \"methods\": [\
+               {\"name\": \"eq\", \"type\": \"fn eq(other: &Self) -> bool\", \
+                \"anchor\": \"method-eq\", \"desc\": \"\"}, \
+               {\"name\": \"set\", \"type\": \"fn set(&mut self, x: int)\", \
+                \"anchor\": \"method-set\", \"desc\": \"\"}]}");
+        let defs = load_impl(&obj.get(), &HashMap(), &HashMap(), &HashMap(),
+                             &HashMap());
+        let n = @Basic(~"int");
+        assert defs[0].args == ~[n, @Ptr(Ref(None), n)];
+        // an explicit receiver keeps how it is taken
+        assert defs[1].args == ~[@Ptr(RefMut(None), n), n];
+        assert defs[1].receiver;
+        // if the trait is declared, the methods come from there instead
+        let traits = HashMap();
+        traits.insert(~"Eq", ());
+        assert load_impl(&obj.get(), &HashMap(), &traits, &HashMap(),
+                         &HashMap()) == ~[];
+        // the impl's generics are declared in its methods' signatures
+        let generic = json::from_str(
+            ~"{\"kind\": \"impl\", \"trait\": \"Push\", \
+               \"for\": \"Vec<T>\", \"path\": \"core::vec\", \
+               \"methods\": [{\"name\": \"push\", \
+                \"type\": \"fn push<T>(&mut self, x: T)\", \
+                \"anchor\": \"method-push\", \"desc\": \"\"}]}");
+        let a = @Constrained(~"A", ~[]);
+        assert load_impl(&generic.get(), &HashMap(), &HashMap(), &HashMap(),
+                         &HashMap())[0].args ==
+            ~[@Ptr(RefMut(None), @Parametric(@Basic(~"Vec"), ~[a])), a];
+    }
+
+    #[test]
+    fn test_load_obj_impl_generics() {
+        // methods in rustle.data never declare their impl's generics
+        let recs = ~[json::from_str(
+            ~"{\"name\": \"get\", \"path\": \"core::option\", \
+               \"anchor\": \"method-get\", \"desc\": \"\", \
+               \"self\": \"Option<T>\", \
+               \"type\": \"fn get() -> T\"}").get()];
+        let data = from_records(&recs);
+        let t = @Constrained(~"A", ~[]);
+        assert data.ar1.defs[0].args ==
+            ~[@Parametric(@Basic(~"Option"), ~[t])];
+        assert data.ar1.defs[0].ret == t;
+        let (_kind, ms) = query::search(&~"Option<int> -> int", &data,
+                                        &default_opts()).get();
+        assert ms.len() == 1;
+        assert ms[0].def.name == ~"get";
+    }
+
+    #[test]
+    fn test_load_obj_unparseable() {
+        let obj = json::from_str(
+            ~"{\"name\": \"foo\", \"path\": \"core::foo\", \
+               \"anchor\": \"function-foo\", \"desc\": \"\", \
+               \"self\": \"\", \"type\": \"fn foo(x: [int) -> int\"}");
+        assert load_obj(&obj.get()) == ~[];
+    }
+
+    #[test]
+    fn test_crate_of() {
+        let obj = json::from_str(~"{\"path\": \"std::map\"}").get();
+        assert crate_of(&obj, &~"std::map") == ~"std";
+        let obj2 = json::from_str(
+            ~"{\"path\": \"map\", \"crate\": \"std\"}").get();
+        assert crate_of(&obj2, &~"map") == ~"std";
+    }
+
+    #[test]
+    fn test_kind() {
+        let obj = json::from_str(~"{\"name\": \"foo\"}");
+        assert kind(&obj.get()) == ~"fn";
+    }
 }
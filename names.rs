@@ -0,0 +1,205 @@
+//! This file contains the name index, and the ways a name query can match a
+//! name: exactly, as a prefix, by the segments of the name, anywhere in it,
+//! or with a few typos
+
+use types::*;
+
+// A NameMatch is how well a query matched a name, from best to worst.
+// Typos gives how many edits the name was from the query.
+enum NameMatch { Exact, Prefix, Segments, Substring, Typos(uint) }
+
+impl NameMatch : Eq {
+    pure fn eq(other: &NameMatch) -> bool {
+        match (self, *other) {
+            (Exact, Exact) | (Prefix, Prefix) | (Segments, Segments) |
+            (Substring, Substring) => true,
+            (Typos(n1), Typos(n2)) => n1 == n2,
+            _ => false
+        }
+    }
+    pure fn ne(other: &NameMatch) -> bool {
+        !self.eq(other)
+    }
+}
+
+// how_named names a kind of name match, for saying how a result matched
+pub fn how_named(m: NameMatch) -> ~str {
+    match m {
+        Exact => ~"exact",
+        Prefix => ~"prefix",
+        Segments => ~"segments",
+        Substring => ~"substring",
+        Typos(n) => fmt!("typos:%u", n)
+    }
+}
+
+// add_name adds a definition to the index, under its name
+pub fn add_name(names: &NameIndex, d: @Definition) {
+    let mut ds = option::get_default(&names.find(copy d.name), ~[]);
+    ds.push(d);
+    names.insert(copy d.name, ds);
+}
+
+// match_name says how a query matches a name, if it does at all. apart
+// from an exact match, case is ignored.
+pub fn match_name(q: &~str, name: &~str) -> Option<NameMatch> {
+    if *q == *name {
+        return Some(Exact);
+    }
+    let lq = str::to_lower(*q);
+    let ln = str::to_lower(*name);
+    if str::starts_with(ln, lq) {
+        return Some(Prefix);
+    }
+    // underscores in the query are just where it was split already
+    let bare = str::replace(lq, ~"_", ~"");
+    if bare.len() > 0 && matches_segments(bare, segments(name)) {
+        return Some(Segments);
+    }
+    if str::contains(ln, lq) {
+        return Some(Substring);
+    }
+    // short queries are a few edits from too many names to be useful. a
+    // typo in the start of a longer name is caught by comparing against
+    // just the start of it.
+    let allowed = lq.len() / 4;
+    if allowed == 0 {
+        return None;
+    }
+    let edits = uint::min(edit_distance(lq, ln),
+                          edit_distance(lq, str::slice(ln, 0,
+                              uint::min(lq.len(), ln.len()))));
+    if edits <= allowed { Some(Typos(edits)) } else { None }
+}
+
+// segments splits a name at underscores and where camel case goes from
+// lower to upper case, lowercasing each segment: each_char is each and
+// char, and HashMap is hash and map
+pub fn segments(name: &~str) -> ~[~str] {
+    let mut segs = ~[];
+    let mut cur = ~"";
+    let mut lower = false;
+    for str::each_char(*name) |c| {
+        if c == '_' || (lower && char::is_uppercase(c)) {
+            if cur.len() > 0 {
+                segs.push(cur);
+            }
+            cur = ~"";
+        }
+        if c != '_' {
+            str::push_char(&mut cur, c);
+        }
+        lower = char::is_lowercase(c) || char::is_digit(c);
+    }
+    if cur.len() > 0 {
+        segs.push(cur);
+    }
+    return vec::map(segs, |s| { str::to_lower(*s) });
+}
+
+// matches_segments says whether a query is made of the starts of
+// consecutive segments, beginning at any of them (ec is each_char, and so
+// is ch)
+fn matches_segments(q: &str, segs: &[~str]) -> bool {
+    // covers says whether the query can be taken from the starts of the
+    // segments from i on
+    fn covers(q: &str, segs: &[~str], i: uint) -> bool {
+        if q.len() == 0 {
+            return true;
+        }
+        if i >= segs.len() {
+            return false;
+        }
+        // take as much from this segment as possible, then back off
+        let mut n = uint::min(q.len(), segs[i].len());
+        while n > 0 {
+            if str::slice(q, 0, n) == str::slice(segs[i], 0, n) &&
+                covers(str::slice(q, n, q.len()), segs, i + 1) {
+                return true;
+            }
+            n -= 1;
+        }
+        return false;
+    }
+    let mut i = 0;
+    while i < segs.len() {
+        if covers(q, segs, i) {
+            return true;
+        }
+        i += 1;
+    }
+    return false;
+}
+
+// edit_distance counts the insertions, deletions, substitutions and swaps
+// of neighbouring characters it takes to turn one string into the other
+pub fn edit_distance(a: &str, b: &str) -> uint {
+    let a = str::chars(a);
+    let b = str::chars(b);
+    let w = b.len() + 1;
+    // d[i * w + j] is the distance between the first i characters of a and
+    // the first j of b
+    let mut d = vec::from_elem((a.len() + 1) * w, 0u);
+    let mut i = 0;
+    while i <= a.len() {
+        let mut j = 0;
+        while j <= b.len() {
+            d[i * w + j] = if i == 0 { j } else if j == 0 { i } else {
+                let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+                let mut best = uint::min(d[(i - 1) * w + j] + 1,
+                                         uint::min(d[i * w + j - 1] + 1,
+                                                   d[(i - 1) * w + j - 1] +
+                                                   cost));
+                if i > 1 && j > 1 && a[i - 1] == b[j - 2] &&
+                    a[i - 2] == b[j - 1] {
+                    best = uint::min(best, d[(i - 2) * w + j - 2] + 1);
+                }
+                best
+            };
+            j += 1;
+        }
+        i += 1;
+    }
+    return d[a.len() * w + b.len()];
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn test_segments() {
+        assert segments(&~"each_char") == ~[~"each", ~"char"];
+        assert segments(&~"HashMap") == ~[~"hash", ~"map"];
+        assert segments(&~"to_str") == ~[~"to", ~"str"];
+        assert segments(&~"_private__name") == ~[~"private", ~"name"];
+        assert segments(&~"from_utf8Bytes") == ~[~"from", ~"utf8", ~"bytes"];
+    }
+
+    #[test]
+    fn test_match_name() {
+        assert match_name(&~"each", &~"each") == Some(Exact);
+        assert match_name(&~"each", &~"each_char") == Some(Prefix);
+        assert match_name(&~"Each", &~"each_char") == Some(Prefix);
+        assert match_name(&~"ec", &~"each_char") == Some(Segments);
+        assert match_name(&~"eachc", &~"each_char") == Some(Segments);
+        assert match_name(&~"char", &~"each_char") == Some(Segments);
+        assert match_name(&~"hm", &~"HashMap") == Some(Segments);
+        assert match_name(&~"ach", &~"each_char") == Some(Substring);
+        assert match_name(&~"fidn", &~"find") == Some(Typos(1));
+        assert match_name(&~"fidn", &~"find_str") == Some(Typos(1));
+        assert match_name(&~"eahc_char", &~"each_char") == Some(Typos(1));
+        assert match_name(&~"ecah", &~"map") == None;
+        // there are no typos in queries this short
+        assert match_name(&~"fnd", &~"find") == None;
+        assert how_named(Typos(2)) == ~"typos:2";
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert edit_distance("", "") == 0;
+        assert edit_distance("abc", "") == 3;
+        assert edit_distance("kitten", "sitting") == 3;
+        assert edit_distance("each", "eahc") == 1;
+        assert edit_distance("map", "map") == 0;
+    }
+}
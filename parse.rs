@@ -28,103 +28,1005 @@ pub fn split_arguments(a: &~str) -> ~[~str] {
 }
 
 // parse_signature takes a string of a function and returns a list of the
-// argument types, and the return type
-fn parse_signature(arg_list: ~str, self: Option<~str>, canonicalize: bool)
-        -> (~[@Arg], @Arg, uint) {
-    let self_list = match self {
+// argument types, and the return type. any bounds on type variables (in
+// the generics list or a where clause) are attached to them as constraints.
+// if the signature can't be parsed, the error says where the problem is.
+fn parse_signature(sig: ~str, self: Option<~str>, canonicalize: bool)
+        -> Result<(~[@Arg], @Arg, uint), ParseError> {
+    match check_brackets(&sig) {
+        Some(e) => return Err(e),
+        None => {}
+    }
+    let (bounds, arg_list) = split_bounds(&sig);
+    let self_arg = match self {
+        None => None,
+        Some(s) => {
+            match parse_arg(&s) {
+                Ok(a) => Some(a),
+                // the self type isn't part of the signature, so there is
+                // nowhere in it to point to
+                Err(e) => return Err(ParseError { pos: 0, ..e })
+            }
+        }
+    };
+    let bounds = match self_arg {
+        Some(sa) => vec::append(copy bounds, impl_generics(sa, &bounds)),
+        None => bounds
+    };
+    // the return type can itself have arrows in it (fn() -> fn() -> int),
+    // so the first one outside of any brackets is the one that separates it
+    let (arg_part, ret) = match find_arrow(&arg_list) {
+        Some(i) => {
+            let r = str::trim(str::slice(arg_list, i + 2, arg_list.len()));
+            match parse_arg(&r) {
+                Ok(a) => (str::slice(arg_list, 0, i), generalize(a, &bounds)),
+                Err(e) => return Err(relocate(e, &sig, &r))
+            }
+        }
+        None => (copy arg_list, @Basic(~"()"))
+    };
+    let arg_str = trim_parens(arg_part);
+    let mut args = ~[];
+    let mut receiver = None;
+    if str::len(arg_str) > 0 {
+        for split_arguments(&arg_str).each |a| {
+            // .. stands for any other arguments, in a query (see has_rest)
+            if *a == ~".." {
+                loop;
+            }
+            // an explicit self argument is the receiver. its type is given
+            // to us separately (as the self type), but how it is taken
+            // (&self, &mut self, ...) is only in the signature.
+            if is_receiver(a) {
+                if self_arg.is_some() {
+                    let ty = receiver_type(a);
+                    match parse_arg(&ty) {
+                        Ok(r) => receiver = Some(r),
+                        Err(e) => return Err(relocate(e, &sig, &ty))
+                    }
+                }
+                loop;
+            }
+            let ty = arg_type(a);
+            match parse_arg(&ty) {
+                Ok(arg) => args.push(generalize(arg, &bounds)),
+                Err(e) => return Err(relocate(e, &sig, &ty))
+            }
+        }
+    }
+    let self_list = match self_arg {
         None => ~[],
-        Some(s) => ~[parse_arg(&trim_sigils(s))]
+        Some(sa) => {
+            let r = match receiver {
+                Some(r) => replace_arg(r, @Basic(~"Self"), sa),
+                None => sa
+            };
+            ~[generalize(r, &bounds)]
+        }
     };
-    let args_ret = str::split_str(arg_list, "->");
-    let mut arlen = vec::len(args_ret);
-    let ret;
-    if vec::len(args_ret) == 1 {
-        arlen += 1;
-        ret = @Basic(~"()");
+    if canonicalize {
+        return Ok(canonicalize_args(vec::append(self_list,args), ret));
     } else {
-        ret = parse_arg(&str::trim(args_ret[arlen-1]));
+        return Ok((vec::append(self_list,args), ret, 0));
     }
-    let arg_str =
-        trim_parens(str::connect(vec::view(args_ret, 0, arlen-1), "->"));
-    let args;
-    if str::len(arg_str) == 0 {
-        args = ~[];
-    } else {
-        let arg_strs = vec::map(split_arguments(&arg_str), |a| {
-            let t = str::splitn_char(*a, ':', 1);
-            if t.len() > 1 {
-                copy t[1]
-            } else {
-                copy t[0]
+
+}
+
+// parse_query parses a query, which is a signature that single capital
+// letters are type variables in without being declared - a shorthand, so
+// that Option<A> -> A can be written for <A> Option<A> -> A. definitions
+// only have the variables they declare (see declare).
+pub fn parse_query(q: ~str) -> Result<(~[@Arg], @Arg, uint), ParseError> {
+    fn shorthand(a: @Arg) -> @Arg {
+        map_leaves(a, |l| {
+            match *l {
+                Basic(ref n) if n.len() == 1 &&
+                    char::is_uppercase(n.char_at(0)) => {
+                    @Constrained(copy *n, ~[])
+                }
+                _ => l
             }
-        });
-        args = vec::map(arg_strs, |x| {
-                parse_arg(&trim_sigils(*x))
-            } );
+        })
     }
-    if canonicalize {
-        return canonicalize_args(vec::append(self_list,args), ret);
-    } else {
-        return (vec::append(self_list,args), ret, 0);
+    match parse_signature(q, None, false) {
+        Ok((args, ret, _l)) => {
+            Ok(canonicalize_args(vec::map(args, |a| { shorthand(*a) }),
+                                 shorthand(ret)))
+        }
+        Err(e) => Err(e)
+    }
+}
+
+// check_brackets makes sure that all the brackets in a signature are
+// closed, and closed by the right thing, which is the most common mistake
+// in a query. arrows (->) aren't brackets.
+pub fn check_brackets(s: &~str) -> Option<ParseError> {
+    let mut open = ~[];
+    for str::each_chari(*s) |i, c| {
+        match c {
+            '(' | '[' | '<' => open.push((i, c)),
+            ')' | ']' | '>' => {
+                if c == '>' && i > 0 && s.char_at(i-1) == '-' {
+                    loop;
+                }
+                let want = match c { ')' => '(', ']' => '[', _ => '<' };
+                if open.len() == 0 || open.last().second() != want {
+                    return Some(ParseError {
+                        pos: i, msg: fmt!("unmatched %c", c) });
+                }
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+    if open.len() > 0 {
+        let (i, c) = open.last();
+        return Some(ParseError { pos: i, msg: fmt!("unclosed %c", c) });
+    }
+    return None;
+}
+
+// relocate moves an error found in part of a string to where that part is
+// in the whole of it
+fn relocate(e: ParseError, outer: &~str, inner: &~str) -> ParseError {
+    match str::find_str(*outer, *inner) {
+        Some(i) => ParseError { pos: e.pos + i, ..e },
+        None => e
+    }
+}
+
+// is_receiver says whether an argument is self (&self, &'a mut self, etc)
+pub fn is_receiver(a: &~str) -> bool {
+    let words = str::words(str::splitn_char(*a, ':', 1)[0]);
+    words.len() > 0 && trim_sigils(words.last()) == ~"self"
+}
+
+// has_rest says whether the arguments of a query include .., meaning that
+// the definitions it matches can take any other arguments as well
+pub fn has_rest(sig: &~str) -> bool {
+    let (_bounds, s) = split_bounds(sig);
+    let arg_part = match find_arrow(&s) {
+        Some(i) => str::slice(s, 0, i),
+        None => copy s
+    };
+    vec::contains(split_arguments(&trim_parens(arg_part)), &~"..")
+}
+
+// arg_type gives the type of an argument (x: T), without the name (or
+// pattern) if it has one. the colons of a path (a::B) aren't a separator.
+fn arg_type(a: &~str) -> ~str {
+    for str::each_chari(*a) |i, c| {
+        if c == ':' && (i == 0 || a.char_at(i-1) != ':') &&
+            (i + 1 == a.len() || a.char_at(i+1) != ':') {
+            return str::trim(str::slice(*a, i + 1, a.len()));
+        }
+    }
+    return str::trim(*a);
+}
+
+// receiver_type gives the type of a receiver, in terms of Self: &mut self
+// is &mut Self, and self: ~Self is ~Self. a mut that is only on the
+// binding (mut self) doesn't change the type.
+fn receiver_type(a: &~str) -> ~str {
+    let parts = str::splitn_char(*a, ':', 1);
+    if parts.len() > 1 {
+        return str::trim(parts[1]);
+    }
+    let r = str::trim(parts[0]);
+    let prefix = str::trim(str::slice(r, 0, r.len() - 4));
+    if prefix == ~"mut" { ~"Self" } else { prefix + ~" Self" }
+}
+
+// split_bounds pulls the generics list (ie, <T: Copy Ord, U>) and any where
+// clause out of a signature or a query, returning the bounds that they
+// declare and what is left of the signature
+pub fn split_bounds(sig: &~str) -> (~[(~str, ~[Constraint])], ~str) {
+    let mut s = str::trim(*sig);
+    let mut bounds = ~[];
+    // a query can also declare its variables with forall, like
+    // forall K V. HashMap<K, V> -> V
+    if str::starts_with(s, "forall ") {
+        match str::find_char(s, '.') {
+            Some(dot) => {
+                let decl = str::slice(s, 7, dot);
+                bounds = if str::contains_char(decl, ':') ||
+                    str::contains_char(decl, ',') {
+                    parse_bounds(&decl)
+                } else {
+                    vec::map(str::words(decl), |w| { (copy *w, ~[]) })
+                };
+                s = str::trim(str::slice(s, dot + 1, s.len()));
+            }
+            None => {}
+        }
+    }
+    let mut rest = copy s;
+    // the generics come first in a query, or right after the name of a
+    // function, but either way before the argument list
+    let paren = option::get_default(&str::find_char(s, '('), str::len(s));
+    match str::find_char(s, '<') {
+        Some(open) if open < paren &&
+            (open == 0 || str::contains(str::slice(s, 0, open), "fn ")) &&
+            !is_qualified_path(&s, open) => {
+            let close = matching_angle(&s, open);
+            bounds.push_all_move(parse_bounds(&str::slice(s, open+1, close)));
+            rest = str::append(str::slice(s, 0, open),
+                               str::slice(s, close+1, str::len(s)));
+        }
+        _ => {}
+    }
+    match str::find_str(rest, " where ") {
+        Some(w) => {
+            bounds.push_all_move(
+                parse_bounds(&str::slice(rest, w+7, str::len(rest))));
+            rest = str::trim(str::slice(rest, 0, w));
+        }
+        None => {}
     }
+    return (bounds, rest);
+}
 
+// is_qualified_path says whether the < at open starts a qualified path
+// (<T as Trait>::Item), which a query can begin with, rather than a list of
+// generics
+fn is_qualified_path(s: &~str, open: uint) -> bool {
+    let close = matching_angle(s, open);
+    close < s.len() &&
+        str::starts_with(str::slice(*s, close + 1, s.len()), "::")
 }
 
-// parse_arg takes a string and turns it into an Arg
-pub fn parse_arg(su: &~str) -> @Arg {
-    let s = trim_sigils(*su);
-    if str::len(s) == 0 {
-        return @Basic(~"()");
-    }
-    let ps = str::splitn_char(str::trim(s), '<', 1);
-    if vec::len(ps) == 1 {
-        // non-parametrized type, see if it's a vec or a tuple
-        match str::char_at(s, 0) {
-            '[' => {
-                // if there is not a ']', we want to fail
-                let end = option::get(&str::rfind_char(s, ']'));
-                let mut vs = str::trim(str::slice(s, 1, end));
-                // we drop any modifiers: const, mut.
-                vs = drop_modifiers(&vs);
-                return @Vec(parse_arg(&vs));
-            }
-            // want tuples, but not unit
-            '(' if str::len(s) > 2 => {
-                // we want to fail if there is no matching paren
-                let end = option::get(&str::rfind_char(s, ')'));
-                let inn = str::trim(str::slice(s, 1, end));
-                let inner = vec::map(split_arguments(&inn), |a| { parse_arg(a) });
-                return @Tuple(inner);
-            }
-            // a function type
-            'f' if str::char_at(s,1) == 'n'
-                && !char::is_alphanumeric(str::char_at(s,2)) => {
-                let (args, ret, _n) = parse_signature(s, None, false);
-                return @Function(args, ret);
+// matching_angle finds the '>' that closes the '<' at open. arrows don't
+// count, and if it is never closed, the end of the string is used.
+pub fn matching_angle(s: &~str, open: uint) -> uint {
+    let mut level = 0;
+    for str::each_chari(*s) |i,c| {
+        if i < open { loop; }
+        match c {
+            '<' => level += 1,
+            '>' if s.char_at(i-1) != '-' => {
+                level -= 1;
+                if level == 0 { return i; }
+            }
+            _ => {}
+        }
+    }
+    return str::len(*s);
+}
+
+// parse_bounds parses a list of bounds, like "T: Copy Ord, U: Eq". both old
+// (space separated) and new (+ separated) style bounds are accepted.
+// lifetimes and ?Sized aren't traits, so they are dropped.
+pub fn parse_bounds(s: &~str) -> ~[(~str, ~[Constraint])] {
+    let mut bounds = ~[];
+    for split_arguments(s).each |g| {
+        let parts = str::splitn_char(*g, ':', 1);
+        let name = str::trim(parts[0]);
+        if str::len(name) == 0 || str::starts_with(name, "'") {
+            loop;
+        }
+        // const generic parameters are values rather than types, so what
+        // comes after their : is a type, not bounds
+        if str::starts_with(name, "const ") {
+            bounds.push((str::trim(str::slice(name, 6, name.len())), ~[]));
+            loop;
+        }
+        let mut cs = ~[];
+        if parts.len() > 1 {
+            for split_traits(&parts[1]).each |t| {
+                if !str::starts_with(*t, "'") && !str::starts_with(*t, "?") {
+                    // we only care which trait it is, not its parameters
+                    let tn = str::splitn_char(*t, '<', 1)[0];
+                    cs.push(Constraint(str::trim(tn)));
+                }
+            }
+        }
+        bounds.push((name, cs));
+    }
+    return bounds;
+}
+
+// split_traits splits the traits in a single bound on spaces or +s, except
+// where they are inside of type parameters (ie, Add<A, B>)
+fn split_traits(s: &~str) -> ~[~str] {
+    let mut traits = ~[];
+    let mut level = 0;
+    let mut cur = ~"";
+    for str::each_char(*s) |c| {
+        match c {
+            '+' | ' ' | '\t' | '\n' if level == 0 => {
+                if str::len(cur) > 0 { traits.push(copy cur); }
+                cur = ~"";
             }
             _ => {
-                if s.len() == 1 {
-                    // assume this is a constrained type without constraints.
-                    // note that this is to allow shorthand, so you don't need to write
-                    // <A:>Option<A> -> A
-                    // to make it fit the pattern
-                    // <A: Copy, B: Copy> Option<A> -> B
-                    return @Constrained(copy s, ~[]);
+                if c == '<' { level += 1; }
+                if c == '>' { level -= 1; }
+                str::push_char(&mut cur, c);
+            }
+        }
+    }
+    if str::len(cur) > 0 { traits.push(cur); }
+    return traits;
+}
+
+// impl_generics finds the generic parameters of the impl a method is in,
+// in its self type. older data never declares them (a method of Option<T>
+// is just fn get() -> T), so the single capital letters in the self type
+// that the method doesn't declare itself are taken to be them.
+fn impl_generics(self_arg: @Arg, bounds: &~[(~str, ~[Constraint])])
+        -> ~[(~str, ~[Constraint])] {
+    let mut found = ~[];
+    map_leaves(self_arg, |l| {
+        match *l {
+            Basic(ref n) if n.len() == 1 &&
+                char::is_uppercase(n.char_at(0)) &&
+                !vec::any(*bounds, |b| { b.first() == *n }) &&
+                !vec::any(found, |f| { f.first() == *n }) => {
+                found.push((copy *n, ~[]));
+            }
+            _ => {}
+        }
+        l
+    });
+    return found;
+}
+
+// generalize makes the generic parameters that a list of bounds declares
+// into type variables in a type, with their constraints
+fn generalize(a: @Arg, bounds: &~[(~str, ~[Constraint])]) -> @Arg {
+    let names = vec::map(*bounds, |b| { b.first() });
+    constrain(declare(a, &names), bounds)
+}
+
+// declare makes the names given into type variables. in a definition,
+// this is the only way a name (T, K, Item or T1) becomes one - queries
+// have a shorthand on top of it (see parse_query). associated types of
+// declared names (Rhs::Output) are found too.
+pub fn declare(a: @Arg, names: &~[~str]) -> @Arg {
+    if names.len() == 0 {
+        return a;
+    }
+    map_leaves(a, |l| {
+        match *l {
+            Basic(ref n) if vec::contains(*names, n) => {
+                @Constrained(copy *n, ~[])
+            }
+            Basic(ref n) => {
+                match str::find_str(*n, "::") {
+                    Some(i) if vec::contains(*names,
+                                             &str::slice(*n, 0, i)) => {
+                        @Projection(@Constrained(str::slice(*n, 0, i), ~[]),
+                                    None, str::slice(*n, i + 2, n.len()))
+                    }
+                    _ => l
+                }
+            }
+            _ => l
+        }
+    })
+}
+
+// constrain attaches the constraints from a list of bounds to the type
+// variables they name
+pub fn constrain(a: @Arg, bounds: &~[(~str, ~[Constraint])]) -> @Arg {
+    if bounds.len() == 0 {
+        return a;
+    }
+    map_constrained(a, |name, constraints| {
+        let mut cs = copy *constraints;
+        for bounds.each |b| {
+            let (n, bcs) = copy *b;
+            if n == *name {
+                cs.push_all(bcs);
+            }
+        }
+        @Constrained(copy *name, cs)
+    })
+}
+
+// parse_arg takes a string and turns it into an Arg. both the old forms of
+// types (~[T], @T, &a/T, fn&(A) -> B) and the modern ones (Box<T>, [T; N],
+// dyn Trait, impl Trait, Fn(A) -> B, !, <T as Trait>::Item) are understood.
+// the whole string has to be a type.
+pub fn parse_arg(s: &~str) -> Result<@Arg, ParseError> {
+    let p = TypeParser { s: copy *s, pos: 0 };
+    let a = match p.parse_type() {
+        Ok(a) => a,
+        Err(e) => return Err(e)
+    };
+    if p.peek() != '\0' {
+        return Err(p.error(~"unexpected " + str::slice(p.s, p.pos, p.s.len())));
+    }
+    return Ok(a);
+}
+
+// a TypeParser reads a type by recursive descent. pos is how far through s
+// it has got, which is also where any error it finds is.
+struct TypeParser { s: ~str, mut pos: uint }
+
+impl TypeParser {
+    fn error(msg: ~str) -> ParseError {
+        ParseError { pos: self.pos, msg: msg }
+    }
+
+    fn bump() {
+        self.pos = str::char_range_at(self.s, self.pos).next;
+    }
+
+    // peek gives the next character that isn't whitespace, without moving
+    // past it ('\0' at the end)
+    fn peek() -> char {
+        while self.pos < self.s.len() &&
+            char::is_whitespace(self.s.char_at(self.pos)) {
+            self.bump();
+        }
+        if self.pos < self.s.len() { self.s.char_at(self.pos) } else { '\0' }
+    }
+
+    fn looking_at(t: &str) -> bool {
+        self.peek();
+        str::starts_with(str::slice(self.s, self.pos, self.s.len()), t)
+    }
+
+    // looking_at_word is looking_at, but only for all of a word (fn, but not
+    // the start of fnord)
+    fn looking_at_word(w: &str) -> bool {
+        self.peek();
+        let end = self.pos + w.len();
+        self.looking_at(w) &&
+            (end >= self.s.len() || !is_ident_char(self.s.char_at(end)))
+    }
+
+    fn eat(t: &str) -> bool {
+        if !self.looking_at(t) {
+            return false;
+        }
+        self.pos += t.len();
+        return true;
+    }
+
+    fn eat_word(w: &str) -> bool {
+        if !self.looking_at_word(w) {
+            return false;
+        }
+        self.pos += w.len();
+        return true;
+    }
+
+    // unclosed is the error for the bracket at open never being closed
+    fn unclosed(open: uint) -> ParseError {
+        ParseError { pos: open, msg: fmt!("unclosed %c", self.s.char_at(open)) }
+    }
+
+    // expect_close moves past the bracket c that closes the one at open
+    fn expect_close(c: char, open: uint) -> Result<(), ParseError> {
+        match self.peek() {
+            d if d == c => {
+                self.bump();
+                Ok(())
+            }
+            '\0' => Err(self.unclosed(open)),
+            _ => Err(self.error(fmt!("expected %c", c)))
+        }
+    }
+
+    // ident reads a name, which is empty if there isn't one next
+    fn ident() -> ~str {
+        self.peek();
+        let start = self.pos;
+        while self.pos < self.s.len() &&
+            is_ident_char(self.s.char_at(self.pos)) {
+            self.bump();
+        }
+        return str::slice(self.s, start, self.pos);
+    }
+
+    // path reads a name with any path in front of it, like std::str::Chars
+    fn path() -> ~str {
+        let mut p = self.ident();
+        while p.len() > 0 && self.looking_at("::") {
+            let before = self.pos;
+            self.pos += 2;
+            let seg = self.ident();
+            if seg.len() == 0 {
+                self.pos = before;
+                break;
+            }
+            p = p + ~"::" + seg;
+        }
+        return p;
+    }
+
+    // lifetime reads a lifetime ('a), if one is next. 'a' is a char, not a
+    // lifetime.
+    fn lifetime() -> Option<~str> {
+        if self.peek() != '\'' {
+            return None;
+        }
+        let before = self.pos;
+        self.bump();
+        let name = self.ident();
+        if name.len() == 0 ||
+            (self.pos < self.s.len() && self.s.char_at(self.pos) == '\'') {
+            self.pos = before;
+            return None;
+        }
+        return Some(~"'" + name);
+    }
+
+    // skip_name moves past the name of an argument (the x in x: int), if it
+    // has one
+    fn skip_name() {
+        let before = self.pos;
+        let name = self.ident();
+        if name.len() > 0 && self.peek() == ':' && !self.looking_at("::") {
+            self.bump();
+        } else {
+            self.pos = before;
+        }
+    }
+
+    // parse_type reads a single type
+    fn parse_type() -> Result<@Arg, ParseError> {
+        // + is the old by-copy argument mode, which isn't part of the type
+        while self.peek() == '+' {
+            self.bump();
+        }
+        match self.pointer() {
+            Some(p) => {
+                return match self.parse_type() {
+                    Ok(a) => Ok(@Ptr(p, a)),
+                    Err(e) => Err(e)
+                };
+            }
+            None => {}
+        }
+        // we drop any modifiers: const, mut, and pure on old functions.
+        if !self.eat_word("mut") {
+            self.eat_word("const");
+        }
+        self.eat_word("pure");
+        let next = self.peek();
+        let start = self.pos;
+        match next {
+            '\0' => return Ok(@Basic(~"()")),
+            '!' => {
+                self.bump();
+                return Ok(@Never);
+            }
+            '[' => return self.vector(),
+            '(' => {
+                self.bump();
+                if self.peek() == ')' {
+                    self.bump();
+                    return Ok(@Basic(~"()"));
+                }
+                return match self.list(start, false) {
+                    Ok(args) => Ok(@Tuple(args)),
+                    Err(e) => Err(e)
+                };
+            }
+            '<' => return self.projection(),
+            // a constant, as a generic argument or the length of an array
+            '{' | '"' | '\'' | '-' => return self.constant(),
+            c if char::is_digit(c) => return self.constant(),
+            _ => {}
+        }
+        if self.eat_word("dyn") {
+            return self.bounded(true);
+        }
+        if self.eat_word("impl") {
+            return self.bounded(false);
+        }
+        if self.looking_at_word("unsafe") || self.looking_at_word("extern") {
+            return self.qualified_fn();
+        }
+        if self.eat_word("fn") {
+            return match self.function() {
+                Ok((args, ret)) => Ok(@Function(args, ret)),
+                Err(e) => Err(e)
+            };
+        }
+        if self.eat_word("_") {
+            return Ok(@Hole);
+        }
+        if self.looking_at_word("true") || self.looking_at_word("false") {
+            return Ok(@ConstArg(self.ident()));
+        }
+        return self.named();
+    }
+
+    // pointer reads the pointer (or reference) at the front of a type, if
+    // there is one. both old (&const T, &a/T) and new (*const T, &'a T)
+    // forms are understood.
+    fn pointer() -> Option<Pointer> {
+        let raw = match self.peek() {
+            '~' => {
+                self.bump();
+                return Some(Owned);
+            }
+            '@' => {
+                self.bump();
+                return Some(Managed);
+            }
+            '&' => false,
+            '*' => true,
+            _ => return None
+        };
+        self.bump();
+        let mut lifetime = None;
+        if !raw {
+            lifetime = self.lifetime();
+            if lifetime.is_none() {
+                // old regions were written without the ', before a /
+                let before = self.pos;
+                let r = self.ident();
+                if r.len() > 0 && self.peek() == '/' {
+                    self.bump();
+                    lifetime = Some(~"'" + r);
                 } else {
-                    // basic type
-                    return @Basic(copy s);
+                    self.pos = before;
                 }
             }
         }
-    } else {
-        let params = split_arguments(&str::split_char(ps[1], '>')[0]);
-        return @Parametric(@Basic(ps[0]), vec::map(params, |a| {parse_arg(a)}));
+        let mutable = self.eat_word("mut");
+        if !mutable {
+            self.eat_word("const");
+        }
+        return Some(match (raw, mutable) {
+            (true, true) => RawMut,
+            (true, false) => RawConst,
+            (false, true) => RefMut(lifetime),
+            (false, false) => Ref(lifetime)
+        });
+    }
+
+    // list reads types separated by commas, up to the bracket that closes
+    // the one at open. the arguments of a function can be named, and in the
+    // parameters of a type, lifetimes and associated types (Item = T) don't
+    // change which type it is, so they are left out.
+    fn list(open: uint, params: bool) -> Result<~[@Arg], ParseError> {
+        match self.params(open, params) {
+            Ok((args, _bindings)) => Ok(args),
+            Err(e) => Err(e)
+        }
+    }
+
+    // params is list, also giving the associated types bound in the list,
+    // by name, which a trait's parameters fix the trait's types with
+    fn params(open: uint, params: bool)
+            -> Result<(~[@Arg], ~[(~str, @Arg)]), ParseError> {
+        let close = match self.s.char_at(open) {
+            '(' => ')', '[' => ']', _ => '>'
+        };
+        let mut args = ~[];
+        let mut bindings = ~[];
+        loop {
+            if self.peek() == close {
+                self.bump();
+                return Ok((args, bindings));
+            }
+            let lifetime = params && self.lifetime().is_some();
+            if !lifetime {
+                if !params {
+                    self.skip_name();
+                }
+                let a = match self.parse_type() {
+                    Ok(a) => a,
+                    Err(e) => return Err(e)
+                };
+                if params && self.peek() == '=' {
+                    self.bump();
+                    let item = match *a {
+                        Basic(ref n) => copy *n,
+                        _ => return Err(self.error(
+                            ~"expected the name of an associated type"))
+                    };
+                    match self.parse_type() {
+                        Ok(t) => bindings.push((item, t)),
+                        Err(e) => return Err(e)
+                    }
+                } else {
+                    args.push(a);
+                }
+            }
+            match self.peek() {
+                ',' => self.bump(),
+                '\0' => return Err(self.unclosed(open)),
+                c if c == close => {}
+                _ => return Err(self.error(fmt!("expected , or %c", close)))
+            }
+        }
+    }
+
+    // vector reads a vector or slice ([T]) or a fixed size array ([T; N])
+    fn vector() -> Result<@Arg, ParseError> {
+        let open = self.pos;
+        self.bump();
+        let elem = match self.parse_type() {
+            Ok(a) => a,
+            Err(e) => return Err(e)
+        };
+        let len = if self.eat(";") {
+            match self.parse_type() {
+                Ok(l) => Some(l),
+                Err(e) => return Err(e)
+            }
+        } else {
+            None
+        };
+        return match (self.expect_close(']', open), len) {
+            (Err(e), _) => Err(e),
+            (Ok(_), Some(l)) => Ok(@Array(elem, l)),
+            (Ok(_), None) => Ok(@Vec(elem))
+        };
+    }
+
+    // constant reads a constant: a number, a char or string, or a block
+    // ({N + 1}). it is kept as it was written.
+    fn constant() -> Result<@Arg, ParseError> {
+        let start = self.pos;
+        let c = self.s.char_at(start);
+        self.bump();
+        if c == '"' || c == '\'' || c == '{' {
+            let mut depth = 1;
+            while depth > 0 {
+                if self.pos >= self.s.len() {
+                    return Err(ParseError { pos: start,
+                                            msg: fmt!("unclosed %c", c) });
+                }
+                let d = self.s.char_at(self.pos);
+                if d == '\\' && c != '{' {
+                    self.bump();
+                } else if d == '{' && c == '{' {
+                    depth += 1;
+                } else if d == c || (d == '}' && c == '{') {
+                    depth -= 1;
+                }
+                self.bump();
+            }
+        } else {
+            while self.pos < self.s.len() &&
+                (is_ident_char(self.s.char_at(self.pos)) ||
+                 self.s.char_at(self.pos) == '.') {
+                self.bump();
+            }
+        }
+        return Ok(@ConstArg(str::slice(self.s, start, self.pos)));
+    }
+
+    // function reads the rest of a function type, after the fn. old closures
+    // had a sigil (fn&, fn~, fn@), which doesn't matter here.
+    fn function() -> Result<(~[@Arg], @Arg), ParseError> {
+        match self.peek() {
+            '&' | '~' | '@' => self.bump(),
+            _ => {}
+        }
+        self.peek();
+        let open = self.pos;
+        if !self.eat("(") {
+            return Err(self.error(~"expected ("));
+        }
+        let args = match self.list(open, false) {
+            Ok(args) => args,
+            Err(e) => return Err(e)
+        };
+        if !self.eat("->") {
+            return Ok((args, @Basic(~"()")));
+        }
+        return match self.parse_type() {
+            Ok(ret) => Ok((args, ret)),
+            Err(e) => Err(e)
+        };
+    }
+
+    // qualified_fn reads an unsafe and/or extern function type. the abi of
+    // an extern function is "C" unless another is given, as in rust.
+    fn qualified_fn() -> Result<@Arg, ParseError> {
+        let mut quals = ~[];
+        if self.eat_word("unsafe") {
+            quals.push(~"unsafe");
+        }
+        if self.eat_word("extern") {
+            let mut abi = ~"\"C\"";
+            if self.peek() == '"' {
+                match self.constant() {
+                    Ok(@ConstArg(ref a)) => abi = copy *a,
+                    Ok(_) => {}
+                    Err(e) => return Err(e)
+                }
+            }
+            quals.push(~"extern " + abi);
+        }
+        if !self.eat_word("fn") {
+            return Err(self.error(~"expected fn"));
+        }
+        return match self.function() {
+            Ok((args, ret)) => {
+                Ok(@QualifiedFn(str::connect(quals, " "), args, ret))
+            }
+            Err(e) => Err(e)
+        };
+    }
+
+    // trait_ref reads a trait, giving its name without its path or
+    // parameters (std::iter::Iterator<Item = T> is Iterator), and the
+    // associated types its parameters bind (Item = T)
+    fn trait_ref() -> Result<(~str, ~[Binding]), ParseError> {
+        let path = self.path();
+        if path.len() == 0 {
+            return Err(self.error(~"expected a trait"));
+        }
+        let segs = str::split_str(path, "::");
+        let tr = copy segs[segs.len() - 1];
+        let mut bindings = ~[];
+        if self.peek() == '<' {
+            let open = self.pos;
+            self.bump();
+            match self.params(open, true) {
+                Ok((_args, bs)) => {
+                    for bs.each |b| {
+                        let (item, ty) = copy *b;
+                        bindings.push(Binding { tr: copy tr, item: item,
+                                                ty: ty });
+                    }
+                }
+                Err(e) => return Err(e)
+            }
+        }
+        return Ok((tr, bindings));
+    }
+
+    // bounded reads the traits of a trait object (dyn Trait) or an impl
+    // Trait type. like with bounds, only which traits they are matters,
+    // along with the associated types they bind, except that a closure
+    // trait keeps its signature, as the closure type it is.
+    fn bounded(dynamic: bool) -> Result<@Arg, ParseError> {
+        let mut cs = ~[];
+        let mut bs = ~[];
+        let mut closure = None;
+        loop {
+            // lifetimes and ?Sized aren't traits
+            if self.lifetime().is_none() {
+                let maybe = self.eat("?");
+                let tr = match self.trait_ref() {
+                    Ok((tr, bindings)) => {
+                        bs.push_all(bindings);
+                        tr
+                    }
+                    Err(e) => return Err(e)
+                };
+                if is_closure_trait(&tr) && self.peek() == '(' {
+                    match self.function() {
+                        Ok((args, ret)) => {
+                            closure = Some(@Closure(tr, args, ret))
+                        }
+                        Err(e) => return Err(e)
+                    }
+                } else if !maybe {
+                    cs.push(Constraint(tr));
+                }
+            }
+            if !self.eat("+") {
+                break;
+            }
+        }
+        return match closure {
+            Some(c) => Ok(c),
+            None => Ok(if dynamic { @Dyn(cs, bs) } else { @Impl(cs, bs) })
+        };
+    }
+
+    // projection reads a qualified path to an associated type, like
+    // <T as Iterator>::Item (or <T>::Item)
+    fn projection() -> Result<@Arg, ParseError> {
+        let open = self.pos;
+        self.bump();
+        let base = match self.parse_type() {
+            Ok(a) => a,
+            Err(e) => return Err(e)
+        };
+        let mut tr = None;
+        if self.eat_word("as") {
+            match self.trait_ref() {
+                Ok((t, _bindings)) => tr = Some(t),
+                Err(e) => return Err(e)
+            }
+        }
+        match self.expect_close('>', open) {
+            Ok(_) => {}
+            Err(e) => return Err(e)
+        }
+        if !self.eat("::") {
+            return Err(self.error(~"expected ::, as in <T as Trait>::Item"));
+        }
+        let item = self.path();
+        if item.len() == 0 {
+            return Err(self.error(~"expected an associated type"));
+        }
+        return Ok(@Projection(base, tr, item));
+    }
+
+    // named reads a type that is given by its name, like int, Option<T>,
+    // T::Item or Fn(A) -> B
+    fn named() -> Result<@Arg, ParseError> {
+        let name = self.path();
+        if name.len() == 0 {
+            return Err(self.error(fmt!("unexpected %c", self.peek())));
+        }
+        if is_closure_trait(&name) && self.peek() == '(' {
+            return match self.function() {
+                Ok((args, ret)) => Ok(@Closure(name, args, ret)),
+                Err(e) => Err(e)
+            };
+        }
+        if self.peek() == '<' {
+            let open = self.pos;
+            self.bump();
+            let params = match self.list(open, true) {
+                Ok(params) => params,
+                Err(e) => return Err(e)
+            };
+            // Box<T> is what ~T became
+            let t = if name == ~"Box" && params.len() == 1 {
+                @Ptr(Owned, params[0])
+            } else {
+                @Parametric(@Basic(name), params)
+            };
+            if !self.eat("::") {
+                return Ok(t);
+            }
+            let item = self.path();
+            if item.len() == 0 {
+                return Err(self.error(~"expected an associated type"));
+            }
+            return Ok(@Projection(t, None, item));
+        }
+        // an associated type of a type variable, like T::Item
+        match str::find_str(name, "::") {
+            Some(i) if i == 1 || str::slice(name, 0, i) == ~"Self" => {
+                return Ok(@Projection(named_type(str::slice(name, 0, i)), None,
+                                      str::slice(name, i + 2, name.len())));
+            }
+            _ => {}
+        }
+        return Ok(named_type(name));
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    char::is_alphanumeric(c) || c == '_'
+}
+
+fn is_closure_trait(name: &~str) -> bool {
+    *name == ~"Fn" || *name == ~"FnMut" || *name == ~"FnOnce"
+}
+
+// named_type gives the type for a plain name. it is a basic type until it
+// is declared to be a type variable (see declare and parse_query).
+fn named_type(name: ~str) -> @Arg {
+    @Basic(name)
+}
+
+// find_arrow finds the first -> in s that isn't inside of any brackets,
+// which is the one between the arguments of a function and its return type
+fn find_arrow(s: &~str) -> Option<uint> {
+    let mut level = 0;
+    for str::each_chari(*s) |i, c| {
+        match c {
+            '<' | '(' | '[' => level += 1,
+            ')' | ']' => level -= 1,
+            '>' => if i == 0 || s.char_at(i-1) != '-' { level -= 1 },
+            '-' if level == 0 && i + 1 < s.len() &&
+                s.char_at(i+1) == '>' => return Some(i),
+            _ => {}
+        }
     }
+    return None;
 }
 
 // canonicalize_args takes a list of arguments and a return type
-// and replaces generic names consistently (alphabetically, single
-// uppercase letters, in order of frequency)
+// and replaces generic names consistently (alphabetically, in order of
+// frequency, see letters).
 pub fn canonicalize_args(args: ~[@Arg], ret: @Arg) -> (~[@Arg],@Arg,uint) {
     // The basic process is as follows:
     // 1. identify and count polymorphic params
@@ -193,7 +1095,34 @@ pub fn replace_arg(a: @Arg, old: @Arg, new: @Arg) -> @Arg {
             @Function(vec::map(args, |i| { replace_arg(*i, old, new)}),
                      replace_arg(ret, old, new))
         }
-        _ => a
+        Ptr(p, inner) => {
+            @Ptr(p, replace_arg(inner, old, new))
+        }
+        Array(elem, len) => {
+            @Array(replace_arg(elem, old, new), replace_arg(len, old, new))
+        }
+        Closure(tr, args, ret) => {
+            @Closure(tr, vec::map(args, |i| { replace_arg(*i, old, new)}),
+                     replace_arg(ret, old, new))
+        }
+        Projection(base, tr, item) => {
+            @Projection(replace_arg(base, old, new), tr, item)
+        }
+        QualifiedFn(qs, args, ret) => {
+            @QualifiedFn(qs, vec::map(args, |i| { replace_arg(*i, old, new)}),
+                         replace_arg(ret, old, new))
+        }
+        Dyn(cs, bs) => {
+            @Dyn(cs, vec::map(bs, |b| { b.map(|t| {
+                replace_arg(t, old, new)
+            }) }))
+        }
+        Impl(cs, bs) => {
+            @Impl(cs, vec::map(bs, |b| { b.map(|t| {
+                replace_arg(t, old, new)
+            }) }))
+        }
+        Basic(_) | Constrained(_, _) | Never | ConstArg(_) | Hole => a
     }
 }
 
@@ -208,7 +1137,7 @@ fn drop_modifiers(s: &~str) -> ~str {
     return copy *s;
 }
 
-// trim_sigils trims off the sigils off of types
+// trim_sigils trims the sigils off of a type (or a name, like &self)
 pub fn trim_sigils(s: &str) -> ~str {
     drop_modifiers(&str::trim_left_chars(s, &[' ', '&', '~', '@', '+']))
 }
@@ -235,38 +1164,397 @@ mod tests {
 
     #[test]
     fn test_parse_signature() {
+        let str_ref = @Ptr(Ref(None), @Basic(~"str"));
         assert parse_signature(~"fn ne(other: & ~str) -> bool",
-                         Some(~"& str"), false) ==
-                (~[@Basic(~"str"), @Basic(~"str")], @Basic(~"bool"), 0);
-        assert parse_signature(~"fn foo(bar: Option<T>) -> bool",
-                         None, true) ==
+                         Some(~"& str"), false).get() ==
+                (~[str_ref, @Ptr(Ref(None), @Ptr(Owned, @Basic(~"str")))],
+                 @Basic(~"bool"), 0);
+        assert parse_signature(~"fn foo<T>(bar: Option<T>) -> bool",
+                         None, true).get() ==
                 (~[@Parametric(@Basic(~"Option"),~[@Constrained(~"A", ~[])])],
                  @Basic(~"bool"),
                  1);
+        // a definition's variables have to be declared, even short ones
+        assert parse_signature(~"fn foo(bar: Option<T>) -> bool",
+                         None, true).get() ==
+                (~[@Parametric(@Basic(~"Option"),~[@Basic(~"T")])],
+                 @Basic(~"bool"),
+                 0);
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let a = @Constrained(~"A", ~[]);
+        // single capital letters are variables without being declared
+        assert parse_query(~"Option<T> -> T").get() ==
+            (~[@Parametric(@Basic(~"Option"), ~[a])], a, 1);
+        assert parse_query(~"<I as IntoIterator>::IntoIter -> I").get() ==
+            (~[@Projection(a, Some(~"IntoIterator"), ~"IntoIter")], a, 1);
+        // but other names aren't
+        assert parse_query(~"Vec<u> -> Key").get() ==
+            (~[@Parametric(@Basic(~"Vec"), ~[@Basic(~"u")])],
+             @Basic(~"Key"), 0);
+        assert parse_query(~"forall Key. (Key, Key) -> T").get() ==
+            (~[a, a], @Constrained(~"B", ~[]), 2);
+    }
+
+    #[test]
+    fn test_parse_signature_bounds() {
+        let ord = ~[Constraint(~"Ord")];
+        assert parse_signature(~"fn max<T: Copy Ord>(v: &[T]) -> T",
+                               None, false).get() ==
+            (~[@Ptr(Ref(None),
+                    @Vec(@Constrained(~"T", ~[Constraint(~"Copy"),
+                                              Constraint(~"Ord")])))],
+             @Constrained(~"T", ~[Constraint(~"Copy"), Constraint(~"Ord")]),
+             0);
+        assert parse_signature(~"fn max<T>(v: &[T]) -> T where T: Ord",
+                               None, false).get() ==
+            (~[@Ptr(Ref(None), @Vec(@Constrained(~"T", copy ord)))],
+             @Constrained(~"T", copy ord), 0);
+        assert parse_signature(~"<A: Ord> [A] -> A", None, true).get() ==
+            (~[@Vec(@Constrained(~"A", copy ord))],
+             @Constrained(~"A", copy ord), 1);
+    }
+
+    #[test]
+    fn test_declared_generics() {
+        let k = @Constrained(~"K", ~[]);
+        let v = @Constrained(~"Value", ~[]);
+        let map = @Parametric(@Basic(~"HashMap"), ~[k, v]);
+        let sig = ~"pub fn get<K: Hash, Value>(m: HashMap<K, Value>, k: K)" +
+            ~" -> Option<Value>";
+        let hash_k = @Constrained(~"K", ~[Constraint(~"Hash")]);
+        assert parse_signature(sig, None, false).get() ==
+            (~[@Parametric(@Basic(~"HashMap"), ~[hash_k, v]), hash_k],
+             @Parametric(@Basic(~"Option"), ~[v]), 0);
+        // undeclared names are still types
+        assert parse_arg(&~"Key").get() == @Basic(~"Key");
+        // queries declare them with forall, or a generics list
+        assert parse_signature(~"forall K Value. HashMap<K, Value> -> Value",
+                               None, false).get() == (~[map], v, 0);
+        assert parse_signature(~"forall K: Hash, Value. K -> Value",
+                               None, false).get() ==
+            (~[@Constrained(~"K", ~[Constraint(~"Hash")])], v, 0);
+        assert parse_signature(~"<K, Value> HashMap<K, Value> -> Value",
+                               None, false).get() == (~[map], v, 0);
+        // as are associated types of declared names
+        assert parse_signature(~"fn add<Rhs>(x: Rhs) -> Rhs::Output",
+                               None, false).get() ==
+            (~[@Constrained(~"Rhs", ~[])],
+             @Projection(@Constrained(~"Rhs", ~[]), None, ~"Output"), 0);
+        // and canonical names don't care how long the originals were
+        assert parse_signature(~"forall Elem. Elem -> Option<Elem>",
+                               None, true).get() ==
+            (~[@Constrained(~"A", ~[])],
+             @Parametric(@Basic(~"Option"), ~[@Constrained(~"A", ~[])]), 1);
+    }
+
+    #[test]
+    fn test_explicit_self() {
+        assert parse_signature(~"fn eq(&self, other: &Self) -> bool",
+                               Some(~"int"), false).get() ==
+            (~[@Ptr(Ref(None), @Basic(~"int")),
+               @Ptr(Ref(None), @Basic(~"Self"))], @Basic(~"bool"), 0);
+        // how the receiver is taken is kept, but not a mut on the binding
+        let v = @Parametric(@Basic(~"Vec"), ~[@Constrained(~"T", ~[])]);
+        assert parse_signature(~"fn push<T>(&'a mut self, x: T)",
+                               Some(~"Vec<T>"), false).get() ==
+            (~[@Ptr(RefMut(Some(~"'a")), v), @Constrained(~"T", ~[])],
+             @Basic(~"()"), 0);
+        assert parse_signature(~"fn into_iter<T>(mut self) -> int",
+                               Some(~"Vec<T>"), false).get() ==
+            (~[v], @Basic(~"int"), 0);
+        assert parse_signature(~"fn boxed<T>(self: ~Self) -> int",
+                               Some(~"Vec<T>"), false).get() ==
+            (~[@Ptr(Owned, v)], @Basic(~"int"), 0);
+        // older data doesn't declare the impl's generics anywhere
+        let t = @Constrained(~"T", ~[]);
+        assert parse_signature(~"fn get() -> T", Some(~"Option<T>"),
+                               false).get() ==
+            (~[@Parametric(@Basic(~"Option"), ~[t])], t, 0);
+        assert parse_signature(~"fn map<U>(f: fn(&T) -> U) -> Option<U>",
+                               Some(~"& Option<T>"), false).get().first()[0] ==
+            @Ptr(Ref(None), @Parametric(@Basic(~"Option"), ~[t]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert parse_signature(~"[int -> int", None, true) ==
+            Err(ParseError { pos: 0, msg: ~"unclosed [" });
+        assert parse_signature(~"(int, Option<uint) -> int", None, true) ==
+            Err(ParseError { pos: 16, msg: ~"unmatched )" });
+        assert parse_signature(~"int -> Vec<int", None, true) ==
+            Err(ParseError { pos: 10, msg: ~"unclosed <" });
+        assert parse_arg(&~"[int").is_err();
+        assert parse_arg(&~"Option<int").is_err();
+    }
+
+    #[test]
+    fn test_parse_bounds() {
+        assert parse_bounds(&~"T: Copy + Ord, 'a, U") ==
+            ~[(~"T", ~[Constraint(~"Copy"), Constraint(~"Ord")]),
+              (~"U", ~[])];
+        assert parse_bounds(&~"T: Add<T, T> ?Sized") ==
+            ~[(~"T", ~[Constraint(~"Add")])];
+        assert parse_bounds(&~"T, const N: usize") ==
+            ~[(~"T", ~[]), (~"N", ~[])];
     }
 
     #[test]
     fn test_method_args() {
         assert parse_signature(~"fn ne(other: & str) -> bool",
-                         Some(~"& str"), false) ==
-                (~[@Basic(~"str"),
-                   @Basic(~"str")],
+                         Some(~"& str"), false).get() ==
+                (~[@Ptr(Ref(None), @Basic(~"str")),
+                   @Ptr(Ref(None), @Basic(~"str"))],
                  @Basic(~"bool"),
                  0);
     }
 
     #[test]
     fn test_parse_arg() {
-        assert parse_arg(&~"~str") == @Basic(~"str");
-        assert parse_arg(&~"@str") == @Basic(~"str");
-        assert parse_arg(&~"&@str") == @Basic(~"str");
-        assert parse_arg(&~"Option<~str>") ==
-            @Parametric(@Basic(~"Option"), ~[@Basic(~"str")]);
-        assert parse_arg(&~"~[uint]") ==
-            @Vec(@Basic(~"uint"));
-        assert parse_arg(&~"(uint, ~str)") ==
-            @Tuple(~[@Basic(~"uint"), @Basic(~"str")]);
-        assert parse_arg(&~"[const T]") == @Vec(@Constrained(~"T", ~[]));
+        let st = @Basic(~"str");
+        assert parse_arg(&~"str").get() == st;
+        assert parse_arg(&~"+str").get() == st;
+        assert parse_arg(&~"~str").get() == @Ptr(Owned, st);
+        assert parse_arg(&~"@str").get() == @Ptr(Managed, st);
+        assert parse_arg(&~"&@str").get() == @Ptr(Ref(None), @Ptr(Managed, st));
+        assert parse_arg(&~"Option<~str>").get() ==
+            @Parametric(@Basic(~"Option"), ~[@Ptr(Owned, st)]);
+        assert parse_arg(&~"~[uint]").get() ==
+            @Ptr(Owned, @Vec(@Basic(~"uint")));
+        assert parse_arg(&~"(uint, ~str)").get() ==
+            @Tuple(~[@Basic(~"uint"), @Ptr(Owned, st)]);
+        assert parse_arg(&~"[const T]").get() == @Vec(@Basic(~"T"));
+    }
+
+    #[test]
+    fn test_parse_pointers() {
+        let t = @Basic(~"T");
+        assert parse_arg(&~"&mut T").get() == @Ptr(RefMut(None), t);
+        assert parse_arg(&~"&'a T").get() == @Ptr(Ref(Some(~"'a")), t);
+        assert parse_arg(&~"&'a mut T").get() == @Ptr(RefMut(Some(~"'a")), t);
+        assert parse_arg(&~"&const T").get() == @Ptr(Ref(None), t);
+        assert parse_arg(&~"*T").get() == @Ptr(RawConst, t);
+        assert parse_arg(&~"*const T").get() == @Ptr(RawConst, t);
+        assert parse_arg(&~"*mut T").get() == @Ptr(RawMut, t);
+        assert parse_arg(&~"&&T").get() == @Ptr(Ref(None), @Ptr(Ref(None), t));
+        assert parse_arg(&~"&mut Vec<T>").get() ==
+            @Ptr(RefMut(None), @Parametric(@Basic(~"Vec"), ~[t]));
+        assert parse_arg(&~"&mut Vec<T").is_err();
+    }
+
+    #[test]
+    fn test_parse_modern() {
+        let t = @Basic(~"T");
+        let n = @Basic(~"u8");
+        assert parse_arg(&~"&[T]").get() == @Ptr(Ref(None), @Vec(t));
+        assert parse_arg(&~"[u8; 4]").get() == @Array(n, @ConstArg(~"4"));
+        assert parse_arg(&~"[[T; N]; 2]").get() ==
+            @Array(@Array(t, @Basic(~"N")), @ConstArg(~"2"));
+        assert parse_arg(&~"Box<T>").get() == @Ptr(Owned, t);
+        assert parse_arg(&~"Option<Option<T>>").get() ==
+            @Parametric(@Basic(~"Option"),
+                        ~[@Parametric(@Basic(~"Option"), ~[t])]);
+        assert parse_arg(&~"Cow<'a, str>").get() ==
+            @Parametric(@Basic(~"Cow"), ~[@Basic(~"str")]);
+        assert parse_arg(&~"Matrix<T, 3, {N + 1}>").get() ==
+            @Parametric(@Basic(~"Matrix"),
+                        ~[t, @ConstArg(~"3"), @ConstArg(~"{N + 1}")]);
+        assert parse_arg(&~"dyn std::fmt::Debug + Send + 'static").get() ==
+            @Dyn(~[Constraint(~"Debug"), Constraint(~"Send")], ~[]);
+        assert parse_arg(&~"impl Iterator<Item = T>").get() ==
+            @Impl(~[Constraint(~"Iterator")],
+                  ~[Binding { tr: ~"Iterator", item: ~"Item", ty: t }]);
+        assert parse_arg(&~"Box<dyn Iterator<Item = u8> + Send>").get() ==
+            @Ptr(Owned, @Dyn(~[Constraint(~"Iterator"), Constraint(~"Send")],
+                             ~[Binding { tr: ~"Iterator", item: ~"Item",
+                                         ty: @Basic(~"u8") }]));
+        assert parse_arg(&~"impl Iterator<Vec<T> = T>").is_err();
+        assert parse_arg(&~"!").get() == @Never;
+        assert parse_arg(&~"Vec<_>").get() ==
+            @Parametric(@Basic(~"Vec"), ~[@Hole]);
+        assert parse_arg(&~"_t").get() == @Basic(~"_t");
+        assert parse_arg(&~"&'static str").get() ==
+            @Ptr(Ref(Some(~"'static")), @Basic(~"str"));
+    }
+
+    #[test]
+    fn test_parse_fn_types() {
+        let a = @Basic(~"A");
+        let b = @Basic(~"B");
+        assert parse_arg(&~"Fn(A) -> B").get() == @Closure(~"Fn", ~[a], b);
+        assert parse_arg(&~"FnMut(A)").get() ==
+            @Closure(~"FnMut", ~[a], @Basic(~"()"));
+        assert parse_arg(&~"Box<dyn FnOnce(A) -> B + Send>").get() ==
+            @Ptr(Owned, @Closure(~"FnOnce", ~[a], b));
+        assert parse_arg(&~"impl Fn(Vec<A>) -> B").get() ==
+            @Closure(~"Fn", ~[@Parametric(@Basic(~"Vec"), ~[a])], b);
+        assert parse_arg(&~"fn(A) -> fn(A) -> B").get() ==
+            @Function(~[a], @Function(~[a], b));
+        assert parse_arg(&~"unsafe extern \"C\" fn(A) -> B").get() ==
+            @QualifiedFn(~"unsafe extern \"C\"", ~[a], b);
+        assert parse_arg(&~"extern fn(A)").get() ==
+            @QualifiedFn(~"extern \"C\"", ~[a], @Basic(~"()"));
+        assert parse_arg(&~"unsafe fn() -> !").get() ==
+            @QualifiedFn(~"unsafe", ~[], @Never);
+        // a closure can be returned, arrows and all
+        assert parse_signature(~"fn adder(n: int) -> impl Fn(int) -> int",
+                               None, false).get() ==
+            (~[@Basic(~"int")],
+             @Closure(~"Fn", ~[@Basic(~"int")], @Basic(~"int")), 0);
+    }
+
+    #[test]
+    fn test_parse_projection() {
+        let t = @Basic(~"T");
+        assert parse_arg(&~"<T as Iterator>::Item").get() ==
+            @Projection(t, Some(~"Iterator"), ~"Item");
+        assert parse_arg(&~"T::Item").get() == @Projection(t, None, ~"Item");
+        assert parse_arg(&~"Self::Err").get() ==
+            @Projection(@Basic(~"Self"), None, ~"Err");
+        assert parse_arg(&~"<T as Iterator>Item").is_err();
+        // at the start of a query, it isn't a generics list
+        assert parse_signature(~"<I as IntoIterator>::IntoIter -> I",
+                               None, false).get() ==
+            (~[@Projection(@Basic(~"I"), Some(~"IntoIterator"),
+                           ~"IntoIter")],
+             @Basic(~"I"), 0);
+        // and paths in unnamed arguments aren't names
+        assert parse_signature(~"fn f(std::string::String) -> int",
+                               None, false).get() ==
+            (~[@Basic(~"std::string::String")], @Basic(~"int"), 0);
+    }
+
+    #[test]
+    fn test_parse_nested() {
+        let t = @Basic(~"T");
+        let v = @Basic(~"V");
+        assert parse_arg(&~"Option<Vec<T>>").get() ==
+            @Parametric(@Basic(~"Option"),
+                        ~[@Parametric(@Basic(~"Vec"), ~[t])]);
+        assert parse_arg(&~"HashMap<K, Option<V>>").get() ==
+            @Parametric(@Basic(~"HashMap"),
+                        ~[@Basic(~"K"),
+                          @Parametric(@Basic(~"Option"), ~[v])]);
+        assert parse_arg(&~"Result<Vec<(T, V)>, ()>").get() ==
+            @Parametric(@Basic(~"Result"),
+                        ~[@Parametric(@Basic(~"Vec"), ~[@Tuple(~[t, v])]),
+                          @Basic(~"()")]);
+        // old regions, and named arguments of old closures
+        assert parse_arg(&~"&a/[const T]").get() ==
+            @Ptr(Ref(Some(~"'a")), @Vec(t));
+        assert parse_arg(&~"fn&(push: pure fn&(v: T))").get() ==
+            @Function(~[@Function(~[t], @Basic(~"()"))], @Basic(~"()"));
+        assert parse_arg(&~"Vec<T> Vec<T>") ==
+            Err(ParseError { pos: 7, msg: ~"unexpected Vec<T>" });
+        assert parse_arg(&~"HashMap<K V>") ==
+            Err(ParseError { pos: 10, msg: ~"expected , or >" });
+    }
+
+    #[test]
+    fn test_print_arg() {
+        // printing a parsed type and parsing it again gives the same type
+        for [~"Option<Vec<T>>", ~"HashMap<K, Option<V>>", ~"&'a mut [u8]",
+             ~"~[@str]", ~"*const T", ~"(int,)", ~"(A, B)", ~"[[T; N]; 2]",
+             ~"Box<dyn FnOnce(A) -> B + Send>", ~"fn(A) -> fn(A) -> B",
+             ~"impl Iterator<Item = T> + Send", ~"<T as Iterator>::Item",
+             ~"T::Item", ~"Vec<T>::IntoIter", ~"unsafe extern \"C\" fn(A)",
+             ~"Matrix<T, 3, {N + 1}>", ~"!", ~"std::string::String",
+             ~"&self /T"].each |s| {
+            let a = parse_arg(s).get();
+            assert parse_arg(&a.to_str()).get() == a;
+        }
+        assert parse_arg(&~"Box<Option< T >>").get().to_str() ==
+            ~"~Option<T>";
+        assert parse_arg(&~"&a/T").get().to_str() == ~"&'a T";
+    }
+
+    // random_arg makes a random type, of the kinds that the parser gives,
+    // with at most depth levels of types inside of it
+    fn random_arg(r: rand::Rng, depth: uint) -> @Arg {
+        fn pick(r: rand::Rng, v: &[~str]) -> ~str {
+            copy v[r.gen_uint_range(0, v.len())]
+        }
+        fn some(r: rand::Rng, depth: uint, min: uint) -> ~[@Arg] {
+            vec::from_fn(r.gen_uint_range(min, 3), |_i| {
+                random_arg(r, depth)
+            })
+        }
+        fn traits(r: rand::Rng) -> ~[Constraint] {
+            vec::from_fn(r.gen_uint_range(1, 3), |_i| {
+                Constraint(pick(r, &[~"Send", ~"Debug", ~"Ord"]))
+            })
+        }
+        // bounds gives traits, sometimes with an Iterator binding its Item
+        fn bounds(r: rand::Rng, d: uint) -> (~[Constraint], ~[Binding]) {
+            let mut cs = traits(r);
+            if r.gen_bool() {
+                return (cs, ~[]);
+            }
+            cs.push(Constraint(~"Iterator"));
+            return (cs, ~[Binding { tr: ~"Iterator", item: ~"Item",
+                                    ty: random_arg(r, d) }]);
+        }
+        let kind = r.gen_uint_range(0, if depth == 0 { 5 } else { 16 });
+        let d = if depth == 0 { 0 } else { depth - 1 };
+        match kind {
+            0 => @Basic(pick(r, &[~"int", ~"str", ~"()", ~"io::Error"])),
+            // names only become variables once declared (see declare)
+            1 => @Basic(pick(r, &[~"A", ~"B", ~"T"])),
+            2 => @ConstArg(pick(r, &[~"3", ~"-1", ~"{N + 1}", ~"true",
+                                     ~"'c'", ~"\"s\""])),
+            3 => @Never,
+            4 => @Hole,
+            5 => @Parametric(@Basic(pick(r, &[~"Option", ~"Vec",
+                                              ~"comm::Port"])),
+                             some(r, d, 1)),
+            6 => @Tuple(some(r, d, 1)),
+            7 => @Vec(random_arg(r, d)),
+            8 => @Function(some(r, d, 0), random_arg(r, d)),
+            9 => {
+                let lifetime = if r.gen_bool() { Some(~"'a") } else { None };
+                let p = match r.gen_uint_range(0, 6) {
+                    0 => Owned, 1 => Managed, 2 => RawConst, 3 => RawMut,
+                    4 => Ref(lifetime), _ => RefMut(lifetime)
+                };
+                @Ptr(p, random_arg(r, d))
+            }
+            10 => @Array(random_arg(r, d),
+                         if r.gen_bool() { @ConstArg(~"4") }
+                         else { @Basic(~"N") }),
+            11 => {
+                let (cs, bs) = bounds(r, d);
+                @Dyn(cs, bs)
+            }
+            12 => {
+                let (cs, bs) = bounds(r, d);
+                @Impl(cs, bs)
+            }
+            13 => @Closure(pick(r, &[~"Fn", ~"FnMut", ~"FnOnce"]),
+                           some(r, d, 0), random_arg(r, d)),
+            14 => @Projection(random_arg(r, d),
+                              if r.gen_bool() { Some(~"Iterator") }
+                              else { None }, ~"Item"),
+            _ => @QualifiedFn(pick(r, &[~"unsafe", ~"extern \"C\"",
+                                        ~"unsafe extern \"system\""]),
+                              some(r, d, 0), random_arg(r, d))
+        }
+    }
+
+    #[test]
+    fn test_print_random_args() {
+        let r = rand::seeded_rng(&~[1, 7, 2, 5]);
+        for 2000.times {
+            let a = random_arg(r, 4);
+            let s = a.to_str();
+            match parse_arg(&s) {
+                Ok(b) => if b != a {
+                    fail fmt!("%s came back as %s", s, b.to_str());
+                },
+                Err(e) => fail fmt!("can't parse %s: %s", s, e.msg)
+            }
+        }
     }
 
     #[test]
@@ -309,6 +1597,28 @@ mod tests {
         //            Arg {name: ~"A", inner: ~[]}, 2);
     }
 
+    #[test]
+    fn test_canonicalize_many_args() {
+        // there can be more variables than letters
+        let args = vec::from_fn(30, |i| {
+            @Constrained(fmt!("T%u", i), ~[])
+        });
+        let (canonical, ret, n) = canonicalize_args(args, @Basic(~"()"));
+        assert n == 30;
+        assert ret == @Basic(~"()");
+        let mut names = ~[];
+        for canonical.each |a| {
+            match **a {
+                Constrained(ref name, _) => {
+                    assert !vec::contains(names, name);
+                    names.push(copy *name);
+                }
+                _ => fail ~"not a variable"
+            }
+        }
+        assert vec::contains(names, &~"AD");
+    }
+
     #[test]
     fn test_canonicalize_parameterized_args() {
         // assert canonicalize_args(~[Arg {name: ~"Option",
@@ -356,6 +1666,15 @@ mod tests {
         // assert b_rep.inner[0].name == ~"U";
     }
 
+    #[test]
+    fn test_replace_arg() {
+        let a = parse_arg(&~"fn([Self; 3], Fn(Self) -> Self) -> \
+                             <Self as Iterator>::Item").get();
+        assert replace_arg(a, @Basic(~"Self"), @Basic(~"Foo")) ==
+            parse_arg(&~"fn([Foo; 3], Fn(Foo) -> Foo) -> \
+                         <Foo as Iterator>::Item").get();
+    }
+
     #[test]
     fn test_drop_modifiers() {
         assert drop_modifiers(&~"const hello") == ~"hello";
@@ -382,4 +1701,12 @@ mod tests {
         assert trim_sigils(~"str") == ~"str";
         assert trim_sigils(~"& str") == ~"str";
     }
+
+    #[test]
+    fn test_is_receiver() {
+        assert is_receiver(&~"self");
+        assert is_receiver(&~"&'a mut self");
+        assert is_receiver(&~"self: ~Self");
+        assert !is_receiver(&~"other: &Self");
+    }
 }
\ No newline at end of file
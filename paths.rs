@@ -0,0 +1,201 @@
+//! This file contains the paths of types: resolving the names of types in
+//! the index to their full paths when it is loaded, and finding the names
+//! in a query that could be more than one type (like Error, which is both
+//! std::io::Error and std::fmt::Error)
+
+use types::*;
+
+// add_type records that a type is declared at a full path, under its name
+pub fn add_type(types: &Types, path: ~str) {
+    let segs = str::split_str(path, "::");
+    let name = copy segs[segs.len() - 1];
+    let mut paths = option::get_default(&types.find(copy name), ~[]);
+    if !vec::contains(paths, &path) {
+        paths.push(path);
+    }
+    types.insert(name, paths);
+}
+
+// candidates gives the full paths of the types that a name, written with
+// or without some of its path, could be: io::Error could be
+// std::io::Error, but not std::fmt::Error
+pub fn candidates(name: &~str, types: &Types) -> ~[~str] {
+    let segs = str::split_str(*name, "::");
+    match types.find(copy segs[segs.len() - 1]) {
+        Some(paths) => vec::filter(paths, |p| {
+            let ps = str::split_str(*p, "::");
+            ps.len() >= segs.len() &&
+                vec::slice(ps, ps.len() - segs.len(), ps.len()) == segs
+        }),
+        None => ~[]
+    }
+}
+
+// resolve gives the full path of the type that a name written in a module
+// stands for. when the name could be several types, the one declared in
+// the module is taken - otherwise there is no telling which was meant, as
+// imports aren't indexed, and the name isn't resolved.
+pub fn resolve(name: &~str, module: &~str, types: &Types) -> Option<~str> {
+    let cands = candidates(name, types);
+    if cands.len() == 1 {
+        return Some(copy cands[0]);
+    }
+    let here = *module + ~"::" + *name;
+    if vec::contains(cands, &here) { Some(here) } else { None }
+}
+
+// canonical resolves the type names in a type written in a module to their
+// full paths. names that an alias in the module declares are left for the
+// alias to be expanded by (see alias.rs).
+pub fn canonical(a: @Arg, module: &~str, types: &Types, aliases: &Aliases)
+        -> @Arg {
+    map_leaves(a, |l| {
+        match *l {
+            Basic(ref n) if !declares_alias(module, n, aliases) => {
+                match resolve(n, module, types) {
+                    Some(p) => @Basic(p),
+                    None => l
+                }
+            }
+            _ => l
+        }
+    })
+}
+
+// declares_alias says whether an alias with a name is declared in a module
+fn declares_alias(module: &~str, name: &~str, aliases: &Aliases) -> bool {
+    match aliases.find(copy *name) {
+        Some(als) => vec::any(als, |a| { a.path == *module }),
+        None => false
+    }
+}
+
+// canonical_def gives a definition with the types in its signature
+// resolved to their full paths, in the module it is in
+pub fn canonical_def(d: @Definition, types: &Types, aliases: &Aliases)
+        -> @Definition {
+    if types.size() == 0 {
+        return d;
+    }
+    @Definition { args: vec::map(d.args, |a| {
+                      canonical(*a, &d.path, types, aliases)
+                  }),
+                  ret: canonical(d.ret, &d.path, types, aliases),
+                  ..copy *d }
+}
+
+// ambiguous finds the type names in a query that could be more than one
+// type, along with the full paths of the types each could be
+pub fn ambiguous(q: &Query, types: &Types) -> ~[(~str, ~[~str])] {
+    let mut found = ~[];
+    if types.size() == 0 {
+        return found;
+    }
+    for vec::append_one(copy q.args, q.ret).each |a| {
+        map_leaves(*a, |l| {
+            match *l {
+                Basic(ref n) => {
+                    let cands = candidates(n, types);
+                    if cands.len() > 1 &&
+                        !vec::any(found, |f| { f.first() == *n }) {
+                        found.push((copy *n, cands));
+                    }
+                }
+                _ => {}
+            }
+            l
+        });
+    }
+    return found;
+}
+
+// resolved gives the full paths of the types a definition has for the
+// ambiguous names of a query, which say which of them it was found for
+pub fn resolved(d: &Definition, amb: &~[(~str, ~[~str])]) -> ~[~str] {
+    let mut found = ~[];
+    if amb.len() == 0 {
+        return found;
+    }
+    for vec::append_one(copy d.args, d.ret).each |a| {
+        map_leaves(*a, |l| {
+            match *l {
+                Basic(ref n) if !vec::contains(found, n) => {
+                    if vec::any(*amb, |p| { vec::contains(p.second(), n) }) {
+                        found.push(copy *n);
+                    }
+                }
+                _ => {}
+            }
+            l
+        });
+    }
+    return found;
+}
+
+// explain says what an ambiguous name could be, for pointing it out
+pub fn explain(name: &~str, paths: &~[~str]) -> ~str {
+    fmt!("%s could be %s", *name, str::connect(*paths, " or "))
+}
+
+#[cfg(test)]
+mod tests {
+
+    fn p(s: &str) -> @Arg {
+        parse::parse_arg(&str::from_slice(s)).get()
+    }
+
+    fn std_types() -> Types {
+        let types = HashMap();
+        for [~"std::io::Error", ~"std::fmt::Error", ~"std::option::Option",
+             ~"demo::Thing"].each |p| {
+            add_type(&types, copy *p);
+        }
+        return types;
+    }
+
+    #[test]
+    fn test_candidates() {
+        let types = std_types();
+        assert candidates(&~"Error", &types) ==
+            ~[~"std::io::Error", ~"std::fmt::Error"];
+        assert candidates(&~"io::Error", &types) == ~[~"std::io::Error"];
+        assert candidates(&~"std::io::Error", &types) == ~[~"std::io::Error"];
+        assert candidates(&~"net::Error", &types) == ~[];
+        assert candidates(&~"int", &types) == ~[];
+    }
+
+    #[test]
+    fn test_canonical() {
+        let types = std_types();
+        let aliases = HashMap();
+        assert canonical(p("Option<Thing>"), &~"demo", &types, &aliases) ==
+            p("std::option::Option<demo::Thing>");
+        assert canonical(p("io::Error"), &~"demo", &types, &aliases) ==
+            p("std::io::Error");
+        // which Error is meant depends on where it is written
+        assert canonical(p("Error"), &~"demo", &types, &aliases) ==
+            p("Error");
+        assert canonical(p("Error"), &~"std::fmt", &types, &aliases) ==
+            p("std::fmt::Error");
+        // a local alias hides the type
+        alias::add_alias(&aliases, alias::parse_alias(&~"type Thing = int",
+                                                      &~"demo").get());
+        assert canonical(p("Thing"), &~"demo", &types, &aliases) ==
+            p("Thing");
+    }
+
+    #[test]
+    fn test_ambiguous() {
+        let types = std_types();
+        let q = query::query(~"(Error, io::Error) -> Option<Error>").get();
+        assert ambiguous(&q, &types) ==
+            ~[(~"Error", ~[~"std::io::Error", ~"std::fmt::Error"])];
+        let d = Definition { name: ~"f", path: ~"std::io", anchor: ~"",
+                             desc: ~"", args: ~[@Basic(~"std::io::Error")],
+                             ret: @Basic(~"int"), signature: ~"",
+                             trait_name: None, crate_name: ~"std",
+                             receiver: false };
+        assert resolved(&d, &ambiguous(&q, &types)) == ~[~"std::io::Error"];
+        assert resolved(&d, &~[]) == ~[];
+    }
+}
@@ -2,126 +2,241 @@
 
 use parse::*;
 
-// query builds Querys from whatever was passed in on the commandline
-pub fn query(q: ~str) -> ~[Query] {
-    let (args, ret, l) = parse_signature(q, None, true);
-    // now create more general variants
-    let mut queries = ~[Query {args: args, ret: ret}];
-
-    generalize_queries(args,ret,l,&mut queries);
-    // only take first 5 generalizations
-    if vec::len(queries) > 5 {
-        vec::truncate(&mut queries, 3);
-    }
-    return queries;
+// query builds a Query from whatever was passed in on the commandline, or
+// says what is wrong with it
+pub fn query(q: ~str) -> Result<Query, ParseError> {
+    let rest = has_rest(&q);
+    match parse_query(q) {
+        Ok((args, ret, _l)) => Ok(Query {args: args, ret: ret, rest: rest}),
+        Err(e) => Err(e)
+    }
 }
 
-// search_type looks for matches from the query in the data, and prints out
-// what it finds
-pub fn search_type(qs: ~[Query], d: &Data) -> ~[@Definition] {
-    let mut results = ~[];
-    for qs.each |q| {
-        let res = match vec::len(q.args) {
-                                0 => search_bucket(&d.ar0, q),
-                                1 => search_bucket(&d.ar1, q),
-                                2 => search_bucket(&d.ar2, q),
-                                3 => search_bucket(&d.ar3, q),
-                                4 => search_bucket(&d.ar4, q),
-                                5 => search_bucket(&d.ar5, q),
-                                _ => search_bucket(&d.arn, q)
-                            };
-        results.push_all_move(res);
+// with_crates takes any crate:name restrictions off the front of a query,
+// adding them to the options the query is searched with
+pub fn with_crates(q: &~str, opts: &SearchOpts) -> (~str, SearchOpts) {
+    let mut crates = copy opts.crates;
+    let mut rest = str::trim(*q);
+    while str::starts_with(rest, "crate:") {
+        let end = option::get_default(&str::find_char(rest, ' '), rest.len());
+        crates.push(str::slice(rest, 6, end));
+        rest = str::trim(str::slice(rest, end, rest.len()));
     }
-    return results;
+    return (rest, SearchOpts { crates: crates, ..copy *opts });
 }
 
-// search_name looks for a function by name, prefix only
-pub fn search_name(q: ~str, d: &Data) -> ~[@Definition] {
-    let mut name = copy q;
-    let mut results = ~[];
-    search_trie(d.names, &mut name, &q, &mut results);
-    return results;
+// split_name takes the name off of a combined query, written either as
+// name :: type (find :: [A] -> Option<A>) or with a name: prefix (name:split
+// str -> _). it gives the name, if there was one, and the rest of the query,
+// which is always the end of the one given.
+pub fn split_name(q: &~str) -> (Option<~str>, ~str) {
+    let q = str::trim_left(*q);
+    if str::starts_with(q, "name:") {
+        let end = option::get_default(&str::find_char(q, ' '), q.len());
+        return (Some(str::slice(q, 5, end)),
+                str::trim_left(str::slice(q, end, q.len())));
+    }
+    match str::find_str(q, " :: ") {
+        // the name has to be a single word, so paths and types with spaces
+        // in them aren't mistaken for one
+        Some(i) if i > 0 && !str::contains(str::slice(q, 0, i), " ") => {
+            (Some(str::slice(q, 0, i)),
+             str::trim_left(str::slice(q, i + 4, q.len())))
+        }
+        _ => (None, q)
+    }
 }
 
-// search_bucket looks for matches in a bucket
-fn search_bucket(b: &Bucket, q: &Query) -> ~[@Definition] {
-    let mut results = ~[];
-    let q_args = set_from_vec(&q.args);
+// is_type_query says whether a query is by type (rather than by name)
+pub fn is_type_query(q: &~str) -> bool {
+    q.contains(~"->") || q.contains(~",")
+}
 
-    for b.defs.each |d| {
-        let d_args = set_from_vec(&d.args);
-        let e = set_equals(&d_args, &q_args);
-        if e && d.ret == q.ret {
-            results.push(*d);
+// search runs a query by type, by name, or by both, whichever it is, and
+// gives which kind it was along with the results. a type that doesn't
+// parse is an error, with its position in the query given.
+pub fn search(q: &~str, d: &Data, opts: &SearchOpts)
+        -> Result<(~str, ~[Match]), ParseError> {
+    let (name, ty) = split_name(q);
+    // where the type starts, for pointing out problems in it
+    let offset = q.len() - ty.len();
+    match name {
+        Some(ref n) if ty.len() > 0 => match query(copy ty) {
+            Ok(query) => Ok((~"combined", search_named(n, &query, d, opts))),
+            Err(e) => Err(ParseError { pos: e.pos + offset, ..e })
+        },
+        Some(n) => Ok((~"name", search_name(n, d, opts))),
+        None if is_type_query(&ty) => match query(copy ty) {
+            Ok(query) => Ok((~"type", search_type(&query, d, opts))),
+            Err(e) => Err(ParseError { pos: e.pos + offset, ..e })
+        },
+        None => Ok((~"name", search_name(ty, d, opts)))
+    }
+}
+
+// ambiguities gives the type names in a query that could be more than one
+// type, with the full paths of the types each could be, so they can be
+// pointed out along with the results. name queries have none, and neither
+// do queries that don't parse (search says what is wrong with them).
+pub fn ambiguities(q: &~str, d: &Data) -> ~[(~str, ~[~str])] {
+    let (name, ty) = split_name(q);
+    if ty.len() == 0 || (name.is_none() && !is_type_query(&ty)) {
+        return ~[];
+    }
+    match query(ty) {
+        Ok(query) => paths::ambiguous(&query, &d.types),
+        Err(_) => ~[]
+    }
+}
+
+// search_type looks for matches from the query in the data, returning
+// the best of them, best first
+pub fn search_type(q: &Query, d: &Data, opts: &SearchOpts) -> ~[Match] {
+    let buckets = [&d.ar0, &d.ar1, &d.ar2, &d.ar3, &d.ar4, &d.ar5, &d.arn];
+    let mut i = uint::min(vec::len(q.args), buckets.len() - 1);
+    let mut results = search_bucket(buckets[i], q, d, opts);
+    if opts.partial || q.rest {
+        // definitions may take more arguments than the query gave, so every
+        // bigger bucket has candidates too
+        i += 1;
+        while i < buckets.len() {
+            results.push_all_move(search_bucket(buckets[i], q, d, opts));
+            i += 1;
         }
     }
+    return best(score::sort_matches(results));
+}
 
-    // only give 10 responses per query
-    if results.len() > 10 {
-        return vec::slice(results,0,10);
-    } else {
-        return results;
+// search_name looks for definitions by name, which can match exactly, as
+// a prefix, by segments (ec is each_char), anywhere in the name, or with a
+// typo or two (see names.rs). the best of them are returned, best first.
+pub fn search_name(q: ~str, d: &Data, opts: &SearchOpts) -> ~[Match] {
+    let mut results = ~[];
+    for d.names.each |name, defs| {
+        match names::match_name(&q, &name) {
+            Some(m) => {
+                for defs.each |def| {
+                    if opts.allows(*def) {
+                        results.push(Match {
+                            def: *def,
+                            score: score::name_score(&q, m, *def, d),
+                            coercions: ~[],
+                            receiver: false,
+                            resolved: ~[],
+                            how: names::how_named(m)
+                        });
+                    }
+                }
+            }
+            None => {}
+        }
     }
+    return best(score::sort_matches(results));
 }
 
-// search_trie looks for matching definitions by name
-fn search_trie(t: @Trie, n: &mut ~str, q: &~str, r: &mut ~[@Definition]) {
-    fn find_defs(t: @Trie, q: &~str, r: &mut ~[@Definition]) {
-        // go through everything at this level, and any deeper
-        r.push_all_move(vec::filter(t.defs, |d| {
-            str::contains(d.name, *q)
-        }));
-        for t.children.each_value |c| {
-            find_defs(c, q, r);
+// search_named looks for definitions whose names contain name, ranked by
+// how well their types match the query. those that match it come first,
+// and the rest by how much of it they have.
+pub fn search_named(name: &~str, q: &Query, d: &Data, opts: &SearchOpts)
+        -> ~[Match] {
+    let buckets = [&d.ar0, &d.ar1, &d.ar2, &d.ar3, &d.ar4, &d.ar5, &d.arn];
+    let expanded = expand_aliases(q, d, opts);
+    let amb = paths::ambiguous(q, &d.types);
+    let mut results = ~[];
+    for buckets.each |b| {
+        for b.defs.each |def| {
+            if !str::contains(def.name, *name) || !opts.allows(*def) {
+                loop;
+            }
+            match match_aliased(q, &expanded, *def, d, opts) {
+                Some(u) => {
+                    results.push(Match {
+                        def: *def,
+                        score: score::type_score(&u, *def, d),
+                        coercions: copy u.coercions,
+                        receiver: u.receiver,
+                        resolved: paths::resolved(*def, &amb),
+                        how: score::how_typed(&u)
+                    });
+                }
+                None => {
+                    results.push(Match {
+                        def: *def,
+                        score: score::similarity_score(q, *def, opts, d),
+                        coercions: ~[],
+                        receiver: false,
+                        resolved: paths::resolved(*def, &amb),
+                        // found by name, without the type matching
+                        how: ~"unmatched"
+                    });
+                }
+            }
         }
     }
-    if n.len() == 0 {
-        // at level, look for definition
-        find_defs(t, q, r);
-    } else {
-        // look deeper, if we can
-        let c = str::from_char(str::shift_char(n));
-        match t.children.find(c) {
-            None => return,
-            Some(child) => search_trie(child, n, q, r)
+    return best(score::sort_matches(results));
+}
+
+// search_bucket looks for matches in a bucket. a definition matches if it
+// unifies with the query (see unify.rs)
+fn search_bucket(b: &Bucket, q: &Query, data: &Data, opts: &SearchOpts)
+        -> ~[Match] {
+    let mut results = ~[];
+    let expanded = expand_aliases(q, data, opts);
+    let amb = paths::ambiguous(q, &data.types);
+    for b.defs.each |d| {
+        if !opts.allows(*d) {
+            loop;
+        }
+        match match_aliased(q, &expanded, *d, data, opts) {
+            Some(u) => {
+                results.push(Match { def: *d,
+                                     score: score::type_score(&u, *d, data),
+                                     coercions: copy u.coercions,
+                                     receiver: u.receiver,
+                                     resolved: paths::resolved(*d, &amb),
+                                     how: score::how_typed(&u) });
+            }
+            None => {}
         }
     }
+    return results;
 }
 
-// generalize_queries creates more general versions of queries
-// by replacing concrete types with polymorphic variables
-// note that how we are doing it now, it will generate (lots of) duplicate
-// queries. l is the next available polymorphic variable letter
-fn generalize_queries(args: ~[@Arg], ret: @Arg, l: uint, q: &mut ~[Query]) {
-    let arg_names = HashMap();
-    fn get_arg_names(a: @Arg, n: &HashMap<@~str,()>) {
-        match a {
-            @Basic(ref name) => {n.insert(@copy *name,());},
-            @Vec(inner) => get_arg_names(inner, n),
-            @Tuple(inner) => {vec::map(inner, |a| {get_arg_names(*a, n)});},
-            @Parametric(base,inner) => {
-                get_arg_names(base, n);
-                vec::map(inner, |a| {get_arg_names(*a, n)});
-            },
-            @Function(args,ret) => {
-                get_arg_names(ret, n);
-                vec::map(args, |a| {get_arg_names(*a, n)});
-            },
-            @Constrained(_,_) => {}
+// expand_aliases gives the query with any aliases in it expanded, if it has
+// some and they are to be
+fn expand_aliases(q: &Query, data: &Data, opts: &SearchOpts)
+        -> Option<Query> {
+    if opts.aliases { alias::expand_query(q, &data.aliases) } else { None }
+}
+
+// match_aliased matches a definition against a query as they are, and if
+// that fails, with the aliases in both expanded (see alias.rs). expanded
+// is the query with its aliases expanded, if it has any.
+fn match_aliased(q: &Query, expanded: &Option<Query>, d: @Definition,
+                 data: &Data, opts: &SearchOpts) -> Option<unify::Unified> {
+    match unify::match_def(q, d, opts, &data.impls) {
+        Some(u) => return Some(u),
+        None => {}
+    }
+    if !opts.aliases {
+        return None;
+    }
+    let eq = match *expanded { Some(ref e) => e, None => q };
+    match alias::expand_def(d, &data.aliases) {
+        Some(ed) => unify::match_def(eq, ed, opts, &data.impls),
+        None if expanded.is_some() => {
+            unify::match_def(eq, d, opts, &data.impls)
         }
-        ;
-    }
-    vec::map(args, |a| {get_arg_names(*a,&arg_names)});
-    get_arg_names(ret,&arg_names);
-    // now for all that aren't polymorphic, make them and
-    // search recursively.
-    for arg_names.each_key |n| {
-        let old = @Basic(*n);
-        let nn = @Constrained(*letters(l), ~[]);
-        let nargs = vec::map(args, |a| { replace_arg(*a,old,nn) });
-        let nret = replace_arg(ret,old,nn);
-        q.push(Query {args: nargs, ret: nret});
-        generalize_queries(nargs, nret, l+1, q);
+        None => None
+    }
+}
+
+// best keeps only the top results - past that they stop being useful
+fn best(ms: ~[Match]) -> ~[Match] {
+    if ms.len() > 10 {
+        return vec::slice(ms,0,10);
+    } else {
+        return ms;
     }
 }
 
@@ -132,55 +247,203 @@ mod tests {
     fn test_search_bucket() {
         let def = @Definition { name: ~"foo", path: ~"foo",
             desc: ~"", anchor: ~"function-foo", args: ~[],
-            ret: @Basic(~"()"), signature: ~"fn foo()"};
+            ret: @Basic(~"()"), signature: ~"fn foo()", trait_name: None,
+            crate_name: ~"foo", receiver: false };
         let bucket = Bucket {defs: ~[def]};
-        let query = Query { args: ~[], ret: copy def.ret };
-        assert search_bucket(&bucket, &query) == ~[def];
+        let data = empty_data();
+        let opts = default_opts();
+        let query = Query { args: ~[], ret: copy def.ret, rest: false };
+        assert search_bucket(&bucket, &query, &data, &opts).map(|m| { m.def })
+            == ~[def];
 
-        let query2 = Query { args: ~[copy def.ret], ret: copy def.ret };
-        assert search_bucket(&bucket, &query2) == ~[];
+        let query2 = Query { args: ~[copy def.ret], ..query };
+        assert search_bucket(&bucket, &query2, &data, &opts) == ~[];
     }
 
     #[test]
-    fn test_search_trie() {
+    fn test_search_bucket_crates() {
         let def = @Definition { name: ~"foo", path: ~"foo",
             desc: ~"", anchor: ~"function-foo", args: ~[],
-            ret: @Basic(~"()"), signature: ~"fn foo()"};
-        let trie =
-            @Trie { children: HashMap(),
-                    defs: ~[def]};
-        let mut n = ~"";
-        let q = ~"fo";
-        let mut r = ~[];
-        search_trie(trie, &mut n, &q, &mut r);
-        assert r == ~[def];
-
-        let trie2 = @Trie { children: HashMap(),
-                            defs: ~[]};
-        trie2.children.insert(~"f", trie);
-        r = ~[];
-        n = ~"f";
-        search_trie(trie2, &mut n, &q, &mut r);
-        assert r == ~[def];
-
-        r = ~[];
-        n = ~"a";
-        search_trie(trie2, &mut n, &q, &mut r);
-        assert r == ~[];
+            ret: @Basic(~"()"), signature: ~"fn foo()", trait_name: None,
+            crate_name: ~"foo", receiver: false };
+        let bucket = Bucket {defs: ~[def]};
+        let data = empty_data();
+        let query = Query { args: ~[], ret: copy def.ret, rest: false };
+        let opts = SearchOpts { crates: ~[~"std"], ..default_opts() };
+        assert search_bucket(&bucket, &query, &data, &opts) == ~[];
+        let opts2 = SearchOpts { crates: ~[~"std", ~"foo"], ..default_opts() };
+        assert search_bucket(&bucket, &query, &data, &opts2).len() == 1;
+    }
+
+    #[test]
+    fn test_query_holes() {
+        let a = @Constrained(~"A", ~[]);
+        assert query(~"(_, [A], ..) -> Option<A>").get() ==
+            Query { args: ~[@Hole, @Vec(a)],
+                    ret: @Parametric(@Basic(~"Option"), ~[a]), rest: true };
+        assert !query(~"(_, [A]) -> _").get().rest;
+        assert query(~"(..) -> A").get() ==
+            Query { args: ~[], ret: a, rest: true };
+    }
+
+    #[test]
+    fn test_with_crates() {
+        let (q, opts) = with_crates(&~"crate:std crate:core Option<A> -> A",
+                                    &default_opts());
+        assert q == ~"Option<A> -> A";
+        assert opts.crates == ~[~"std", ~"core"];
+        let (q2, opts2) = with_crates(&~"each", &default_opts());
+        assert q2 == ~"each";
+        assert opts2.crates == ~[];
+    }
+
+    #[test]
+    fn test_split_name() {
+        assert split_name(&~"find :: [A] -> Option<A>") ==
+            (Some(~"find"), ~"[A] -> Option<A>");
+        assert split_name(&~"name:split str -> _") ==
+            (Some(~"split"), ~"str -> _");
+        assert split_name(&~"name:split") == (Some(~"split"), ~"");
+        assert split_name(&~"core::vec::each") == (None, ~"core::vec::each");
+        assert split_name(&~"A, B :: C -> A") == (None, ~"A, B :: C -> A");
+    }
+
+    #[test]
+    fn test_search_named() {
+        fn def(name: ~str, args: ~[@Arg], ret: @Arg) -> @Definition {
+            @Definition { name: name, path: ~"core::vec", desc: ~"",
+                          anchor: ~"", args: args, ret: ret,
+                          signature: ~"", trait_name: None,
+                          crate_name: ~"core", receiver: false }
+        }
+        let a = @Constrained(~"A", ~[]);
+        let opt = @Parametric(@Basic(~"Option"), ~[a]);
+        let find = def(~"find", ~[@Vec(a)], opt);
+        let find_str = def(~"find_str", ~[@Basic(~"str"), @Basic(~"str")],
+                           @Parametric(@Basic(~"Option"), ~[@Basic(~"uint")]));
+        let head = def(~"head", ~[@Vec(a)], opt);
+        let data = Data { ar1: Bucket { defs: ~[find, head] },
+                          ar2: Bucket { defs: ~[find_str] },
+                          ..empty_data() };
+        let opts = default_opts();
+        // the type filters nothing out, but puts what matches it first
+        let ms = search_named(&~"find", &query(~"[A] -> Option<A>").get(),
+                              &data, &opts);
+        assert ms.map(|m| { m.def }) == ~[find, find_str];
+        assert ms[0].score < ms[1].score;
+
+        match search(&~"find :: [A] -> Option<A>", &data, &opts) {
+            Ok((kind, ms)) => {
+                assert kind == ~"combined";
+                assert ms.len() == 2;
+            }
+            Err(_) => fail
+        }
+        match search(&~"[A] -> Option<A>", &data, &opts) {
+            Ok((kind, ms)) => {
+                assert kind == ~"type";
+                assert ms.map(|m| { m.def }) == ~[find, head];
+            }
+            Err(_) => fail
+        }
+        // problems are pointed out in the whole query, not just its type
+        match search(&~"name:find [int -> int", &data, &opts) {
+            Ok(_) => fail,
+            Err(e) => assert e.pos == 10
+        }
     }
 
     #[test]
-    fn test_generalize_queries() {
-        let args = ~[@Constrained(~"A", ~[]),
-                     @Basic(~"str"),
-                     @Constrained(~"A", ~[])];
-        let ret = @Constrained(~"A", ~[]);
-        let mut queries = ~[];
-        generalize_queries(args, ret, 1, &mut queries);
-        assert queries == ~[Query { args: ~[@Constrained(~"A", ~[]),
-                                            @Constrained(~"B", ~[]),
-                                            @Constrained(~"A", ~[])],
-                                    ret: @Constrained(~"A", ~[])}];
+    fn test_search_name() {
+        fn def(name: ~str, path: ~str) -> @Definition {
+            @Definition { name: name, path: path, desc: ~"", anchor: ~"",
+                          args: ~[], ret: @Basic(~"()"), signature: ~"",
+                          trait_name: None, crate_name: ~"core",
+                          receiver: false }
+        }
+        let each = def(~"each", ~"core::vec");
+        let each_char = def(~"each_char", ~"core::str");
+        let char_at = def(~"char_at", ~"core::str");
+        let reach = def(~"reach", ~"core::vec");
+        let map = def(~"map", ~"core::vec");
+        let data = empty_data();
+        for [each, each_char, char_at, reach, map].each |d| {
+            names::add_name(&data.names, *d);
+        }
+        fn found(q: ~str, data: &Data) -> ~[@Definition] {
+            search_name(q, data, &default_opts()).map(|m| { m.def })
+        }
+        assert found(~"each", &data) == ~[each, each_char, reach];
+        assert found(~"char", &data) == ~[char_at, each_char];
+        assert found(~"ec", &data) == ~[each_char];
+        assert found(~"eahc", &data) == ~[each, each_char];
+        assert found(~"filter", &data) == ~[];
+        let opts2 = SearchOpts { crates: ~[~"std"], ..default_opts() };
+        assert search_name(~"each", &data, &opts2) == ~[];
     }
 
+    #[test]
+    fn test_search_bucket_polymorphic() {
+        let def = @Definition { name: ~"get", path: ~"core::option",
+            desc: ~"", anchor: ~"function-get",
+            args: ~[@Parametric(@Basic(~"Option"),
+                                ~[@Constrained(~"A", ~[])])],
+            ret: @Constrained(~"A", ~[]),
+            signature: ~"fn get<T>(opt: Option<T>) -> T", trait_name: None,
+            crate_name: ~"core", receiver: false };
+        let bucket = Bucket {defs: ~[def]};
+        let data = empty_data();
+        let opts = default_opts();
+        assert search_bucket(&bucket, &query(~"Option<int> -> int").get(),
+                             &data, &opts).map(|m| { m.def }) == ~[def];
+        assert search_bucket(&bucket, &query(~"Option<int> -> uint").get(),
+                             &data, &opts) == ~[];
+    }
+
+    #[test]
+    fn test_search_aliases() {
+        let recs = ~[json::from_str(
+            ~"{\"name\": \"flush\", \"path\": \"std::io\", \
+               \"anchor\": \"fn.flush\", \"desc\": \"\", \"self\": \"\", \
+               \"type\": \"fn flush(w: &mut Writer) -> Result<()>\"}").get(),
+                     json::from_str(
+            ~"{\"kind\": \"type\", \"name\": \"Result\", \
+               \"path\": \"std::io\", \
+               \"type\": \"type Result<T> = Result<T, Error>\"}").get()];
+        let data = load::from_records(&recs);
+        let q = query(~"Writer -> Result<(), io::Error>").get();
+        assert search_type(&q, &data, &default_opts()).len() == 1;
+        let q2 = query(~"Writer -> io::Result<()>").get();
+        assert search_type(&q2, &data, &default_opts()).len() == 1;
+        let plain = SearchOpts { aliases: false, ..default_opts() };
+        assert search_type(&q, &data, &plain) == ~[];
+    }
+
+    #[test]
+    fn test_search_paths() {
+        let recs = ~[json::from_str(
+            ~"{\"name\": \"kind\", \"path\": \"std::io\", \
+               \"anchor\": \"fn.kind\", \"desc\": \"\", \"self\": \"\", \
+               \"type\": \"fn kind(e: &Error) -> int\"}").get(),
+                     json::from_str(
+            ~"{\"kind\": \"struct\", \"name\": \"Error\", \
+               \"path\": \"std::io\"}").get(),
+                     json::from_str(
+            ~"{\"kind\": \"struct\", \"name\": \"Error\", \
+               \"path\": \"std::fmt\"}").get()];
+        let data = load::from_records(&recs);
+        assert ambiguities(&~"&Error -> int", &data) ==
+            ~[(~"Error", ~[~"std::io::Error", ~"std::fmt::Error"])];
+        assert ambiguities(&~"&io::Error -> int", &data) == ~[];
+        assert ambiguities(&~"kind", &data) == ~[];
+        let ms = search_type(&query(~"&Error -> int").get(), &data,
+                             &default_opts());
+        assert ms.len() == 1;
+        assert ms[0].resolved == ~[~"std::io::Error"];
+        // a short name can be written with as much of its path as needed
+        let q = query(~"&std::io::Error -> int").get();
+        assert search_type(&q, &data, &default_opts()).len() == 1;
+        let q2 = query(~"&fmt::Error -> int").get();
+        assert search_type(&q2, &data, &default_opts()) == ~[];
+    }
 }
\ No newline at end of file
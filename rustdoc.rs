@@ -0,0 +1,566 @@
+//! This file contains code to load the json that rustdoc produces (with
+//! --output-format json) directly, instead of scraping its html
+
+use types::*;
+
+// load reads a rustdoc json file, and builds the same Data that loading a
+// rustle.data file would
+pub fn load(path: path::Path) -> Data {
+    load::from_records(&records(&load::read_json(&path)))
+}
+
+// records turns the items in rustdoc's index into the records that a
+// rustle.data file is made of (see load.rs), so that they can be loaded in
+// exactly the same way. only items from the documented crate are included.
+pub fn records(doc: &Json) -> ~[Json] {
+    let index = get(doc, ~"index");
+    let paths = get(doc, ~"paths");
+    let krate = match get(&get(&index, id_key(&get(doc, ~"root"))),
+                          ~"name") {
+        String(s) => s,
+        _ => ~""
+    };
+    let mut recs = ~[];
+    match index {
+        Object(items) => {
+            for items.each |id, item| {
+                if get(item, ~"crate_id") != Number(0f) {
+                    loop;
+                }
+                let inner = get(item, ~"inner");
+                // older versions of the format called aliases typedefs
+                let alias = match get(&inner, ~"type_alias") {
+                    Null => get(&inner, ~"typedef"),
+                    a => a
+                };
+                if alias != Null {
+                    let p = get(&paths, copy *id);
+                    if p != Null {
+                        recs.push(alias_record(item, &alias, &module_of(&p)));
+                    }
+                    loop;
+                }
+                match (get(&inner, ~"function"), get(&inner, ~"trait"),
+                       get(&inner, ~"impl")) {
+                    // methods are functions too, but they aren't in paths -
+                    // they are picked up from their trait or impl instead
+                    (Null, Null, Null) => {}
+                    (f, Null, Null) => {
+                        let p = get(&paths, copy *id);
+                        if p != Null {
+                            recs.push(fn_record(item, &f, &module_of(&p)));
+                        }
+                    }
+                    (Null, t, Null) => {
+                        let p = get(&paths, copy *id);
+                        recs.push(trait_record(item, &t, &module_of(&p),
+                                               &index));
+                    }
+                    (Null, Null, i) => {
+                        recs.push_all_move(impl_records(&i, &paths, &index,
+                                                        &krate));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+    // paths has the types of other crates as well, which are just as much
+    // what the names in signatures refer to
+    match paths {
+        Object(ps) => {
+            for ps.each |_id, p| {
+                match str_of(&get(p, ~"kind")) {
+                    ~"struct" | ~"enum" | ~"union" => {
+                        recs.push(type_record(p));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+    return recs;
+}
+
+// type_record makes the record for the declaration of a type, from its
+// entry in paths
+fn type_record(p: &Json) -> Json {
+    let segs = list_of(&get(p, ~"path"));
+    let name = if segs.len() == 0 { ~"" } else { str_of(&segs.last()) };
+    record(~[(~"kind", String(str_of(&get(p, ~"kind")))),
+             (~"name", String(name)),
+             (~"path", String(module_of(p)))])
+}
+
+// fn_record makes the record for a free function
+fn fn_record(item: &Json, f: &Json, path: &~str) -> Json {
+    let name = str_of(&get(item, ~"name"));
+    let (sig, _has_self) = render_fn(&name, f, &~[]);
+    record(~[(~"anchor", String(~"fn." + name)),
+             (~"name", String(copy name)),
+             (~"type", String(sig)),
+             (~"self", String(~"")),
+             (~"desc", String(summary(&get(item, ~"docs")))),
+             (~"path", String(copy *path))])
+}
+
+// alias_record makes the record for a type alias, which is written out as
+// its declaration
+fn alias_record(item: &Json, a: &Json, path: &~str) -> Json {
+    let name = str_of(&get(item, ~"name"));
+    let (params, _wheres) = render_generics(&~[get(a, ~"generics")]);
+    let generics = if params.len() == 0 { ~"" }
+                   else { fmt!("<%s>", str::connect(params, ", ")) };
+    record(~[(~"kind", String(~"type")),
+             (~"name", String(copy name)),
+             (~"type", String(fmt!("type %s%s = %s", name, generics,
+                                   render_type(&get(a, ~"type"))))),
+             (~"path", String(copy *path))])
+}
+
+// trait_record makes the record for a trait declaration, with its methods
+fn trait_record(item: &Json, t: &Json, path: &~str, index: &Json) -> Json {
+    let methods = methods(&get(t, ~"items"), index, &~[]);
+    record(~[(~"kind", String(~"trait")),
+             (~"anchor", String(~"trait." + str_of(&get(item, ~"name")))),
+             (~"name", get(item, ~"name")),
+             (~"desc", String(summary(&get(item, ~"docs")))),
+             (~"path", String(copy *path)),
+             (~"methods", List(methods))])
+}
+
+// impl_records makes the records for an impl. the methods of an inherent
+// impl are just methods on the type, while trait impls become impl records.
+// impls rustdoc made up (auto traits, blanket impls) are left out.
+fn impl_records(i: &Json, paths: &Json, index: &Json, krate: &~str)
+        -> ~[Json] {
+    if is_synthetic(i) || get(i, ~"blanket_impl") != Null {
+        return ~[];
+    }
+    let self = render_type(&get(i, ~"for"));
+    // methods live in the module of the type they are on
+    let path = match get(&get(&get(i, ~"for"), ~"resolved_path"), ~"id") {
+        Null => copy *krate,
+        id => module_of(&get(paths, id_key(&id)))
+    };
+    let generics = ~[get(i, ~"generics")];
+    match get(i, ~"trait") {
+        Null => {
+            let mut recs = ~[];
+            for list_of(&get(i, ~"items")).each |id| {
+                let item = get(index, id_key(id));
+                let f = get(&get(&item, ~"inner"), ~"function");
+                if f == Null { loop; }
+                let name = str_of(&get(&item, ~"name"));
+                let (sig, has_self) = render_fn(&name, &f, &generics);
+                // a static method is just a function with no self type
+                recs.push(record(~[
+                    (~"anchor", String(~"method." + name)),
+                    (~"name", String(copy name)),
+                    (~"type", String(sig)),
+                    (~"self", String(if has_self { copy self }
+                                     else { ~"" })),
+                    (~"desc", String(summary(&get(&item, ~"docs")))),
+                    (~"path", String(copy path))]));
+            }
+            recs
+        }
+        t => {
+            ~[record(~[(~"kind", String(~"impl")),
+                       (~"trait", String(render_path(&t))),
+                       (~"for", String(copy self)),
+                       (~"path", String(path)),
+                       (~"methods", List(methods(&get(i, ~"items"), index,
+                                                 &generics)))])]
+        }
+    }
+}
+
+// methods makes the method records for the items of a trait or impl
+// (anything that isn't a function, like an associated type, is skipped).
+// methods without a self argument are static.
+fn methods(ids: &Json, index: &Json, generics: &~[Json]) -> ~[Json] {
+    let mut ms = ~[];
+    for list_of(ids).each |id| {
+        let item = get(index, id_key(id));
+        let f = get(&get(&item, ~"inner"), ~"function");
+        if f == Null { loop; }
+        let name = str_of(&get(&item, ~"name"));
+        let (sig, has_self) = render_fn(&name, &f, generics);
+        ms.push(record(~[(~"anchor", String(~"method." + name)),
+                         (~"name", String(copy name)),
+                         (~"type", String(if has_self { sig }
+                                          else { ~"static " + sig })),
+                         (~"desc", String(summary(&get(&item, ~"docs"))))]));
+    }
+    return ms;
+}
+
+// render_fn writes out the signature of a function the way the parser
+// expects it, along with whether it has a self argument. the self argument
+// is written the way it would be in the source (&mut self), as the loader
+// only gets how it is taken from there. the generics of an enclosing impl
+// are included with the function's own.
+fn render_fn(name: &~str, f: &Json, outer: &~[Json]) -> (~str, bool) {
+    // older versions of the format called the signature a decl
+    let sig = match get(f, ~"sig") { Null => get(f, ~"decl"), s => s };
+    let mut has_self = false;
+    let mut args = ~[];
+    for list_of(&get(&sig, ~"inputs")).each |input| {
+        let pair = list_of(input);
+        let n = str_of(&pair[0]);
+        let ty = render_type(&pair[1]);
+        if n == ~"self" {
+            has_self = true;
+            args.push(match ty {
+                ~"Self" | ~"&Self" | ~"&mut Self" => {
+                    str::replace(ty, ~"Self", ~"self")
+                }
+                _ => ~"self: " + ty
+            });
+            loop;
+        }
+        args.push(fmt!("%s: %s", n, ty));
+    }
+    let ret = match get(&sig, ~"output") {
+        Null => ~"",
+        t => ~" -> " + render_type(&t)
+    };
+    let all = vec::append(copy *outer, ~[get(f, ~"generics")]);
+    let (params, wheres) = render_generics(&all);
+    let generics = if params.len() == 0 { ~"" }
+                   else { fmt!("<%s>", str::connect(params, ", ")) };
+    let clause = if wheres.len() == 0 { ~"" }
+                 else { ~" where " + str::connect(wheres, ", ") };
+    return (fmt!("fn %s%s(%s)%s%s", *name, generics,
+                 str::connect(args, ", "), ret, clause), has_self);
+}
+
+// render_generics writes out the type parameters (with their bounds) and
+// the where predicates of some generics. lifetimes are left out, but const
+// parameters are kept, as they are declared like type parameters.
+fn render_generics(gs: &~[Json]) -> (~[~str], ~[~str]) {
+    let mut params = ~[];
+    let mut wheres = ~[];
+    for gs.each |g| {
+        for list_of(&get(g, ~"params")).each |p| {
+            let kind = get(p, ~"kind");
+            let cnst = get(&kind, ~"const");
+            if cnst != Null {
+                params.push(fmt!("const %s: %s", str_of(&get(p, ~"name")),
+                                 render_type(&get(&cnst, ~"type"))));
+                loop;
+            }
+            let ty = get(&kind, ~"type");
+            if ty == Null { loop; }
+            // impl Trait arguments show up as synthetic parameters
+            if is_synthetic(&ty) { loop; }
+            let b = render_bound(str_of(&get(p, ~"name")),
+                                 &get(&ty, ~"bounds"));
+            // only the parameters of types (and aliases) have defaults
+            params.push(match get(&ty, ~"default") {
+                Null => b,
+                d => fmt!("%s = %s", b, render_type(&d))
+            });
+        }
+        for list_of(&get(g, ~"where_predicates")).each |w| {
+            let b = get(w, ~"bound_predicate");
+            if b == Null { loop; }
+            // older versions of the format called the type ty
+            let t = match get(&b, ~"type") { Null => get(&b, ~"ty"), t => t };
+            wheres.push(render_bound(render_type(&t), &get(&b, ~"bounds")));
+        }
+    }
+    return (params, wheres);
+}
+
+// render_bound writes out a type and the traits it is bound by
+fn render_bound(ty: ~str, bounds: &Json) -> ~str {
+    let traits = render_bounds(bounds);
+    if traits.len() == 0 { ty }
+    else { fmt!("%s: %s", ty, str::connect(traits, " + ")) }
+}
+
+// render_bounds writes out each trait in a list of bounds
+fn render_bounds(bounds: &Json) -> ~[~str] {
+    let mut traits = ~[];
+    for list_of(bounds).each |b| {
+        let t = get(b, ~"trait_bound");
+        if t == Null { loop; }
+        let name = render_path(&get(&t, ~"trait"));
+        traits.push(if get(&t, ~"modifier") == String(~"maybe") {
+            ~"?" + name
+        } else {
+            name
+        });
+    }
+    return traits;
+}
+
+// render_type writes out a type in rust syntax
+pub fn render_type(t: &Json) -> ~str {
+    match *t {
+        // the inferred type (_) is just a string
+        String(ref s) if *s == ~"infer" => ~"_",
+        Object(ref o) => {
+            // a type is an object with a single key, saying what kind it is
+            for o.each |kind, v| {
+                return render_kind(kind, v);
+            }
+            ~"()"
+        }
+        _ => ~"()"
+    }
+}
+
+fn render_kind(kind: &~str, v: &Json) -> ~str {
+    match *kind {
+        ~"primitive" | ~"generic" => str_of(v),
+        ~"resolved_path" => render_path(v),
+        ~"tuple" => fmt!("(%s)", str::connect(list_of(v).map(|t| {
+            render_type(t)
+        }), ", ")),
+        ~"slice" => fmt!("[%s]", render_type(v)),
+        ~"array" => fmt!("[%s; %s]", render_type(&get(v, ~"type")),
+                         str_of(&get(v, ~"len"))),
+        ~"borrowed_ref" => {
+            let m = if get(v, ~"is_mutable") == Boolean(true) { ~"mut " }
+                    else { ~"" };
+            fmt!("&%s%s", m, render_type(&get(v, ~"type")))
+        }
+        ~"raw_pointer" => {
+            let m = if get(v, ~"is_mutable") == Boolean(true) { ~"mut " }
+                    else { ~"const " };
+            fmt!("*%s%s", m, render_type(&get(v, ~"type")))
+        }
+        ~"function_pointer" => {
+            let (sig, _has_self) = render_fn(&~"", v, &~[]);
+            sig
+        }
+        ~"impl_trait" => ~"impl " + str::connect(render_bounds(v), " + "),
+        ~"dyn_trait" => {
+            let traits = list_of(&get(v, ~"traits")).map(|t| {
+                render_path(&get(t, ~"trait"))
+            });
+            ~"dyn " + str::connect(traits, " + ")
+        }
+        ~"qualified_path" => {
+            fmt!("<%s as %s>::%s", render_type(&get(v, ~"self_type")),
+                 render_path(&get(v, ~"trait")), str_of(&get(v, ~"name")))
+        }
+        _ => copy *kind
+    }
+}
+
+// render_path writes out a path to a type or trait, with its arguments.
+// only the last segment of the path is used, as that is how types are
+// written in signatures.
+fn render_path(p: &Json) -> ~str {
+    // older versions of the format called the path a name
+    let full = match get(p, ~"path") { Null => get(p, ~"name"), n => n };
+    let segs = str::split_str(str_of(&full), "::");
+    let name = copy segs[segs.len()-1];
+    let args = get(p, ~"args");
+    match (get(&args, ~"angle_bracketed"), get(&args, ~"parenthesized")) {
+        (Null, Null) => name,
+        (Null, paren) => {
+            // Fn(A, B) -> C
+            let inputs = list_of(&get(&paren, ~"inputs")).map(|t| {
+                render_type(t)
+            });
+            let ret = match get(&paren, ~"output") {
+                Null => ~"",
+                t => ~" -> " + render_type(&t)
+            };
+            fmt!("%s(%s)%s", name, str::connect(inputs, ", "), ret)
+        }
+        (angle, _) => {
+            let mut params = ~[];
+            for list_of(&get(&angle, ~"args")).each |a| {
+                match get(a, ~"type") {
+                    Null => {}
+                    t => params.push(render_type(&t))
+                }
+            }
+            // associated types bound with it (Iterator<Item = T>), under
+            // constraints, or bindings in older versions of the format
+            let cs = match get(&angle, ~"constraints") {
+                Null => get(&angle, ~"bindings"),
+                cs => cs
+            };
+            for list_of(&cs).each |c| {
+                let eq = get(&get(c, ~"binding"), ~"equality");
+                // newer versions wrap the type, as it could be a constant
+                let ty = match get(&eq, ~"type") { Null => eq, t => t };
+                if ty != Null {
+                    params.push(fmt!("%s = %s", str_of(&get(c, ~"name")),
+                                     render_type(&ty)));
+                }
+            }
+            if params.len() == 0 { name }
+            else { fmt!("%s<%s>", name, str::connect(params, ", ")) }
+        }
+    }
+}
+
+// module_of gives the module an item (given its entry in paths) is in
+fn module_of(p: &Json) -> ~str {
+    let segs = list_of(&get(p, ~"path")).map(|s| { str_of(s) });
+    if segs.len() == 0 {
+        return ~"";
+    }
+    return str::connect(vec::slice(segs, 0, segs.len()-1), "::");
+}
+
+// summary gives the first paragraph of some docs, on one line
+fn summary(docs: &Json) -> ~str {
+    match *docs {
+        String(ref d) => {
+            let para = str::split_str(*d, "\n\n");
+            if para.len() == 0 { ~"" }
+            else { str::replace(str::trim(para[0]), "\n", " ") }
+        }
+        _ => ~""
+    }
+}
+
+// get looks up a key in an object, giving Null if there is no such key (or
+// it isn't an object), which makes looking deep into the json bearable
+fn get(j: &Json, key: ~str) -> Json {
+    match *j {
+        Object(ref o) => {
+            match o.find(&key) {
+                Some(v) => v,
+                None => Null
+            }
+        }
+        _ => Null
+    }
+}
+
+// is_synthetic says whether rustdoc made something up. newer versions of
+// the format call the flag is_synthetic, and older ones synthetic.
+fn is_synthetic(j: &Json) -> bool {
+    get(j, ~"is_synthetic") == Boolean(true) ||
+        get(j, ~"synthetic") == Boolean(true)
+}
+
+fn list_of(j: &Json) -> ~[Json] {
+    match *j { List(ref l) => copy *l, _ => ~[] }
+}
+
+fn str_of(j: &Json) -> ~str {
+    match *j {
+        String(ref s) => copy *s,
+        Number(n) => int::to_str(n as int, 10),
+        _ => ~""
+    }
+}
+
+// id_key gives the key an id is stored under in index and paths. ids used
+// to be strings, but are now numbers.
+fn id_key(id: &Json) -> ~str {
+    str_of(id)
+}
+
+fn record(fields: ~[(~str, Json)]) -> Json {
+    let mut obj = send_map::linear::LinearMap();
+    for fields.each |f| {
+        let (k, v) = copy *f;
+        obj.insert(k, v);
+    }
+    Object(~obj)
+}
+
+#[cfg(test)]
+mod tests {
+
+    fn doc() -> Json {
+        json::from_str(~"{\"root\": 0, \"format_version\": 30, \
+          \"index\": {\
+            \"0\": {\"crate_id\": 0, \"name\": \"demo\", \"docs\": null, \
+                    \"inner\": {\"module\": {\"items\": [1, 2, 4]}}},\
+            \"1\": {\"crate_id\": 0, \"name\": \"first\", \
+                    \"docs\": \"Gets the first.\\n\\nMore.\", \
+                    \"inner\": {\"function\": {\
+                      \"sig\": {\"inputs\": [[\"v\", {\"slice\": {\"generic\": \"T\"}}]], \
+                                \"output\": {\"resolved_path\": {\"path\": \"Option\", \"id\": 9, \
+                                  \"args\": {\"angle_bracketed\": {\"args\": [{\"type\": {\"generic\": \"T\"}}]}}}}}, \
+                      \"generics\": {\"params\": [{\"name\": \"T\", \"kind\": {\"type\": {\"bounds\": [\
+                        {\"trait_bound\": {\"trait\": {\"path\": \"Clone\"}, \"modifier\": \"none\"}}]}}}], \
+                        \"where_predicates\": []}}}},\
+            \"2\": {\"crate_id\": 0, \"name\": null, \"docs\": null, \
+                    \"inner\": {\"impl\": {\"trait\": {\"path\": \"fmt::Display\"}, \
+                      \"for\": {\"resolved_path\": {\"path\": \"Thing\", \"id\": 5}}, \
+                      \"items\": [3], \"generics\": {\"params\": [], \"where_predicates\": []}, \
+                      \"blanket_impl\": null, \"synthetic\": false}}},\
+            \"3\": {\"crate_id\": 0, \"name\": \"fmt\", \"docs\": null, \
+                    \"inner\": {\"function\": {\
+                      \"sig\": {\"inputs\": [[\"self\", {\"borrowed_ref\": {\"is_mutable\": false, \
+                                  \"type\": {\"generic\": \"Self\"}}}]], \
+                                \"output\": {\"primitive\": \"bool\"}}, \
+                      \"generics\": {\"params\": [], \"where_predicates\": []}}}},\
+            \"4\": {\"crate_id\": 0, \"name\": \"Firsts\", \"docs\": null, \
+                    \"inner\": {\"type_alias\": {\
+                      \"type\": {\"slice\": {\"generic\": \"T\"}}, \
+                      \"generics\": {\"params\": [{\"name\": \"T\", \"kind\": {\"type\": {\"bounds\": []}}}], \
+                        \"where_predicates\": []}}}}\
+          }, \
+          \"paths\": {\"1\": {\"crate_id\": 0, \"path\": [\"demo\", \"first\"], \"kind\": \"function\"}, \
+                      \"4\": {\"crate_id\": 0, \"path\": [\"demo\", \"Firsts\"], \"kind\": \"type_alias\"}, \
+                      \"5\": {\"crate_id\": 0, \"path\": [\"demo\", \"thing\", \"Thing\"], \"kind\": \"struct\"}}}").get()
+    }
+
+    #[test]
+    fn test_render_type() {
+        let t = json::from_str(~"{\"borrowed_ref\": {\"is_mutable\": true, \
+            \"type\": {\"slice\": {\"primitive\": \"u8\"}}}}").get();
+        assert render_type(&t) == ~"&mut [u8]";
+        let t2 = json::from_str(~"{\"tuple\": []}").get();
+        assert render_type(&t2) == ~"()";
+        let t3 = json::from_str(~"{\"impl_trait\": [{\"trait_bound\": \
+            {\"trait\": {\"path\": \"Iterator\", \"args\": \
+            {\"angle_bracketed\": {\"args\": [], \"constraints\": \
+            [{\"name\": \"Item\", \"binding\": {\"equality\": \
+            {\"type\": {\"primitive\": \"u8\"}}}}]}}}}}]}").get();
+        assert render_type(&t3) == ~"impl Iterator<Item = u8>";
+    }
+
+    #[test]
+    fn test_is_synthetic() {
+        let older = json::from_str(~"{\"synthetic\": true}").get();
+        let newer = json::from_str(~"{\"is_synthetic\": true}").get();
+        let real = json::from_str(~"{\"is_synthetic\": false}").get();
+        assert is_synthetic(&older);
+        assert is_synthetic(&newer);
+        assert !is_synthetic(&real);
+    }
+
+    #[test]
+    fn test_records() {
+        let recs = records(&doc());
+        // first, the alias, the impl and the struct
+        assert recs.len() == 4;
+        let data = load::from_records(&recs);
+        assert data.ar1.defs.len() == 2;
+        let first = vec::find(data.ar1.defs, |d| { d.name == ~"first" }).get();
+        assert first.path == ~"demo";
+        assert first.desc == ~"Gets the first.";
+        assert first.signature == ~"fn first<T: Clone>(v: [T]) -> Option<T>";
+        // Display isn't declared in the crate, so the impl's methods are
+        // loaded as methods of the type
+        let fmt = vec::find(data.ar1.defs, |d| { d.name == ~"fmt" }).get();
+        assert fmt.trait_name == Some(~"Display");
+        assert fmt.signature == ~"fn fmt(&self) -> bool";
+        assert fmt.args ==
+            ~[@Ptr(Ref(None), @Basic(~"demo::thing::Thing"))];
+        assert fmt.path == ~"demo::thing";
+        assert data.impls.get(~"Display") == ~[~"demo::thing::Thing"];
+        let firsts = data.aliases.get(~"Firsts")[0];
+        assert firsts.path == ~"demo";
+        assert firsts.params == ~[~"T"];
+        assert firsts.ty == @Vec(@Constrained(~"T", ~[]));
+    }
+}
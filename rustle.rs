@@ -1,4 +1,5 @@
 use types::*;
+use io::WriterUtil;
 
 fn main() {
     let args = os::args();
@@ -7,10 +8,97 @@ fn main() {
         usage();
         return;
     }
-    // load in data
-    let data = load::load(path::from_str("rustle.data"));
+    let mut json = false;
+    let mut partial = false;
+    let mut strict_refs = false;
+    let mut aliases = true;
+    let mut data_files = ~[];
+    let mut rustdocs = ~[];
+    let mut crates = ~[];
+    let mut port = 8080;
+    // only this machine can reach the web frontend, unless told otherwise
+    let mut bind = ~"127.0.0.1";
+    // anything that isn't a flag is a query
+    let mut queries = ~[];
+    let mut i = 1;
+    while i < args.len() {
+        match copy args[i] {
+            ~"--json" => json = true,
+            ~"--partial" => partial = true,
+            ~"--strict-refs" => strict_refs = true,
+            ~"--no-aliases" => aliases = false,
+            ~"--rustdoc" if i + 1 < args.len() => {
+                i += 1;
+                rustdocs.push(path::from_str(args[i]));
+            }
+            ~"--data" if i + 1 < args.len() => {
+                i += 1;
+                data_files.push(path::from_str(args[i]));
+            }
+            ~"--crate" if i + 1 < args.len() => {
+                i += 1;
+                crates.push(copy args[i]);
+            }
+            ~"--bind" if i + 1 < args.len() => {
+                i += 1;
+                bind = copy args[i];
+            }
+            ~"--port" if i + 1 < args.len() => {
+                i += 1;
+                match uint::from_str(args[i]) {
+                    Some(p) => port = p,
+                    None => {
+                        io::println(fmt!("bad port: %s", args[i]));
+                        libc::exit(1);
+                    }
+                }
+            }
+            a => queries.push(a)
+        }
+        i += 1;
+    }
+    if queries.len() == 2 && queries[0] == ~"index" {
+        // the index is written to the data file, if one was given
+        let out = if data_files.len() > 0 { copy data_files[0] }
+                  else { path::from_str("rustle.data") };
+        index(&path::from_str(queries[1]), &out);
+        return;
+    }
+    if queries.len() <= 2 && queries.len() > 0 &&
+        queries[0] == ~"build-index" {
+        let out = if queries.len() == 2 { path::from_str(queries[1]) }
+                  else { path::from_str("rustle.idx") };
+        let default = path::from_str("rustle.data");
+        // the files the index is built from are recorded in it, so that
+        // it can tell when it is out of date
+        let files = if data_files.len() == 0 && rustdocs.len() == 0 {
+            ~[copy default]
+        } else {
+            copy data_files
+        };
+        let sources = vec::append(
+            vec::map(files, |p| { compiled::source(p, false) }),
+            vec::map(rustdocs, |p| { compiled::source(p, true) }));
+        let data = load_data(data_files, rustdocs, default);
+        compiled::write(&data, &sources, &out);
+        io::println(fmt!("wrote %s", out.to_str()));
+        return;
+    }
+    let opts = SearchOpts { partial: partial, crates: crates,
+                            strict_refs: strict_refs, aliases: aliases };
+    // load in data. a compiled index starts up a lot faster, so it is used
+    // if there is one
+    let idx = path::from_str("rustle.idx");
+    let data = load_data(data_files, rustdocs,
+                         if os::path_exists(&idx) { idx }
+                         else { path::from_str("rustle.data") });
 
-    if args.len() == 1 {
+    if queries == ~[~"serve"] {
+        // run the web frontend
+        server::serve(&bind, port, |target| {
+            web::respond(&data, target)
+        });
+    } else if queries.len() == 0 {
         // start interactive loop
         io::println(~"Rustle, a Rust api search, by type signature (or name), v. 0.1.");
         io::println(~"Type in a type signature, like \"Option<A> -> A\". Ctrl-D to quit");
@@ -26,36 +114,127 @@ fn main() {
                 }
                 loop;
             }
-            run_search(str::trim(raw), &data);
+            run_search(str::trim(raw), &data, &opts, json);
             io::stdout().flush();
         }
     } else {
         // single run
-        run_search(copy args[1], &data);
+        run_search(copy queries[0], &data, &opts, json);
     }
 }
 
-fn run_search(q: ~str, d: &Data) {
-    if q.contains(~"->") || q.contains(~",") {
-        // this is a search by type, for functions
-        // build query
-        let queries = query::query(q);
-        // search
-        for query::search_type(queries, d).each |d| {
-            io::println(d.show());
+// load_data loads everything that is to be searched, or the default file if
+// nothing was given. any number of data files and rustdoc json files can be
+// searched together, but a compiled index can only be loaded on its own. an
+// index whose files have changed since it was built is out of date, so
+// those files are loaded instead.
+fn load_data(data_files: ~[path::Path], rustdocs: ~[path::Path],
+             default: path::Path) -> Data {
+    let data_files = if data_files.len() == 0 && rustdocs.len() == 0 {
+        ~[copy default]
+    } else {
+        data_files
+    };
+    if data_files.len() == 1 && rustdocs.len() == 0 &&
+        compiled::is_index(&data_files[0]) {
+        match compiled::load(&data_files[0]) {
+            Ok((data, sources)) => match compiled::changed(&sources) {
+                None => return data,
+                Some(p) => {
+                    io::stderr().write_line(fmt!(
+                        "%s has changed since %s was built, so it is loaded \
+                         instead (rustle build-index will rebuild the index)",
+                        p, data_files[0].to_str()));
+                    let files = vec::filter(sources, |s| { !s.rustdoc });
+                    let docs = vec::filter(sources, |s| { s.rustdoc });
+                    return load_data(
+                        vec::map(files, |s| { path::from_str(s.path) }),
+                        vec::map(docs, |s| { path::from_str(s.path) }),
+                        default);
+                }
+            },
+            Err(e) => {
+                io::println(fmt!("can't load %s: %s (rustle build-index \
+                                  will rebuild it)", data_files[0].to_str(),
+                                 e));
+                libc::exit(1);
+                fail;
+            }
+        }
+    }
+    let mut recs = ~[];
+    for data_files.each |p| {
+        if compiled::is_index(p) {
+            io::println(fmt!("%s is a compiled index, which can't be \
+                              loaded along with other data", p.to_str()));
+            libc::exit(1);
+        }
+        recs.push_all_move(load::read_records(p));
+    }
+    for rustdocs.each |p| {
+        recs.push_all_move(rustdoc::records(&load::read_json(p)));
+    }
+    return load::from_records(&recs);
+}
+
+fn run_search(q: ~str, d: &Data, opts: &SearchOpts, json: bool) {
+    let (q, opts) = query::with_crates(&q, opts);
+    let results = match query::search(&q, d, &opts) {
+        Ok((_kind, results)) => results,
+        Err(e) => {
+            // point out the problem, and let them try again
+            io::println(e.show(&q));
+            return;
         }
+    };
+    if json {
+        io::println(json::to_str(&List(results.map(|m| { m.to_json() }))));
     } else {
-        // this is a search by name
-        for query::search_name(q, d).each |d| {
-            io::println(d.show());
+        // names that could be more than one type are pointed out first, and
+        // each result then says which it was found for
+        for query::ambiguities(&q, d).each |a| {
+            let (name, paths) = copy *a;
+            io::println(paths::explain(&name, &paths));
+        }
+        for results.each |m| {
+            io::println(m.show());
         }
     }
 }
 
+// index extracts the items from the rust files in a directory, and writes
+// them out as a data file
+fn index(dir: &path::Path, out: &path::Path) {
+    let recs = source::index(dir);
+    source::write(&recs, out);
+    // loading the records back in parses all of the signatures, which
+    // makes sure the index is usable
+    let data = load::from_records(&recs);
+    let count = data.ar0.defs.len() + data.ar1.defs.len() +
+        data.ar2.defs.len() + data.ar3.defs.len() + data.ar4.defs.len() +
+        data.ar5.defs.len() + data.arn.defs.len();
+    io::println(fmt!("indexed %u definitions from %s into %s",
+                     count, dir.to_str(), out.to_str()));
+}
+
 fn usage() {
     io::println(~"Rustle, a Rust api search, by type signature (or name), v. 0.4");
     io::println(~"Usage: rustle -h | --help             -- this message");
     io::println(~"       rustle                         -- start interactive mode");
     io::println(~"       rustle \"[(A,B)] -> ([A],[B])\"  -- query directly");
     io::println(~"       rustle \"each\"                  -- query directly");
+    io::println(~"       rustle --json \"each\"           -- results as json, with scores");
+    io::println(~"       rustle --partial \"str -> bool\" -- allow leaving out arguments");
+    io::println(~"       rustle \"(_, [A], ..) -> A\"    -- _ is any type, .. any other arguments");
+    io::println(~"       rustle \"find :: [A] -> Option<A>\" -- by name and type (or \"name:find [A] -> Option<A>\")");
+    io::println(~"       rustle --strict-refs \"&mut [A] -> ()\" -- make &, &mut, ~, @ and * match exactly");
+    io::println(~"       rustle --no-aliases \"Result<A, Error>\" -- don't expand type aliases (io::Result<A>)");
+    io::println(~"       rustle --rustdoc doc.json ...  -- search rustdoc's json output");
+    io::println(~"       rustle --data std.data ...     -- search a data file (more than one can be given)");
+    io::println(~"       rustle --crate std ...         -- only show results from a crate");
+    io::println(~"       rustle \"crate:std each\"        -- same, as part of the query");
+    io::println(~"       rustle index path/to/crate     -- index rust sources into rustle.data");
+    io::println(~"       rustle build-index [out.idx]   -- compile the data for fast startup");
+    io::println(~"       rustle serve [--port 8080]     -- run the web frontend");
+    io::println(~"       rustle serve --bind 0.0.0.0    -- serve other machines too (just this one by default)");
 }
\ No newline at end of file
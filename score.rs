@@ -0,0 +1,244 @@
+//! This file contains the heuristics used to rank search results
+
+use unify::*;
+use names::*;
+
+// A score is a penalty - a perfect match scores 0, and every way in which a
+// definition differs from what was asked for adds to it. results are shown
+// lowest score first. the weights are guesses, tuned by trying queries.
+
+// the definition had to be instantiated (or the query generalized) at all
+const GENERALIZED: uint = 4;
+// a definition variable was bound to a concrete type
+const DEF_VAR_BOUND: uint = 1;
+// a query variable was bound to a concrete type - the definition is more
+// specific than what was asked for, which is usually less useful. the
+// same goes for each trait a definition's variable needs that the query
+// variable bound to it wasn't given.
+const QUERY_VAR_BOUND: uint = 3;
+// each pair of arguments that only matched the other way around from the
+// order they were given in
+const REORDERED: uint = 2;
+// each coercion needed to match (see coerce.rs)
+const COERCED: uint = 2;
+// a method whose receiver wasn't matched by the query's first argument,
+// which is how methods are usually looked for
+const NOT_RECEIVER: uint = 1;
+// each argument of the definition that a partial query left out
+const MISSING_ARG: uint = 3;
+// a definition found by name didn't match the type given with it
+const UNMATCHED: uint = 10;
+// each part of that type the definition has nothing to match
+const UNMATCHED_PART: uint = 3;
+// how a name query matched a name: as a prefix, by the segments of the
+// name, anywhere in it, or with typos, which cost more than any of those
+// and then more for each edit
+const PREFIX: uint = 1;
+const SEGMENTS: uint = 3;
+const SUBSTRING: uint = 6;
+const TYPO: uint = 5;
+// the most a name that is longer (or shorter) than the query is penalized
+const MAX_LENGTH: uint = 3;
+// each module level below the crate
+const PATH_DEPTH: uint = 1;
+// the least popular modules get at most this much added
+const MAX_POPULARITY: uint = 3;
+
+// type_score scores a definition that matched a type query
+pub fn type_score(u: &Unified, d: &Definition, data: &Data) -> uint {
+    let mut score = 0;
+    let mut generalized = false;
+    for u.subst.each |b| {
+        let (n, a) = copy *b;
+        match a {
+            // binding a variable to a variable is just renaming
+            @Constrained(_, _) => {}
+            _ => {
+                generalized = true;
+                score += if is_def_var(&n) { DEF_VAR_BOUND }
+                         else { QUERY_VAR_BOUND };
+            }
+        }
+    }
+    if generalized {
+        score += GENERALIZED;
+    }
+    score += u.unbounded * QUERY_VAR_BOUND;
+    score += u.swaps * REORDERED;
+    score += u.missing * MISSING_ARG;
+    score += u.coercions.len() * COERCED;
+    if d.receiver && !u.receiver {
+        score += NOT_RECEIVER;
+    }
+    return score + location_score(d, data);
+}
+
+// how_typed names the kind of match a type query made, for saying how a
+// result matched: the worst of needing coercions, leaving out arguments
+// (partial), binding variables to types or to variables with fewer traits
+// (generalized), or none (exact)
+pub fn how_typed(u: &Unified) -> ~str {
+    if u.coercions.len() > 0 {
+        ~"coerced"
+    } else if u.missing > 0 {
+        ~"partial"
+    } else if u.unbounded > 0 || vec::any(u.subst, |b| {
+        match b.second() { @Constrained(_, _) => false, _ => true }
+    }) {
+        ~"generalized"
+    } else {
+        ~"exact"
+    }
+}
+
+// name_score scores a definition that matched a name query. the better
+// the match, and the closer the name is in length to what was typed, the
+// better.
+pub fn name_score(q: &~str, m: NameMatch, d: &Definition,
+                  data: &Data) -> uint {
+    let score = match m {
+        Exact => 0,
+        Prefix => PREFIX,
+        Segments => SEGMENTS,
+        Substring => SUBSTRING,
+        Typos(n) => SUBSTRING + n * TYPO
+    };
+    let diff = if d.name.len() > q.len() { d.name.len() - q.len() }
+               else { q.len() - d.name.len() };
+    return score + uint::min(diff, MAX_LENGTH) + location_score(d, data);
+}
+
+// similarity_score scores a definition that matched the name of a
+// combined query, but not its type. it starts out behind the definitions
+// that matched both, and the more of the type it has, the better.
+pub fn similarity_score(q: &Query, d: &Definition, opts: &SearchOpts,
+                        data: &Data) -> uint {
+    UNMATCHED + unmatched(q, d, opts) * UNMATCHED_PART +
+        location_score(d, data)
+}
+
+// location_score is the part of the score that depends only on where the
+// definition lives: shallower paths and more popular modules are preferred
+fn location_score(d: &Definition, data: &Data) -> uint {
+    let depth = vec::len(str::split_str(d.path, "::")) - 1;
+    let popularity = match data.modules.find(copy d.path) {
+        // modules are ranked by the order the scraper processed them in,
+        // which is intentionally (if subjectively) most useful first
+        Some(rank) => uint::min(rank / 10, MAX_POPULARITY),
+        None => MAX_POPULARITY
+    };
+    return depth * PATH_DEPTH + popularity;
+}
+
+// sort_matches orders matches best first. the sort is stable, so equally
+// scored matches stay in the order they appear in the data.
+pub fn sort_matches(ms: ~[Match]) -> ~[Match] {
+    sort::merge_sort(|x, y| { x.score <= y.score }, ms)
+}
+
+#[cfg(test)]
+mod tests {
+
+    fn def(path: ~str) -> Definition {
+        Definition { name: ~"foo", path: path, anchor: ~"function-foo",
+                     desc: ~"", args: ~[], ret: @Basic(~"int"),
+                     signature: ~"fn foo() -> int", trait_name: None,
+                     crate_name: ~"core", receiver: false }
+    }
+
+    #[test]
+    fn test_type_score() {
+        let data = empty_data();
+        let d = def(~"core::foo");
+        let exact = Unified { subst: ~[(~"'A", @Constrained(~"A", ~[]))],
+                              swaps: 0, missing: 0,
+                              coercions: ~[], receiver: false, unbounded: 0 };
+        let inst = Unified { subst: ~[(~"'A", @Basic(~"int"))],
+                             swaps: 0, missing: 0,
+                             coercions: ~[], receiver: false, unbounded: 0 };
+        let gen = Unified { subst: ~[(~"A", @Basic(~"int"))],
+                            swaps: 0, missing: 0,
+                            coercions: ~[], receiver: false, unbounded: 0 };
+        let reord = Unified { subst: ~[], swaps: 1, missing: 0,
+                              coercions: ~[], receiver: false, unbounded: 0 };
+        let reord2 = Unified { swaps: 2, ..reord };
+        let part = Unified { subst: ~[], swaps: 0, missing: 1,
+                             coercions: ~[], receiver: false, unbounded: 0 };
+        let coerced = Unified { subst: ~[], swaps: 0, missing: 0,
+                                coercions: ~[~"String to str"],
+                                receiver: false, unbounded: 0 };
+        assert type_score(&exact, &d, &data) < type_score(&inst, &d, &data);
+        assert type_score(&inst, &d, &data) < type_score(&gen, &d, &data);
+        assert type_score(&exact, &d, &data) < type_score(&reord, &d, &data);
+        assert type_score(&reord, &d, &data) < type_score(&reord2, &d, &data);
+        assert type_score(&exact, &d, &data) < type_score(&part, &d, &data);
+        assert type_score(&exact, &d, &data) <
+            type_score(&coerced, &d, &data);
+        assert how_typed(&exact) == ~"exact";
+        assert how_typed(&reord) == ~"exact";
+        assert how_typed(&gen) == ~"generalized";
+        assert how_typed(&part) == ~"partial";
+        assert how_typed(&coerced) == ~"coerced";
+        let loose = Unified { unbounded: 1, ..exact };
+        assert type_score(&exact, &d, &data) < type_score(&loose, &d, &data);
+        assert how_typed(&loose) == ~"generalized";
+        let method = Definition { receiver: true, ..d };
+        let recv = Unified { receiver: true, ..exact };
+        assert type_score(&recv, &method, &data) <
+            type_score(&exact, &method, &data);
+    }
+
+    #[test]
+    fn test_similarity_score() {
+        let data = empty_data();
+        let d = def(~"core::foo");
+        let opts = default_opts();
+        let close = Query { args: ~[], ret: @Basic(~"uint"), rest: false };
+        let far = Query { args: ~[@Basic(~"str")], ..close };
+        let part = Unified { subst: ~[], swaps: 0, missing: 1,
+                             coercions: ~[], receiver: false, unbounded: 0 };
+        assert type_score(&part, &d, &data) <
+            similarity_score(&close, &d, &opts, &data);
+        assert similarity_score(&close, &d, &opts, &data) <
+            similarity_score(&far, &d, &opts, &data);
+    }
+
+    #[test]
+    fn test_name_score() {
+        let data = empty_data();
+        fn named(name: ~str) -> Definition {
+            Definition { name: name, ..def(~"core::foo") }
+        }
+        let q = ~"each";
+        assert name_score(&q, Exact, &named(~"each"), &data) <
+            name_score(&q, Prefix, &named(~"each_char"), &data);
+        assert name_score(&q, Prefix, &named(~"each_char"), &data) <
+            name_score(&q, Substring, &named(~"reach"), &data);
+        assert name_score(&q, Typos(1), &named(~"eahc"), &data) <
+            name_score(&q, Typos(2), &named(~"aehc"), &data);
+        // however far off in length, a substring beats a typo
+        assert name_score(&q, Substring, &named(~"breached"), &data) <
+            name_score(&q, Typos(1), &named(~"eahc"), &data);
+    }
+
+    #[test]
+    fn test_location_score() {
+        let data = empty_data();
+        data.modules.insert(~"core::str", 0);
+        assert location_score(&def(~"core::str"), &data) <
+            location_score(&def(~"core::either"), &data);
+        assert location_score(&def(~"core::str"), &data) <
+            location_score(&def(~"core::str::raw"), &data);
+    }
+
+    #[test]
+    fn test_sort_matches() {
+        let d = @def(~"core::foo");
+        let m = Match { def: d, score: 0, coercions: ~[], receiver: false,
+                        resolved: ~[], how: ~"exact" };
+        let ms = sort_matches(~[Match { score: 3, ..m },
+                                Match { score: 1, ..m },
+                                Match { score: 2, ..m }]);
+        assert vec::map(ms, |m| { m.score }) == ~[1, 2, 3];
+    }
+}
@@ -20,16 +20,42 @@ main = do
     -- note that the order they appear is the order results will appear in, equally matching
     -- queries, so the order here is intentional (and subjective).
     where coreFileList = ["str", "vec", "option", "bool", "io", "os", "path", "either", "run", "at_vec", "box", "cast", "char", "cmp", "comm", "dlist", "dlist_iter", "dvec", "dvec_iter",  "f32", "f64", "flate", "float", "from_str", "future", "gc", "hash", "i16", "i32", "i64", "i8", "int", "iter", "libc", "logging", "mutable", "num", "option_iter", "pipes", "ptr", "rand", "reflect", "repr", "result",  "send_map", "sys", "task", "to_bytes", "to_str", "tuple", "u16", "u32", "u64", "u8", "uint", "uniq", "unit", "util"]
+-- an Item is either a function (or method), a trait declaration with its methods,
+-- or an implementation of a trait for a type, with its methods. the methods of
+-- traits are (anchor, name, type, desc), as they live wherever the trait does.
+data Item = Func (String, String, String, String, String, String)
+          | Trait String String String String [(String, String, String, String)]
+          | Impl String String String [(String, String, String, String)]
+
 writeJson files = do
   let dat = encode $ JSArray $ concat $ map writeJson' files
   writeFile "rustle.data" dat
-    where writeJson' = map (\(a,n,t,s,d,p) -> JSObject $
+    where writeJson' = map toJson
+          toJson (Func (a,n,t,s,d,p)) = JSObject $
                                             toJSObject [("anchor", JSString $ toJSString a)
                                                        ,("name",   JSString $ toJSString n)
                                                        ,("type",   JSString $ toJSString t)
                                                        ,("self",   JSString $ toJSString s)
                                                        ,("desc",   JSString $ toJSString d)
-                                                       ,("path",   JSString $ toJSString p)])
+                                                       ,("path",   JSString $ toJSString p)]
+          toJson (Trait a n d p ms) = JSObject $
+                                            toJSObject [("kind",    JSString $ toJSString "trait")
+                                                       ,("anchor",  JSString $ toJSString a)
+                                                       ,("name",    JSString $ toJSString n)
+                                                       ,("desc",    JSString $ toJSString d)
+                                                       ,("path",    JSString $ toJSString p)
+                                                       ,("methods", JSArray $ map methodJson ms)]
+          toJson (Impl tr for p ms) = JSObject $
+                                            toJSObject [("kind",    JSString $ toJSString "impl")
+                                                       ,("trait",   JSString $ toJSString tr)
+                                                       ,("for",     JSString $ toJSString for)
+                                                       ,("path",    JSString $ toJSString p)
+                                                       ,("methods", JSArray $ map methodJson ms)]
+          methodJson (a,n,t,d) = JSObject $
+                                            toJSObject [("anchor", JSString $ toJSString a)
+                                                       ,("name",   JSString $ toJSString n)
+                                                       ,("type",   JSString $ toJSString t)
+                                                       ,("desc",   JSString $ toJSString d)]
 
 parseFile (path, n) = do
     f <- readFile n
@@ -37,40 +63,56 @@ parseFile (path, n) = do
     let sects = partitions (\t -> (isTagOpenName "div" t) &&
                                       ("level2" `isInfixOf` (fromAttrib "class" t)) &&
                                       (("function" `isPrefixOf` (fromAttrib "id" t)) ||
+                                       ("trait" `isPrefixOf` (fromAttrib "id" t)) ||
                                        ("implementation" `isPrefixOf` (fromAttrib "id" t))))
                               tags
     return $ concat $ map (extract path) sects
 
-extract path tags = if ("function" `isPrefixOf` (fromAttrib "id" (head tags)))
-                    then extractFunc path tags
-                    else extractMethods path tags
+extract path tags
+  | "function" `isPrefixOf` ident = extractFunc path tags
+  | "trait" `isPrefixOf` ident    = extractTrait path tags
+  | otherwise                     = extractMethods path tags
+  where ident = fromAttrib "id" (head tags)
 
--- for now, ignore impls of traits - they add lots of results and with the current
--- presentation, dillute the results
-extractMethods path tags = if isExtensions then map (extractMethod path clas self) methods
-                                           else []
-  where methods = partitions (\t -> (isTagOpenName "div" t) &&
-                      ("level3" `isInfixOf` (fromAttrib "class" t)) &&
-                      ("method" `isPrefixOf` (fromAttrib "id" t)))
-               tags
-        impl = partitions (isTagOpenName "code") $
+-- methods of impls of traits are recorded with the impl, not as plain methods of
+-- the type, so that rustle can find them through the trait declaration (and mark
+-- them as trait methods)
+extractMethods path tags = if isExtensions then map (extractMethod path clas self) (methods tags)
+                                           else [Impl clas self path (map traitMethod (methods tags))]
+  where impl = partitions (isTagOpenName "code") $
           takeWhile (not.isTagCloseName "h2") $ getTag "h2" tags
         isExtensions = (length impl) == 1
         self = getCod $ impl !! (if isExtensions then 0 else 1)
         clas = if isExtensions then self else getCod $ impl !! 0
 
-extractMethod path clas self tags = (anchor, name, ty, self, desc, path)
+extractMethod path clas self tags = Func (anchor, name, ty, self, desc, path)
   where anchor = fromAttrib "id" (head tags)
         name   = getCod $ getTag "h3" tags
         ty     = getCod $ getTag "pre" tags
         desc   = "a method of " ++ clas ++ ": " ++
           (getText $ headSafe $ drop 1 $ getTag "p" tags)
 
+-- h2 > code has the trait name, and the methods are like an impl's
+extractTrait path tags = [Trait anchor name desc path (map traitMethod (methods tags))]
+  where anchor = fromAttrib "id" (head tags)
+        name   = getCod $ getTag "h2" tags
+        desc   = getText $ headSafe $ drop 1 $ getTag "p" tags
+
+methods = partitions (\t -> (isTagOpenName "div" t) &&
+                         ("level3" `isInfixOf` (fromAttrib "class" t)) &&
+                         ("method" `isPrefixOf` (fromAttrib "id" t)))
+
+traitMethod tags = (anchor, name, ty, desc)
+  where anchor = fromAttrib "id" (head tags)
+        name   = getCod $ getTag "h3" tags
+        ty     = getCod $ getTag "pre" tags
+        desc   = getText $ headSafe $ drop 1 $ getTag "p" tags
+
 -- div's id gives you link
 -- h2 > code has name
 -- pre > code has type
 -- first p has short description
-extractFunc path tags = [(anchor, name, ty, "", desc, path)]
+extractFunc path tags = [Func (anchor, name, ty, "", desc, path)]
     where anchor = fromAttrib "id" (head tags)
           name   = getCod $ getTag "h2" tags
           ty     = getCod $ getTag "pre" tags
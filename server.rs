@@ -0,0 +1,160 @@
+//! This file contains a small built in http server, which the web frontend
+//! (see web.rs) runs on, so that nothing else needs to be installed to run it
+
+use std::net::ip;
+use std::net::tcp;
+use std::uv;
+use io::WriterUtil;
+
+// a Response is what is sent back for a request
+pub struct Response { status: uint, content_type: ~str, body: ~str }
+
+pub fn html(body: ~str) -> Response {
+    Response { status: 200, content_type: ~"text/html; charset=utf-8",
+               body: body }
+}
+
+pub fn json(status: uint, j: &Json) -> Response {
+    Response { status: status, content_type: ~"application/json",
+               body: json::to_str(j) }
+}
+
+pub fn not_found() -> Response {
+    Response { status: 404, content_type: ~"text/plain; charset=utf-8",
+               body: ~"not found" }
+}
+
+// a Request is the target of a request (its path and query string), and
+// where to send the response to it
+type Request = (~str, comm::Chan<Response>);
+
+// serve answers http requests on an address and port, forever. the data
+// being searched can't be shared between tasks, so the task that calls
+// serve answers every request (with handler), while each connection is read
+// and written in a task of its own.
+pub fn serve(bind: &~str, port: uint, handler: fn(&~str) -> Response) {
+    let addr = match ip::v4::try_parse_addr(*bind) {
+        Ok(a) => a,
+        Err(_) => {
+            io::println(fmt!("bad address to bind to: %s", *bind));
+            libc::exit(1);
+            fail;
+        }
+    };
+    let req_po = comm::Port();
+    let req_ch = comm::Chan(&req_po);
+    do task::spawn {
+        listen(addr, port, req_ch);
+    }
+    io::println(fmt!("rustle listening on http://%s:%u/", *bind, port));
+    loop {
+        let (target, resp_ch) = req_po.recv();
+        resp_ch.send(handler(&target));
+    }
+}
+
+// listen accepts connections, handing each one to a new task
+fn listen(addr: ip::IpAddr, port: uint, req_ch: comm::Chan<Request>) {
+    let res = do tcp::listen(addr, port, 128, uv::global_loop::get(),
+                             |_kill_ch| {}) |conn, _kill_ch| {
+        // the connection has to be accepted before this returns, so the new
+        // task says when it has been
+        let cont_po = comm::Port();
+        let cont_ch = comm::Chan(&cont_po);
+        let req_ch = copy req_ch;
+        do task::spawn {
+            match tcp::accept(conn) {
+                Err(e) => cont_ch.send(Some(e)),
+                Ok(sock) => {
+                    cont_ch.send(None);
+                    handle(tcp::socket_buf(sock), req_ch);
+                }
+            }
+        }
+        match cont_po.recv() {
+            Some(e) => io::println(fmt!("accept failed: %s", e.err_msg)),
+            None => {}
+        }
+    };
+    if res.is_err() {
+        io::println(fmt!("can't listen on port %u", port));
+        libc::exit(1);
+    }
+}
+
+// handle reads a request from a connection, and writes back the response.
+// only GET is supported, and headers are read but ignored.
+fn handle(sock: tcp::TcpSocketBuf, req_ch: comm::Chan<Request>) {
+    let reader = copy sock as io::Reader;
+    let writer = sock as io::Writer;
+    let target = request_target(&reader.read_line());
+    loop {
+        let header = str::trim(reader.read_line());
+        if header.len() == 0 { break; }
+    }
+    let resp = match target {
+        Some(t) => {
+            let resp_po = comm::Port();
+            req_ch.send((t, comm::Chan(&resp_po)));
+            resp_po.recv()
+        }
+        None => Response { status: 405,
+                           content_type: ~"text/plain; charset=utf-8",
+                           body: ~"only GET is supported" }
+    };
+    writer.write_str(format_response(&resp));
+}
+
+// request_target gets the target out of the first line of a GET request
+pub fn request_target(line: &~str) -> Option<~str> {
+    let words = str::words(*line);
+    if words.len() >= 2 && words[0] == ~"GET" {
+        Some(copy words[1])
+    } else {
+        None
+    }
+}
+
+// format_response gives the http for a response. connections aren't kept
+// alive, as a search page has nothing else to load.
+pub fn format_response(r: &Response) -> ~str {
+    fmt!("HTTP/1.0 %u %s\r\nContent-Type: %s\r\nContent-Length: %u\r\n\
+          Connection: close\r\n\r\n%s",
+         r.status, reason(r.status), r.content_type, r.body.len(), r.body)
+}
+
+fn reason(status: uint) -> ~str {
+    match status {
+        200 => ~"OK",
+        400 => ~"Bad Request",
+        404 => ~"Not Found",
+        405 => ~"Method Not Allowed",
+        _ => ~"Internal Server Error"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn test_request_target() {
+        assert request_target(&~"GET /?q=each HTTP/1.1\r") == Some(~"/?q=each");
+        assert request_target(&~"POST / HTTP/1.1") == None;
+        assert request_target(&~"") == None;
+    }
+
+    #[test]
+    fn test_json() {
+        let r = json(400, &String(~"bad"));
+        assert r.status == 400;
+        assert r.content_type == ~"application/json";
+        assert r.body == ~"\"bad\"";
+    }
+
+    #[test]
+    fn test_format_response() {
+        assert format_response(&html(~"<p>hi</p>")) ==
+            ~"HTTP/1.0 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+              Content-Length: 9\r\nConnection: close\r\n\r\n<p>hi</p>";
+    }
+}
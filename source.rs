@@ -0,0 +1,664 @@
+//! This file contains a simple item extractor, which builds an index
+//! straight from rust source files, for crates without generated docs
+
+use io::WriterUtil;
+use parse::*;
+
+// Context is what the scanner is inside of. items are only looked for
+// directly inside of modules, impls and traits - the bodies of everything
+// else (functions, structs, macros...) are skipped over.
+enum Context {
+    // the path of the module
+    ModCtx(~str),
+    // the self type, the trait (for trait impls), the generics and where
+    // clause of the impl, and the methods found in it so far
+    ImplCtx(~str, Option<~str>, ~str, ~str, ~[Json]),
+    // the name and description of the trait, and its methods so far
+    TraitCtx(~str, ~str, ~[Json]),
+    BodyCtx
+}
+
+// index extracts the items of every rust file under a directory. the crate
+// is named after the directory (or its Cargo.toml, if it has one), and
+// modules after the files they are in.
+pub fn index(dir: &path::Path) -> ~[Json] {
+    let krate = crate_name(dir);
+    let mut recs = ~[];
+    for rs_files(dir).each |f| {
+        match io::read_whole_file_str(f) {
+            Ok(src) => {
+                recs.push_all_move(extract(&src, &module_path(&krate, dir, f)));
+            }
+            Err(e) => io::println(fmt!("skipping %s: %s", f.to_str(), e))
+        }
+    }
+    return recs;
+}
+
+// write writes records out as a data file, that load can read back in
+pub fn write(recs: &~[Json], path: &path::Path) {
+    match io::file_writer(path, ~[io::Create, io::Truncate]) {
+        Ok(w) => w.write_str(json::to_str(&List(copy *recs))),
+        Err(e) => {
+            io::println(fmt!("can't write %s: %s", path.to_str(), e));
+            libc::exit(1);
+        }
+    }
+}
+
+// rs_files finds all the rust files under a directory. hidden directories
+// and build output aren't looked in.
+fn rs_files(dir: &path::Path) -> ~[path::Path] {
+    let mut files = ~[];
+    for os::list_dir_path(dir).each |p| {
+        let name = option::get_default(&p.filename(), ~"");
+        if os::path_is_dir(p) {
+            if !str::starts_with(name, ".") && name != ~"target" {
+                files.push_all_move(rs_files(p));
+            }
+        } else if p.filetype() == Some(~".rs") {
+            files.push(copy *p);
+        }
+    }
+    return files;
+}
+
+// crate_name gives the name of the crate in a directory
+fn crate_name(dir: &path::Path) -> ~str {
+    let name = match io::read_whole_file_str(&dir.push(~"Cargo.toml")) {
+        Ok(toml) => package_name(&toml),
+        Err(_) => None
+    };
+    return option::get_default(&name, option::get_default(&dir.filename(),
+                                                          ~"crate"));
+}
+
+// package_name finds the name in the [package] section of a Cargo.toml.
+// other sections ([lib], [[bin]], dependencies) have names too, which
+// aren't the crate's.
+fn package_name(toml: &~str) -> Option<~str> {
+    let mut in_package = false;
+    for str::lines(*toml).each |l| {
+        let line = str::trim(*l);
+        if str::starts_with(line, "[") {
+            in_package = line == ~"[package]";
+            loop;
+        }
+        let parts = str::splitn_char(line, '=', 1);
+        if in_package && parts.len() == 2 && str::trim(parts[0]) == ~"name" {
+            let name = str::replace(str::trim(parts[1]), ~"\"", ~"");
+            return Some(str::replace(name, ~"-", ~"_"));
+        }
+    }
+    return None;
+}
+
+// module_path gives the module a file defines. src/ isn't part of the
+// path, and lib.rs, main.rs and mod.rs are named by where they are.
+fn module_path(krate: &~str, dir: &path::Path, file: &path::Path) -> ~str {
+    let mut segs = vec::slice(file.components, dir.components.len(),
+                              file.components.len());
+    if segs.len() > 0 && segs[0] == ~"src" {
+        segs = vec::tail(segs);
+    }
+    let last = option::get_default(&file.filestem(), ~"");
+    segs = vec::slice(segs, 0, segs.len() - 1);
+    if last != ~"lib" && last != ~"main" && last != ~"mod" {
+        segs.push(last);
+    }
+    return str::connect(vec::append(~[copy *krate], segs), "::");
+}
+
+// extract finds the functions, methods, traits and impls in the source of
+// a single module. it is not a real parser - it only looks at what comes
+// before each brace or semicolon, and relies on comments, strings and
+// character literals being skipped so they can't confuse it.
+pub fn extract(src: &~str, module: &~str) -> ~[Json] {
+    let cs = str::chars(*src);
+    let n = cs.len();
+    let mut i = 0;
+    // the header of the item being read, and the doc comments before it
+    let mut cur = ~"";
+    let mut docs = ~[];
+    let mut stack = ~[ModCtx(copy *module)];
+    let mut recs = ~[];
+    while i < n {
+        let c = cs[i];
+        let in_body = match stack[stack.len()-1] { BodyCtx => true, _ => false };
+        if c == '/' && i+1 < n && cs[i+1] == '/' {
+            let end = line_end(cs, i);
+            let line = str::from_chars(vec::slice(cs, i, end));
+            if !in_body && str::starts_with(line, "///") &&
+                !str::starts_with(line, "////") {
+                docs.push(str::trim(str::slice(line, 3, line.len())));
+            }
+            i = end;
+        } else if c == '/' && i+1 < n && cs[i+1] == '*' {
+            i = skip_block_comment(cs, i);
+        } else if c == '"' || c == '\'' || is_raw_string(cs, i) {
+            let end = if c == '"' { skip_string(cs, i) }
+                      else if c == '\'' { skip_quote(cs, i) }
+                      else { skip_raw_string(cs, i) };
+            if !in_body {
+                str::push_str(&mut cur, str::from_chars(vec::slice(cs, i, end)));
+            }
+            i = end;
+        } else if in_body {
+            if c == '{' {
+                stack.push(BodyCtx);
+            } else if c == '}' {
+                stack.pop();
+            }
+            i += 1;
+        } else if c == '#' && i+1 < n && (cs[i+1] == '[' || cs[i+1] == '!') {
+            i = skip_attribute(cs, i);
+        } else if c == '{' || c == ';' {
+            let ctx = item(&cur, &docs, c == '{', &mut stack, &mut recs);
+            if c == '{' {
+                stack.push(ctx);
+            }
+            cur = ~"";
+            docs = ~[];
+            i += 1;
+        } else if c == '}' {
+            // a stray brace at the top level is ignored rather than
+            // popping the module
+            if stack.len() > 1 {
+                let ctx = stack.pop();
+                close(ctx, &stack, &mut recs);
+            }
+            cur = ~"";
+            docs = ~[];
+            i += 1;
+        } else {
+            str::push_char(&mut cur, c);
+            i += 1;
+        }
+    }
+    return recs;
+}
+
+// item handles an item header, which was followed by a brace (if braced)
+// or a semicolon. functions are recorded, and the context for the inside
+// of the item (if it has one) is returned.
+fn item(header: &~str, docs: &~[~str], braced: bool,
+        stack: &mut ~[Context], recs: &mut ~[Json]) -> Context {
+    let full = str::connect(str::words(*header), " ");
+    let h = strip_qualifiers(&full);
+    if str::starts_with(h, "fn ") {
+        add_fn(&h, docs, stack, recs);
+        return BodyCtx;
+    }
+    if str::starts_with(h, "type ") {
+        add_alias(&h, stack, recs);
+        return BodyCtx;
+    }
+    // tuple and unit structs end with a semicolon, so types are recorded
+    // whether they are braced or not
+    for [~"struct", ~"enum", ~"union"].each |k| {
+        if str::starts_with(h, *k + ~" ") {
+            add_type(k, &h, stack, recs);
+        }
+    }
+    if !braced {
+        return BodyCtx;
+    }
+    if h == ~"impl" || str::starts_with(h, "impl ") ||
+        str::starts_with(h, "impl<") {
+        return parse_impl(&h);
+    } else if str::starts_with(h, "trait ") {
+        return TraitCtx(ident(&h, 6), summary(docs), ~[]);
+    } else if str::starts_with(h, "mod ") {
+        let name = ident(&h, 4);
+        // unit tests aren't part of the api
+        if name == ~"tests" {
+            return BodyCtx;
+        }
+        return ModCtx(current_module(stack) + ~"::" + name);
+    } else if h.len() == 0 && str::contains(full, "extern") {
+        // the functions in an extern block belong to the module
+        return ModCtx(current_module(stack));
+    }
+    return BodyCtx;
+}
+
+// add_fn records a function, in a way that depends on where it is: free
+// functions and methods of inherent impls get their own records, while the
+// methods of traits and trait impls are added to those.
+fn add_fn(h: &~str, docs: &~[~str], stack: &mut ~[Context],
+          recs: &mut ~[Json]) {
+    let name = ident(h, 3);
+    let desc = summary(docs);
+    let receiver = has_receiver(h);
+    let top = stack.pop();
+    let next = match top {
+        ModCtx(ref path) => {
+            recs.push(load::record(~[(~"anchor", String(~"fn." + name)),
+                                     (~"name", String(copy name)),
+                                     (~"type", String(copy *h)),
+                                     (~"self", String(~"")),
+                                     (~"desc", String(desc)),
+                                     (~"path", String(copy *path))]));
+            copy top
+        }
+        ImplCtx(ref self, None, ref gens, ref wheres, _) => {
+            let sig = with_generics(h, gens, wheres);
+            let self_ty = if receiver { copy *self } else { ~"" };
+            recs.push(load::record(~[(~"anchor", String(~"method." + name)),
+                                     (~"name", String(copy name)),
+                                     (~"type", String(sig)),
+                                     (~"self", String(self_ty)),
+                                     (~"desc", String(desc)),
+                                     (~"path",
+                                      String(current_module(stack)))]));
+            copy top
+        }
+        ImplCtx(self, Some(tr), gens, wheres, ms) => {
+            let sig = with_generics(h, &gens, &wheres);
+            let m = method(&name, sig, receiver, desc);
+            ImplCtx(self, Some(tr), gens, wheres, vec::append(ms, ~[m]))
+        }
+        TraitCtx(tname, tdesc, ms) => {
+            let m = method(&name, copy *h, receiver, desc);
+            TraitCtx(tname, tdesc, vec::append(ms, ~[m]))
+        }
+        BodyCtx => BodyCtx
+    };
+    stack.push(next);
+}
+
+// add_alias records a type alias declared in a module. the associated
+// types of traits and impls (type Item = T) are left out, as they aren't
+// named on their own.
+fn add_alias(h: &~str, stack: &~[Context], recs: &mut ~[Json]) {
+    match stack[stack.len()-1] {
+        ModCtx(ref path) => {
+            recs.push(load::record(~[(~"kind", String(~"type")),
+                                     (~"name", String(ident(h, 5))),
+                                     (~"type", String(copy *h)),
+                                     (~"path", String(copy *path))]));
+        }
+        _ => {}
+    }
+}
+
+// add_type records the declaration of a struct, enum or union, which only
+// says where it is, for names of types to be resolved to (see paths.rs)
+fn add_type(kind: &~str, h: &~str, stack: &~[Context], recs: &mut ~[Json]) {
+    match stack[stack.len()-1] {
+        ModCtx(ref path) => {
+            recs.push(load::record(~[(~"kind", String(copy *kind)),
+                                     (~"name",
+                                      String(ident(h, kind.len() + 1))),
+                                     (~"path", String(copy *path))]));
+        }
+        _ => {}
+    }
+}
+
+// method makes the record for a method of a trait or trait impl
+fn method(name: &~str, sig: ~str, receiver: bool, desc: ~str) -> Json {
+    load::record(~[(~"anchor", String(~"method." + *name)),
+                   (~"name", String(copy *name)),
+                   (~"type", String(if receiver { sig }
+                                    else { ~"static " + sig })),
+                   (~"desc", String(desc))])
+}
+
+// close finishes off an item when its closing brace is reached. traits and
+// trait impls are recorded now that all their methods have been seen.
+fn close(ctx: Context, stack: &~[Context], recs: &mut ~[Json]) {
+    match ctx {
+        ImplCtx(self, Some(tr), _, _, ms) => {
+            recs.push(load::record(~[(~"kind", String(~"impl")),
+                                     (~"trait", String(tr)),
+                                     (~"for", String(self)),
+                                     (~"path",
+                                      String(current_module(stack))),
+                                     (~"methods", List(ms))]));
+        }
+        TraitCtx(name, desc, ms) => {
+            recs.push(load::record(~[(~"kind", String(~"trait")),
+                                     (~"anchor", String(~"trait." + name)),
+                                     (~"name", String(copy name)),
+                                     (~"desc", String(desc)),
+                                     (~"path",
+                                      String(current_module(stack))),
+                                     (~"methods", List(ms))]));
+        }
+        _ => {}
+    }
+}
+
+// parse_impl picks apart an impl header, like
+// impl<T: Clone> Trait for Type<T> where T: Eq
+fn parse_impl(h: &~str) -> Context {
+    let mut rest = str::trim(str::slice(*h, 4, h.len()));
+    let mut gens = ~"";
+    if str::starts_with(rest, "<") {
+        let close = matching_angle(&rest, 0);
+        gens = str::slice(rest, 1, close);
+        rest = str::trim(str::slice(rest, uint::min(close+1, rest.len()),
+                                    rest.len()));
+    }
+    let mut wheres = ~"";
+    match str::find_str(rest, " where ") {
+        Some(w) => {
+            wheres = str::trim(str::slice(rest, w+7, rest.len()));
+            rest = str::slice(rest, 0, w);
+        }
+        None => {}
+    }
+    // negative impls (impl !Send for T) don't give anything to index
+    if str::starts_with(rest, "!") {
+        return BodyCtx;
+    }
+    match str::find_str(rest, " for ") {
+        Some(f) => ImplCtx(str::trim(str::slice(rest, f+5, rest.len())),
+                           Some(str::trim(str::slice(rest, 0, f))),
+                           gens, wheres, ~[]),
+        None => ImplCtx(str::trim(rest), None, gens, wheres, ~[])
+    }
+}
+
+// with_generics adds the generics and where clause of an impl to the
+// signature of one of its methods, so the bounds on them aren't lost
+fn with_generics(sig: &~str, gens: &~str, wheres: &~str) -> ~str {
+    let mut s = copy *sig;
+    if gens.len() > 0 {
+        let paren = option::get_default(&str::find_char(s, '('), s.len());
+        s = match str::find_char(s, '<') {
+            Some(open) if open < paren => {
+                str::slice(s, 0, open+1) + *gens + ~", " +
+                    str::slice(s, open+1, s.len())
+            }
+            _ => {
+                str::slice(s, 0, paren) + ~"<" + *gens + ~">" +
+                    str::slice(s, paren, s.len())
+            }
+        };
+    }
+    if wheres.len() > 0 {
+        s += if str::contains(s, " where ") { ~", " } else { ~" where " };
+        s += *wheres;
+    }
+    return s;
+}
+
+// has_receiver says whether a function's first argument is self
+fn has_receiver(h: &~str) -> bool {
+    match str::find_char(*h, '(') {
+        Some(open) => {
+            let args = split_arguments(&str::slice(*h, open+1,
+                                                   matching_paren(h, open)));
+            args.len() > 0 && is_receiver(&args[0])
+        }
+        None => false
+    }
+}
+
+// matching_paren finds the ')' that closes the '(' at open
+fn matching_paren(s: &~str, open: uint) -> uint {
+    let mut level = 0;
+    for str::each_chari(*s) |i, c| {
+        if i < open { loop; }
+        if c == '(' { level += 1; }
+        if c == ')' {
+            level -= 1;
+            if level == 0 { return i; }
+        }
+    }
+    return s.len();
+}
+
+// strip_qualifiers takes the visibility and qualifiers (unsafe, const,
+// extern "C", etc) off of the front of an item header
+fn strip_qualifiers(h: &~str) -> ~str {
+    let mut words = str::words(*h);
+    while words.len() > 0 && is_qualifier(&words[0]) {
+        words = vec::tail(words);
+    }
+    return str::connect(words, " ");
+}
+
+fn is_qualifier(w: &~str) -> bool {
+    match *w {
+        ~"pub" | ~"unsafe" | ~"async" | ~"const" | ~"default" |
+        ~"extern" | ~"auto" => true,
+        _ => str::starts_with(*w, "pub(") || str::starts_with(*w, "\"")
+    }
+}
+
+// ident reads the identifier that starts at position start
+fn ident(h: &~str, start: uint) -> ~str {
+    let mut name = ~"";
+    for str::each_chari(*h) |i, c| {
+        if i < start { loop; }
+        if !(char::is_alphanumeric(c) || c == '_') { break; }
+        str::push_char(&mut name, c);
+    }
+    return name;
+}
+
+// current_module gives the path of the innermost module
+fn current_module(stack: &~[Context]) -> ~str {
+    let mut i = stack.len();
+    while i > 0 {
+        i -= 1;
+        match stack[i] {
+            ModCtx(ref path) => return copy *path,
+            _ => {}
+        }
+    }
+    return ~"";
+}
+
+// summary gives the first paragraph of a doc comment, on one line
+fn summary(docs: &~[~str]) -> ~str {
+    let mut para = ~[];
+    for docs.each |l| {
+        if l.len() == 0 { break; }
+        para.push(copy *l);
+    }
+    return str::connect(para, " ");
+}
+
+fn line_end(cs: &[char], i: uint) -> uint {
+    let mut j = i;
+    while j < cs.len() && cs[j] != '\n' { j += 1; }
+    return j;
+}
+
+// skip_block_comment skips a (possibly nested) block comment
+fn skip_block_comment(cs: &[char], i: uint) -> uint {
+    let mut level = 0;
+    let mut j = i;
+    while j + 1 < cs.len() {
+        if cs[j] == '/' && cs[j+1] == '*' {
+            level += 1;
+            j += 2;
+        } else if cs[j] == '*' && cs[j+1] == '/' {
+            level -= 1;
+            j += 2;
+            if level == 0 { return j; }
+        } else {
+            j += 1;
+        }
+    }
+    return cs.len();
+}
+
+// skip_string skips a string literal, escapes and all
+fn skip_string(cs: &[char], i: uint) -> uint {
+    let mut j = i + 1;
+    while j < cs.len() && cs[j] != '"' {
+        if cs[j] == '\\' { j += 1; }
+        j += 1;
+    }
+    return uint::min(j + 1, cs.len());
+}
+
+// is_raw_string says whether a raw string literal (r"..." or r#"..."#)
+// starts at i, rather than an identifier that happens to end in r
+fn is_raw_string(cs: &[char], i: uint) -> bool {
+    if cs[i] != 'r' || i + 1 >= cs.len() ||
+        (i > 0 && (char::is_alphanumeric(cs[i-1]) || cs[i-1] == '_')) {
+        return false;
+    }
+    let mut j = i + 1;
+    while j < cs.len() && cs[j] == '#' { j += 1; }
+    return j < cs.len() && cs[j] == '"';
+}
+
+fn skip_raw_string(cs: &[char], i: uint) -> uint {
+    let mut hashes = 0;
+    let mut j = i + 1;
+    while cs[j] == '#' { hashes += 1; j += 1; }
+    j += 1;
+    while j < cs.len() {
+        if cs[j] == '"' {
+            let mut k = 0;
+            while k < hashes && j + 1 + k < cs.len() && cs[j+1+k] == '#' {
+                k += 1;
+            }
+            if k == hashes { return j + 1 + k; }
+        }
+        j += 1;
+    }
+    return cs.len();
+}
+
+// skip_quote skips a character literal, or just the quote of a lifetime
+// (which is an identifier, so the rest of it is read normally)
+fn skip_quote(cs: &[char], i: uint) -> uint {
+    if i + 1 < cs.len() && cs[i+1] == '\\' {
+        let mut j = i + 2;
+        while j < cs.len() && cs[j] != '\'' { j += 1; }
+        return uint::min(j + 1, cs.len());
+    }
+    if i + 2 < cs.len() && cs[i+2] == '\'' {
+        return i + 3;
+    }
+    return i + 1;
+}
+
+// skip_attribute skips #[...] or #![...]
+fn skip_attribute(cs: &[char], i: uint) -> uint {
+    let mut level = 0;
+    let mut j = i;
+    while j < cs.len() {
+        if cs[j] == '[' { level += 1; }
+        if cs[j] == ']' {
+            level -= 1;
+            if level == 0 { return j + 1; }
+        }
+        if cs[j] == '"' {
+            j = skip_string(cs, j);
+        } else {
+            j += 1;
+        }
+    }
+    return cs.len();
+}
+
+#[cfg(test)]
+mod tests {
+
+    fn src() -> ~str {
+        ~"//! a test crate\n\
+          use std::fmt;\n\
+          \n\
+          /// Finds the first element.\n\
+          ///\n\
+          /// Really.\n\
+          pub fn first<T: Clone>(v: &[T]) -> Option<T> {\n\
+              let s = \"{ not a brace\"; let c = '}';\n\
+              v.iter().next().cloned()\n\
+          }\n\
+          \n\
+          pub struct Thing { a: u32 }\n\
+          pub type Things = Vec<Thing>;\n\
+          \n\
+          impl<'a> Thing {\n\
+              /// Makes a thing.\n\
+              pub fn new(a: u32) -> Thing { Thing { a: a } }\n\
+              pub fn get(&self) -> u32 { self.a }\n\
+          }\n\
+          \n\
+          pub trait Named {\n\
+              type Name;\n\
+              fn name(&self) -> String;\n\
+          }\n\
+          \n\
+          impl Named for Thing {\n\
+              fn name(&self) -> String { String::new() }\n\
+          }\n\
+          \n\
+          #[cfg(test)]\n\
+          mod tests {\n\
+              fn helper() {}\n\
+          }\n"
+    }
+
+    #[test]
+    fn test_extract() {
+        let recs = extract(&src(), &~"demo");
+        // first, the struct, the alias, new, get, the trait, and the impl
+        assert recs.len() == 7;
+        let data = load::from_records(&recs);
+        let first = vec::find(data.ar1.defs, |d| { d.name == ~"first" }).get();
+        assert first.desc == ~"Finds the first element.";
+        assert first.path == ~"demo";
+        assert first.signature ==
+            ~"fn first<T: Clone>(v: &[T]) -> Option<T>";
+        let get = vec::find(data.ar1.defs, |d| { d.name == ~"get" }).get();
+        // the types in signatures are given their full paths
+        assert data.types.get(~"Thing") == ~[~"demo::Thing"];
+        assert get.args == ~[@Ptr(Ref(None), @Basic(~"demo::Thing"))];
+        let new = vec::find(data.ar1.defs, |d| { d.name == ~"new" }).get();
+        assert new.args == ~[@Basic(~"u32")];
+        let name = vec::find(data.ar1.defs, |d| { d.name == ~"name" }).get();
+        assert name.trait_name == Some(~"Named");
+        assert data.impls.get(~"Named") == ~[~"demo::Thing"];
+        // the trait's associated type isn't an alias
+        assert data.aliases.size() == 1;
+        assert data.aliases.get(~"Things")[0].ty ==
+            @Parametric(@Basic(~"Vec"), ~[@Basic(~"Thing")]);
+    }
+
+    #[test]
+    fn test_package_name() {
+        let toml = ~"[lib]\nname = \"demo_lib\"\n\n\
+                     [package]\nname = \"my-demo\"\nversion = \"0.1.0\"\n\n\
+                     [[bin]]\nname = \"tool\"\n";
+        assert package_name(&toml) == Some(~"my_demo");
+        assert package_name(&~"[[bin]]\nname = \"tool\"\n") == None;
+    }
+
+    #[test]
+    fn test_parse_impl() {
+        match parse_impl(&~"impl<T: Eq> Foo<T> for Bar<T> where T: Ord") {
+            ImplCtx(self, tr, gens, wheres, _) => {
+                assert self == ~"Bar<T>";
+                assert tr == Some(~"Foo<T>");
+                assert gens == ~"T: Eq";
+                assert wheres == ~"T: Ord";
+            }
+            _ => fail
+        }
+    }
+
+    #[test]
+    fn test_with_generics() {
+        assert with_generics(&~"fn get(&self) -> T", &~"T: Copy", &~"") ==
+            ~"fn get<T: Copy>(&self) -> T";
+        assert with_generics(&~"fn map<U>(&self) -> U", &~"T", &~"T: Eq") ==
+            ~"fn map<T, U>(&self) -> U where T: Eq";
+    }
+
+    #[test]
+    fn test_strip_qualifiers() {
+        assert strip_qualifiers(&~"pub(crate) unsafe extern \"C\" fn f()") ==
+            ~"fn f()";
+    }
+}
@@ -1,38 +1,65 @@
 //! This file contains type definitions
 
-use std::treemap::*;
-type Set<T: Copy Ord> = TreeMap<T,()>;
-
 use core::cmp::{Eq, Ord};
 
-// Note that we are using treemaps to get unordered comparison.
-// because vecs are much more supported, we only convert to sets
-// when we compare. this is inefficient, but intended to be temporary.
-// once a more supported container is in the standard library, this will
-// all be pulled out, and some sort of set will be used throughout.
-fn Set<T: Copy Eq Ord>() -> Set<T> {
-    TreeMap()
-}
-fn set_equals<K: Copy Eq Ord>(t1: &const Set<K>,
-                              t2: &const Set<K>)
-                            -> bool {
-    let mut v1 = ~[];
-    let mut v2 = ~[];
-    traverse(*t1, |k,_v| { v1.push((copy *k))} );
-    traverse(*t2, |k,_v| { v2.push((copy *k))} );
-    return v1 == v2;
+// an Arg is a name, like str or Option, and then an optional list
+// of parameters. ex: Option<T> is "Option", ["T"] (roughly).
+// struct Arg { name: ~str, inner: ~[Arg] }
+
+// a Pointer is the kind of a pointer (or reference) to a type. references
+// keep their lifetime, if one was written, though it is never matched on.
+enum Pointer {
+    Ref(Option<~str>),
+    RefMut(Option<~str>),
+    Owned,
+    Managed,
+    RawConst,
+    RawMut
 }
-pub fn set_from_vec<T: Copy Ord Eq>(v: &~[T]) -> Set<T> {
-    let mut s = Set();
-    for v.each |e| {
-        insert(s,*e,());
+
+impl Pointer : Eq {
+    pure fn eq(other: &Pointer) -> bool {
+        match (self, *other) {
+            (Ref(ref l1), Ref(ref l2)) => l1 == l2,
+            (RefMut(ref l1), RefMut(ref l2)) => l1 == l2,
+            (Owned, Owned) | (Managed, Managed) |
+            (RawConst, RawConst) | (RawMut, RawMut) => true,
+            _ => false
+        }
+    }
+    pure fn ne(other: &Pointer) -> bool {
+        !self.eq(other)
     }
-    return s;
 }
 
-// an Arg is a name, like str or Option, and then an optional list
-// of parameters. ex: Option<T> is "Option", ["T"] (roughly).
-// struct Arg { name: ~str, inner: ~[Arg] }
+impl Pointer {
+    // same_kind compares pointers without their lifetimes
+    pure fn same_kind(other: &Pointer) -> bool {
+        match (self, *other) {
+            (Ref(_), Ref(_)) | (RefMut(_), RefMut(_)) => true,
+            _ => self == *other
+        }
+    }
+
+    // sigil gives how the pointer is written, ignoring the lifetime
+    pure fn sigil() -> ~str {
+        match self {
+            Ref(_) => ~"&",
+            RefMut(_) => ~"&mut",
+            Owned => ~"~",
+            Managed => ~"@",
+            RawConst => ~"*const",
+            RawMut => ~"*mut"
+        }
+    }
+
+    pure fn lifetime() -> Option<~str> {
+        match self {
+            Ref(ref l) | RefMut(ref l) => copy *l,
+            _ => None
+        }
+    }
+}
 
 enum Arg {
     Basic(~str),
@@ -43,26 +70,80 @@ enum Arg {
     Tuple(~[@Arg]),
     Vec(@Arg),
     Constrained(~str, ~[Constraint]),
-    Function(~[@Arg],@Arg)
+    Function(~[@Arg],@Arg),
+    // a pointer to (or reference to) a type, like &mut T or ~str
+    Ptr(Pointer, @Arg),
+    // a fixed size array, [T; N]. the length is a ConstArg, or a variable
+    // for a const generic parameter.
+    Array(@Arg, @Arg),
+    // trait objects (dyn Trait) and impl Trait types, by the traits in them
+    // and the associated types they fix (Iterator<Item = u8>)
+    Dyn(~[Constraint], ~[Binding]),
+    Impl(~[Constraint], ~[Binding]),
+    // a closure type, by the trait it is called through (Fn, FnMut or
+    // FnOnce), its arguments and its return type
+    Closure(~str, ~[@Arg], @Arg),
+    // the never type, !
+    Never,
+    // an associated type, like <T as Iterator>::Item (or T::Item, where the
+    // trait isn't given)
+    Projection(@Arg, Option<~str>, ~str),
+    // a constant given as a generic argument, like the 3 in [T; 3]
+    ConstArg(~str),
+    // a function type with qualifiers, like unsafe extern "C" fn(A) -> B.
+    // the qualifiers are kept as written, except that the abi is always
+    // given (extern means extern "C").
+    QualifiedFn(~str, ~[@Arg], @Arg),
+    // a hole (_) in a query, which matches any type without binding it
+    Hole
 }
 
-pub fn map_constrained(a: @Arg, f: fn(&~str, &~[Constraint]) -> @Arg) -> @Arg {
+// map_leaves rebuilds an Arg with f applied to each of the types in it that
+// don't have any other types inside of them (names, variables, and so on)
+pub fn map_leaves(a: @Arg, f: fn(@Arg) -> @Arg) -> @Arg {
     match *a {
-        Constrained(name, constraints) => f(&name, &constraints),
-        Tuple(args) => @Tuple(vec::map(args, |a| { map_constrained(*a, f)})),
-        Vec(arg) => @Vec(map_constrained(arg, f)),
+        Tuple(args) => @Tuple(vec::map(args, |a| { map_leaves(*a, f)})),
+        Vec(arg) => @Vec(map_leaves(arg, f)),
         Parametric(arg,args) => {
-            @Parametric(map_constrained(arg, f),
-                       vec::map(args, |a| { map_constrained(*a, f)}))
+            @Parametric(map_leaves(arg, f),
+                       vec::map(args, |a| { map_leaves(*a, f)}))
         }
         Function(args, ret) => {
-            @Function(vec::map(args, |a| { map_constrained(*a, f)}),
-                      map_constrained(ret, f))
+            @Function(vec::map(args, |a| { map_leaves(*a, f)}),
+                      map_leaves(ret, f))
+        }
+        Ptr(p, inner) => @Ptr(p, map_leaves(inner, f)),
+        Array(elem, len) => @Array(map_leaves(elem, f), map_leaves(len, f)),
+        Closure(tr, args, ret) => {
+            @Closure(tr, vec::map(args, |a| { map_leaves(*a, f)}),
+                     map_leaves(ret, f))
+        }
+        Projection(base, tr, item) => {
+            @Projection(map_leaves(base, f), tr, item)
+        }
+        QualifiedFn(qs, args, ret) => {
+            @QualifiedFn(qs, vec::map(args, |a| { map_leaves(*a, f)}),
+                         map_leaves(ret, f))
+        }
+        Dyn(cs, bs) => {
+            @Dyn(cs, vec::map(bs, |b| { b.map(|t| { map_leaves(t, f) }) }))
+        }
+        Impl(cs, bs) => {
+            @Impl(cs, vec::map(bs, |b| { b.map(|t| { map_leaves(t, f) }) }))
         }
-        Basic(_) => a
+        Basic(_) | Constrained(_, _) | Never | ConstArg(_) | Hole => f(a)
     }
 }
 
+pub fn map_constrained(a: @Arg, f: fn(&~str, &~[Constraint]) -> @Arg) -> @Arg {
+    map_leaves(a, |l| {
+        match *l {
+            Constrained(ref name, ref constraints) => f(name, constraints),
+            _ => l
+        }
+    })
+}
+
 pub fn traverse_constrained(a: @Arg, f: fn(&~str)) {
     map_constrained(a, |n,_cs| { f(n); a } );
 }
@@ -94,6 +175,43 @@ impl Constraint : Ord {
     }
 }
 
+// a Binding fixes an associated type of one of the traits of a trait
+// object or impl Trait type, like the Item = u8 in impl Iterator<Item = u8>
+struct Binding { tr: ~str, item: ~str, ty: @Arg }
+
+impl Binding {
+    // map gives the binding with f applied to its type
+    fn map(f: fn(@Arg) -> @Arg) -> Binding {
+        Binding { tr: copy self.tr, item: copy self.item, ty: f(self.ty) }
+    }
+}
+
+impl Binding : Eq {
+    pure fn eq(other: &Binding) -> bool {
+        self.tr == other.tr && self.item == other.item && self.ty == other.ty
+    }
+    pure fn ne(other: &Binding) -> bool {
+        !self.eq(other)
+    }
+}
+
+impl Binding : Ord {
+    pure fn ge(other: &Binding) -> bool {
+        !self.le(other) || self.eq(other)
+    }
+    pure fn le(other: &Binding) -> bool {
+        if self.tr != other.tr { self.tr <= other.tr }
+        else if self.item != other.item { self.item <= other.item }
+        else { self.ty <= other.ty }
+    }
+    pure fn gt(other: &Binding) -> bool {
+        !self.le(other)
+    }
+    pure fn lt(other: &Binding) -> bool {
+        self.le(other) && !self.eq(other)
+    }
+}
+
 pure fn arg_eq(s: &Arg, o: &Arg) -> bool {
     match (s, o) {
         (&Basic(ref s1), &Basic(ref s2)) => s1 == s2,
@@ -104,6 +222,19 @@ pure fn arg_eq(s: &Arg, o: &Arg) -> bool {
         (&Constrained(s1,c1),&Constrained(s2,c2)) =>
             (s1 == s2) && (c1 == c2),
         (&Function(a1,r1),&Function(a2,r2)) => (a1 == a2) && (r1 == r2),
+        (&Ptr(p1,t1),&Ptr(p2,t2)) => (p1 == p2) && (t1 == t2),
+        (&Array(t1,l1),&Array(t2,l2)) => (t1 == t2) && (l1 == l2),
+        (&Dyn(c1,b1),&Dyn(c2,b2)) => (c1 == c2) && (b1 == b2),
+        (&Impl(c1,b1),&Impl(c2,b2)) => (c1 == c2) && (b1 == b2),
+        (&Closure(ref t1,a1,r1),&Closure(ref t2,a2,r2)) =>
+            (t1 == t2) && (a1 == a2) && (r1 == r2),
+        (&Never,&Never) => true,
+        (&Projection(b1,ref t1,ref i1),&Projection(b2,ref t2,ref i2)) =>
+            (b1 == b2) && (t1 == t2) && (i1 == i2),
+        (&ConstArg(ref c1),&ConstArg(ref c2)) => c1 == c2,
+        (&QualifiedFn(ref q1,a1,r1),&QualifiedFn(ref q2,a2,r2)) =>
+            (q1 == q2) && (a1 == a2) && (r1 == r2),
+        (&Hole,&Hole) => true,
         _ => false
     }
 }
@@ -117,6 +248,29 @@ impl Arg : Eq {
     }
 }
 
+// variant_rank orders the variants of Arg, for comparing args of different
+// variants
+pure fn variant_rank(a: &Arg) -> uint {
+    match *a {
+        Basic(_) => 0,
+        Parametric(_,_) => 1,
+        Tuple(_) => 2,
+        Vec(_) => 3,
+        Constrained(_,_) => 4,
+        Function(_,_) => 5,
+        Ptr(_,_) => 6,
+        Array(_,_) => 7,
+        Dyn(_,_) => 8,
+        Impl(_,_) => 9,
+        Closure(_,_,_) => 10,
+        Never => 11,
+        Projection(_,_,_) => 12,
+        ConstArg(_) => 13,
+        QualifiedFn(_,_,_) => 14,
+        Hole => 15
+    }
+}
+
 pure fn arg_le(s: &Arg, o: &Arg) -> bool {
     match (s, o) {
         // we define ordering of variants, and then ordering within variants
@@ -129,19 +283,36 @@ pure fn arg_le(s: &Arg, o: &Arg) -> bool {
             if s1 == s2 { c1 <= c2 } else { s1 <= s2 },
         (&Function(a1,r1),&Function(a2,r2)) =>
             if a1 == a2 { r1 <= r2 } else { a1 <= a2 },
-        (&Basic(_), _) => true,
-        (_, &Basic(_)) => false,
-        (&Parametric(_,_), _) => true,
-        (_, &Parametric(_,_)) => false,
-        (&Tuple(_), _) => true,
-        (_, &Tuple(_)) => false,
-        (&Vec(_), _) => true,
-        (_, &Vec(_)) => false,
-        (&Constrained(_,_), _) => true,
-        (_, &Constrained(_,_)) => false,
-        // these are implicit in the above patterns
-        //(&Function(_,_), _) => true,
-        //(_, &Function(_,_)) => false,
+        (&Ptr(p1,t1),&Ptr(p2,t2)) =>
+            if p1 == p2 { t1 <= t2 }
+            else if p1.sigil() != p2.sigil() { p1.sigil() <= p2.sigil() }
+            else {
+                // the same kind of reference, with different lifetimes.
+                // one without a lifetime comes first.
+                match (p1.lifetime(), p2.lifetime()) {
+                    (Some(ref l1), Some(ref l2)) => l1 <= l2,
+                    (None, _) => true,
+                    (Some(_), None) => false
+                }
+            },
+        (&Array(t1,l1),&Array(t2,l2)) =>
+            if t1 == t2 { l1 <= l2 } else { t1 <= t2 },
+        (&Dyn(c1,b1),&Dyn(c2,b2)) =>
+            if c1 == c2 { b1 <= b2 } else { c1 <= c2 },
+        (&Impl(c1,b1),&Impl(c2,b2)) =>
+            if c1 == c2 { b1 <= b2 } else { c1 <= c2 },
+        (&Closure(ref t1,a1,r1),&Closure(ref t2,a2,r2)) =>
+            if t1 != t2 { t1 <= t2 }
+            else if a1 == a2 { r1 <= r2 } else { a1 <= a2 },
+        (&Never,&Never) => true,
+        (&Projection(b1,_,ref i1),&Projection(b2,_,ref i2)) =>
+            if b1 == b2 { i1 <= i2 } else { b1 <= b2 },
+        (&ConstArg(ref c1),&ConstArg(ref c2)) => c1 <= c2,
+        (&QualifiedFn(ref q1,a1,r1),&QualifiedFn(ref q2,a2,r2)) =>
+            if q1 != q2 { q1 <= q2 }
+            else if a1 == a2 { r1 <= r2 } else { a1 <= a2 },
+        (&Hole,&Hole) => true,
+        _ => variant_rank(s) <= variant_rank(o)
     }
 }
 
@@ -161,119 +332,507 @@ impl Arg : Ord {
     }
 }
 
-// a query is a set of arguments and a return type
-struct Query { args: ~[@Arg], ret: @Arg }
+// a query is a set of arguments and a return type. rest is set when the
+// query ends its arguments with .., so that it matches definitions that
+// take any other arguments as well.
+struct Query { args: ~[@Arg], ret: @Arg, rest: bool }
 
 impl Query : Eq {
     pure fn eq(other: &Query) -> bool {
-        (self.args == other.args) && (self.ret == other.ret)
+        (self.args == other.args) && (self.ret == other.ret) &&
+            (self.rest == other.rest)
     }
     pure fn ne(other: &Query) -> bool {
-        (self.args != other.args) || (self.ret != other.ret)
+        !self.eq(other)
+    }
+}
+
+// a ParseError is a signature or query that couldn't be parsed. pos is
+// the byte offset in the input that the problem was found at.
+struct ParseError { pos: uint, msg: ~str }
+
+impl ParseError : Eq {
+    pure fn eq(other: &ParseError) -> bool {
+        (self.pos == other.pos) && (self.msg == other.msg)
+    }
+    pure fn ne(other: &ParseError) -> bool {
+        (self.pos != other.pos) || (self.msg != other.msg)
+    }
+}
+
+impl ParseError {
+    // show gives a diagnostic pointing at the problem in the input, like
+    //   [int -> int
+    //   ^ unclosed [
+    fn show(input: &~str) -> ~str {
+        let pad = str::from_chars(vec::from_elem(self.pos, ' '));
+        fmt!("%s\n%s^ %s", *input, pad, self.msg)
+    }
+}
+
+// SearchOpts holds the switches that change how a search is done.
+// partial allows a query to give only some of the arguments of a function,
+// and crates restricts results to those crates (if there are any).
+// strict_refs makes pointers and references have to match exactly (so
+// &mut T only matches &mut T), while otherwise they are ignored on both
+// sides, which we call matching modulo references. aliases allows type
+// aliases to be expanded when matching (see alias.rs).
+struct SearchOpts { partial: bool, crates: ~[~str], strict_refs: bool,
+                    aliases: bool }
+
+fn default_opts() -> SearchOpts {
+    SearchOpts { partial: false, crates: ~[], strict_refs: false,
+                 aliases: true }
+}
+
+impl SearchOpts {
+    // allows says whether a definition is in one of the crates searched
+    fn allows(d: &Definition) -> bool {
+        self.crates.len() == 0 || vec::contains(self.crates, &d.crate_name)
     }
 }
 
 // a Definition is what we are trying to match against. Note that
 // definitions are not exactly unique, as they can be made more specific
-// (ie, A,B -> C can be A,A -> B, etc)
+// (ie, A,B -> C can be A,A -> B, etc). trait_name is set for methods of
+// traits, which are found through the types that implement the trait.
+// crate_name is the crate the definition came from, as several crates can
+// be loaded at once. receiver is set for methods that take self, whose
+// first argument is the type they are called on.
 struct Definition { name: ~str, path: ~str, anchor: ~str, desc: ~str,
-                    args: ~[@Arg], ret: @Arg, signature: ~str }
+                    args: ~[@Arg], ret: @Arg, signature: ~str,
+                    trait_name: Option<~str>, crate_name: ~str,
+                    receiver: bool }
 
 impl Definition : Eq {
     pure fn eq(other: &Definition) -> bool {
         (self.name == other.name) && (self.path == other.path) &&
         (self.anchor == other.anchor) && (self.desc == other.desc) &&
         (self.args == other.args) && (self.ret == other.ret) &&
-        (self.signature == other.signature)
+        (self.signature == other.signature) &&
+        (self.trait_name == other.trait_name) &&
+        (self.crate_name == other.crate_name) &&
+        (self.receiver == other.receiver)
     }
     pure fn ne(other: &Definition) -> bool {
         (self.name != other.name) || (self.path != other.path) ||
         (self.anchor != other.anchor) || (self.desc != other.desc) ||
         (self.args != other.args) || (self.ret != other.ret) ||
-        (self.signature != other.signature)
+        (self.signature != other.signature) ||
+        (self.trait_name != other.trait_name) ||
+        (self.crate_name != other.crate_name) ||
+        (self.receiver != other.receiver)
     }
 }
 
 // fn show_def returns a representation of the definition suitable for printing
 impl Definition {
     fn show() -> ~str {
-        fmt!("%s::%s - %s - %s", self.path,
-             self.name, self.signature, self.desc)
+        self.show_as(&self.signature)
+    }
+
+    // show_as shows the definition with something else where its signature
+    // would go
+    fn show_as(sig: &~str) -> ~str {
+        match self.trait_name {
+            Some(ref t) => fmt!("%s::%s::%s - %s - (trait method) %s",
+                                self.path, *t, self.name, *sig, self.desc),
+            None => fmt!("%s::%s - %s - %s", self.path,
+                         self.name, *sig, self.desc)
+        }
+    }
+
+    // call shows how a method is called, with types in place of values, like
+    // Option<A>.map(fn(A) -> B) -> Option<B>. the receiver is written
+    // without its pointer, as method calls take one automatically.
+    fn call() -> ~str {
+        let recv = match self.args[0] {
+            @Ptr(_, inner) => inner,
+            a => a
+        };
+        let args = vec::map(vec::view(self.args, 1, self.args.len()),
+                            |a| { a.to_str() });
+        let ret = if self.ret == @Basic(~"()") { ~"" }
+                  else { ~" -> " + self.ret.to_str() };
+        fmt!("%s.%s(%s)%s", recv.to_str(), self.name,
+             str::connect(args, ", "), ret)
+    }
+}
+
+// a Match is a definition found by a search, along with how well it
+// matched. lower scores are better (see score.rs). coercions says how any
+// types that were coerced to match were (see coerce.rs), and receiver
+// whether the query's first argument matched the receiver of a method.
+// resolved gives the full paths of the types the definition has for names
+// in the query that could be more than one type (see paths.rs), and how
+// says what kind of match it was (see score::how_typed and
+// names::how_named).
+struct Match { def: @Definition, score: uint, coercions: ~[~str],
+               receiver: bool, resolved: ~[~str], how: ~str }
+
+impl Match : Eq {
+    pure fn eq(other: &Match) -> bool {
+        (self.def == other.def) && (self.score == other.score) &&
+            (self.coercions == other.coercions) &&
+            (self.receiver == other.receiver) &&
+            (self.resolved == other.resolved) && (self.how == other.how)
+    }
+    pure fn ne(other: &Match) -> bool {
+        !self.eq(other)
+    }
+}
+
+impl Match {
+    // signature is the definition's signature, after how it is called when
+    // it matched as a method
+    fn signature() -> ~str {
+        if self.receiver {
+            fmt!("%s - %s", self.def.call(), self.def.signature)
+        } else {
+            copy self.def.signature
+        }
+    }
+
+    // show is the definition, followed by any coercions that were needed
+    // to match it, and which types ambiguous names in the query were
+    fn show() -> ~str {
+        let mut shown = self.def.show_as(&self.signature());
+        if self.coercions.len() > 0 {
+            shown += fmt!(" (coerced %s)", str::connect(self.coercions, ", "));
+        }
+        let which = self.which();
+        if which.len() > 0 {
+            shown += fmt!(" (%s)", which);
+        }
+        return shown;
+    }
+
+    // which says what the ambiguous names in the query were for this
+    // definition, like "Error is std::io::Error"
+    fn which() -> ~str {
+        str::connect(vec::map(self.resolved, |p| {
+            let segs = str::split_str(*p, "::");
+            fmt!("%s is %s", segs[segs.len() - 1], *p)
+        }), ", ")
+    }
+}
+
+// to_json gives the machine readable form of a match, for --json output
+impl Match : ToJson {
+    fn to_json() -> Json {
+        let mut obj = send_map::linear::LinearMap();
+        obj.insert(~"name", String(copy self.def.name));
+        obj.insert(~"crate", String(copy self.def.crate_name));
+        obj.insert(~"path", String(copy self.def.path));
+        obj.insert(~"anchor", String(copy self.def.anchor));
+        obj.insert(~"signature", String(copy self.def.signature));
+        obj.insert(~"desc", String(copy self.def.desc));
+        match self.def.trait_name {
+            Some(ref t) => obj.insert(~"trait", String(copy *t)),
+            None => obj.insert(~"trait", Null)
+        };
+        obj.insert(~"args",
+                   List(vec::map(self.def.args, |a| { a.to_json() })));
+        obj.insert(~"ret", self.def.ret.to_json());
+        obj.insert(~"score", Number(self.score as float));
+        obj.insert(~"coercions",
+                   List(vec::map(self.coercions, |c| { String(copy *c) })));
+        obj.insert(~"receiver", Boolean(self.def.receiver));
+        obj.insert(~"resolved",
+                   List(vec::map(self.resolved, |p| { String(copy *p) })));
+        obj.insert(~"match", String(copy self.how));
+        if self.receiver {
+            obj.insert(~"call", String(self.def.call()));
+        } else {
+            obj.insert(~"call", Null);
+        }
+        Object(~obj)
+    }
+}
+
+// bindings_json gives the associated types bound in a trait object or impl
+// Trait type
+fn bindings_json(bs: &~[Binding]) -> Json {
+    List(vec::map(*bs, |b| {
+        let mut obj = send_map::linear::LinearMap();
+        obj.insert(~"trait", String(copy b.tr));
+        obj.insert(~"item", String(copy b.item));
+        obj.insert(~"type", b.ty.to_json());
+        Object(~obj)
+    }))
+}
+
+// to_json gives the parsed form of a type, for tools that want more than
+// the signature. type variables are "var"s, with the traits bounding them.
+impl Arg : ToJson {
+    fn to_json() -> Json {
+        let mut obj = send_map::linear::LinearMap();
+        match self {
+            Basic(ref n) => {
+                obj.insert(~"kind", String(~"basic"));
+                obj.insert(~"name", String(copy *n));
+            }
+            Parametric(base, ref params) => {
+                obj.insert(~"kind", String(~"parametric"));
+                obj.insert(~"base", base.to_json());
+                obj.insert(~"params",
+                           List(vec::map(*params, |a| { a.to_json() })));
+            }
+            Tuple(ref args) => {
+                obj.insert(~"kind", String(~"tuple"));
+                obj.insert(~"args",
+                           List(vec::map(*args, |a| { a.to_json() })));
+            }
+            Vec(inner) => {
+                obj.insert(~"kind", String(~"vec"));
+                obj.insert(~"elem", inner.to_json());
+            }
+            Constrained(ref n, ref cs) => {
+                obj.insert(~"kind", String(~"var"));
+                obj.insert(~"name", String(copy *n));
+                obj.insert(~"bounds",
+                           List(vec::map(*cs, |c| { String(copy **c) })));
+            }
+            Function(ref args, ret) => {
+                obj.insert(~"kind", String(~"fn"));
+                obj.insert(~"args",
+                           List(vec::map(*args, |a| { a.to_json() })));
+                obj.insert(~"ret", ret.to_json());
+            }
+            Ptr(p, inner) => {
+                obj.insert(~"kind", String(~"ptr"));
+                obj.insert(~"ptr", String(p.sigil()));
+                match p.lifetime() {
+                    Some(l) => obj.insert(~"lifetime", String(l)),
+                    None => obj.insert(~"lifetime", Null)
+                };
+                obj.insert(~"to", inner.to_json());
+            }
+            Array(elem, len) => {
+                obj.insert(~"kind", String(~"array"));
+                obj.insert(~"elem", elem.to_json());
+                obj.insert(~"len", len.to_json());
+            }
+            Dyn(ref cs, ref bs) => {
+                obj.insert(~"kind", String(~"dyn"));
+                obj.insert(~"bounds",
+                           List(vec::map(*cs, |c| { String(copy **c) })));
+                obj.insert(~"bindings", bindings_json(bs));
+            }
+            Impl(ref cs, ref bs) => {
+                obj.insert(~"kind", String(~"impl"));
+                obj.insert(~"bounds",
+                           List(vec::map(*cs, |c| { String(copy **c) })));
+                obj.insert(~"bindings", bindings_json(bs));
+            }
+            Closure(ref tr, ref args, ret) => {
+                obj.insert(~"kind", String(~"closure"));
+                obj.insert(~"trait", String(copy *tr));
+                obj.insert(~"args",
+                           List(vec::map(*args, |a| { a.to_json() })));
+                obj.insert(~"ret", ret.to_json());
+            }
+            Never => {
+                obj.insert(~"kind", String(~"never"));
+            }
+            Projection(base, ref tr, ref item) => {
+                obj.insert(~"kind", String(~"projection"));
+                obj.insert(~"base", base.to_json());
+                match *tr {
+                    Some(ref t) => obj.insert(~"trait", String(copy *t)),
+                    None => obj.insert(~"trait", Null)
+                };
+                obj.insert(~"item", String(copy *item));
+            }
+            ConstArg(ref c) => {
+                obj.insert(~"kind", String(~"const"));
+                obj.insert(~"value", String(copy *c));
+            }
+            QualifiedFn(ref qs, ref args, ret) => {
+                obj.insert(~"kind", String(~"fn"));
+                obj.insert(~"qualifiers", String(copy *qs));
+                obj.insert(~"args",
+                           List(vec::map(*args, |a| { a.to_json() })));
+                obj.insert(~"ret", ret.to_json());
+            }
+            Hole => {
+                obj.insert(~"kind", String(~"hole"));
+            }
+        }
+        Object(~obj)
+    }
+}
+
+// an Arg is written out the way it is in a signature, so that parsing what
+// to_str gives is the same Arg again. constraints aren't part of a type, so
+// they aren't written.
+impl Arg : ToStr {
+    fn to_str() -> ~str {
+        fn show(a: @Arg) -> ~str {
+            (*a).to_str()
+        }
+        fn list(args: &~[@Arg]) -> ~str {
+            str::connect(vec::map(*args, |a| { show(*a) }), ", ")
+        }
+        fn ret(r: @Arg) -> ~str {
+            if *r == Basic(~"()") { ~"" } else { ~" -> " + show(r) }
+        }
+        // traits gives each trait with the associated types bound for it
+        fn traits(cs: &~[Constraint], bs: &~[Binding]) -> ~str {
+            str::connect(vec::map(*cs, |c| {
+                let mine = vec::filter(*bs, |b| { b.tr == **c });
+                if mine.len() == 0 {
+                    copy **c
+                } else {
+                    fmt!("%s<%s>", **c, str::connect(vec::map(mine, |b| {
+                        fmt!("%s = %s", b.item, show(b.ty))
+                    }), ", "))
+                }
+            }), " + ")
+        }
+        match self {
+            Basic(ref n) | Constrained(ref n, _) | ConstArg(ref n) => copy *n,
+            Parametric(base, ref params) => {
+                fmt!("%s<%s>", show(base), list(params))
+            }
+            // a tuple of one needs its comma
+            Tuple(ref args) if args.len() == 1 => fmt!("(%s,)", show(args[0])),
+            Tuple(ref args) => fmt!("(%s)", list(args)),
+            Vec(inner) => fmt!("[%s]", show(inner)),
+            Function(ref args, r) => fmt!("fn(%s)%s", list(args), ret(r)),
+            Ptr(p, inner) => {
+                let lifetime = match p.lifetime() {
+                    Some(l) => l + ~" ",
+                    None => ~""
+                };
+                match p {
+                    Ref(_) => fmt!("&%s%s", lifetime, show(inner)),
+                    RefMut(_) => fmt!("&%smut %s", lifetime, show(inner)),
+                    Owned | Managed => p.sigil() + show(inner),
+                    RawConst | RawMut => fmt!("%s %s", p.sigil(), show(inner))
+                }
+            }
+            Array(elem, len) => fmt!("[%s; %s]", show(elem), show(len)),
+            Dyn(ref cs, ref bs) => ~"dyn " + traits(cs, bs),
+            Impl(ref cs, ref bs) => ~"impl " + traits(cs, bs),
+            Closure(ref tr, ref args, r) => {
+                fmt!("%s(%s)%s", *tr, list(args), ret(r))
+            }
+            Never => ~"!",
+            Hole => ~"_",
+            Projection(base, Some(ref tr), ref item) => {
+                fmt!("<%s as %s>::%s", show(base), *tr, *item)
+            }
+            // T::Item is only short for <T>::Item when T is a variable
+            Projection(@Constrained(ref n, _), None, ref item) => {
+                fmt!("%s::%s", *n, *item)
+            }
+            Projection(base, None, ref item) => {
+                fmt!("<%s>::%s", show(base), *item)
+            }
+            QualifiedFn(ref qs, ref args, r) => {
+                fmt!("%s fn(%s)%s", *qs, list(args), ret(r))
+            }
+        }
     }
 }
 
 // A bucket holds a bunch of definitions
 struct Bucket { defs: ~[@Definition] }
 
-// A trie is used to look up names efficiently by prefix. We assume that
-// most searches will be by the beginning of names, and can expand later.
-// This allows a fast simple implementation (hashing used here for simplicity
-// as well, though it is totally unnecessary).
-struct Trie { children: HashMap<~str,@Trie>, mut defs: ~[@Definition] }
+// The name index maps each name to the definitions with it. there are a
+// lot fewer names than definitions, so a name query goes through all of
+// them, matching each loosely (see names.rs).
+type NameIndex = HashMap<~str, ~[@Definition]>;
+
+// Impls records which types implement each trait. types are recorded by
+// their head name (see head_name), so Option<int> is just Option - or
+// core::option::Option, once the name is resolved (see paths.rs).
+type Impls = HashMap<~str, ~[~str]>;
+
+// Types records the full path of every type declared in the data, under
+// its name - Error is std::io::Error and std::fmt::Error (see paths.rs)
+type Types = HashMap<~str, ~[~str]>;
+
+// an Alias is a type alias, like type Result<T> = Result<T, Error>, in the
+// module path. params are its type parameters, which are variables in ty,
+// and defaults has the default of each of them, if it has one.
+struct Alias { name: ~str, path: ~str, params: ~[~str],
+               defaults: ~[Option<@Arg>], ty: @Arg }
+
+impl Alias : Eq {
+    pure fn eq(other: &Alias) -> bool {
+        (self.name == other.name) && (self.path == other.path) &&
+            (self.params == other.params) &&
+            (self.defaults == other.defaults) && (self.ty == other.ty)
+    }
+    pure fn ne(other: &Alias) -> bool {
+        !self.eq(other)
+    }
+}
+
+// Aliases maps each name to the aliases with it, as several modules can
+// declare one - std::io and std::fmt both have a Result.
+type Aliases = HashMap<~str, ~[@Alias]>;
 
 // Data stores all the definitions in buckets, based on function arity.
+// modules maps each module path to the order it was first seen in, which
+// is used as a rough measure of how popular it is.
 struct Data { ar0: Bucket, ar1: Bucket, ar2: Bucket,
               ar3: Bucket, ar4: Bucket, ar5: Bucket,
-              arn: Bucket, names: @Trie }
+              arn: Bucket, names: NameIndex, modules: HashMap<~str,uint>,
+              impls: Impls, aliases: Aliases, types: Types }
 
 fn empty_data() -> Data {
     let empty_bucket = Bucket { defs: ~[] };
-    let empty_trie = Trie { children: HashMap(), defs: ~[] };
     Data { ar0: empty_bucket, ar1: empty_bucket, ar2: empty_bucket,
            ar3: empty_bucket, ar4: empty_bucket, ar5: empty_bucket,
-           arn: empty_bucket, names: @empty_trie}
+           arn: empty_bucket, names: HashMap(), modules: HashMap(),
+           impls: HashMap(), aliases: HashMap(), types: HashMap()}
+}
+
+// head_name gives the name that trait implementations are recorded under
+// for a type, which is its name with whatever path it was resolved to or
+// written with (see same_name for comparing them). type variables don't
+// have one, and pointers are recorded under the type they point to. the
+// types behind trait objects, impl Trait, associated types and holes
+// aren't known, so they don't have one either.
+pub fn head_name(a: @Arg) -> Option<~str> {
+    match a {
+        @Basic(ref n) => Some(copy *n),
+        @Parametric(base, _) => head_name(base),
+        @Tuple(_) => Some(~"()"),
+        @Vec(_) => Some(~"[]"),
+        @Function(_, _) => Some(~"fn"),
+        @Constrained(_, _) => None,
+        @Ptr(_, inner) => head_name(inner),
+        @Array(_, _) => Some(~"[;]"),
+        @Closure(_, _, _) | @QualifiedFn(_, _, _) => Some(~"fn"),
+        @Never => Some(~"!"),
+        @Dyn(_, _) | @Impl(_, _) | @Projection(_, _, _) | @ConstArg(_) |
+        @Hole => None
+    }
 }
 
+// letters gives the nth canonical name for a type variable: A to Z, then
+// AA, AB, and so on (like the columns of a spreadsheet), so there are
+// always enough
 fn letters(n: uint) -> @~str {
-    match n {
-        0  => @~"A",
-        1  => @~"B",
-        2  => @~"C",
-        3  => @~"D",
-        4  => @~"E",
-        5  => @~"F",
-        6  => @~"G",
-        7  => @~"H",
-        8  => @~"I",
-        9  => @~"J",
-        10 => @~"K",
-        11 => @~"L",
-        12 => @~"M",
-        13 => @~"N",
-        14 => @~"O",
-        15 => @~"P",
-        16 => @~"Q",
-        17 => @~"R",
-        18 => @~"S",
-        19 => @~"T",
-        20 => @~"U",
-        21 => @~"V",
-        22 => @~"W",
-        23 => @~"X",
-        24 => @~"Y",
-        25 => @~"Z",
-        _  => fail ~"not enough letters"
+    let mut n = n;
+    let mut name = ~"";
+    loop {
+        name = str::from_char((('A' as uint) + n % 26) as char) + name;
+        if n < 26 {
+            break;
+        }
+        n = n / 26 - 1;
     }
+    return @name;
 }
 
 #[cfg(test)]
 mod tests {
 
-    #[test]
-    fn test_set_equals() {
-        let v1 : ~[uint] = ~[1,2,4];
-        let v2 : ~[uint] = ~[2,1,4];
-        assert set_equals(&set_from_vec(&v1), &set_from_vec(&v2));
-
-        let u1 = ~[@Basic(~"uint"),
-                   @Vec(@Basic(~"A")),
-                   @Basic(~"A")];
-        let u2 = ~[@Vec(@Basic(~"A")),
-                   @Basic(~"A"),
-                   @Basic(~"uint")];
-        assert set_equals(&set_from_vec(&u1), &set_from_vec(&u2));
-    }
-
     #[test]
     fn arg_eq() {
         assert @Basic(~"uint") == @Basic(~"uint");
@@ -286,13 +845,109 @@ mod tests {
             Definition { name: ~"foo", path: ~"core::foo", anchor: ~"fun-foo",
                          desc: ~"foo does bar", args: ~[],
                          ret: @Basic(~"int"),
-                         signature: ~"fn foo() -> int" };
+                         signature: ~"fn foo() -> int", trait_name: None,
+                         crate_name: ~"core", receiver: false };
         assert d.show() == ~"core::foo::foo - fn foo() -> int - foo does bar";
+        let t = Definition { trait_name: Some(~"Foo"), ..d };
+        assert t.show() ==
+            ~"core::foo::Foo::foo - fn foo() -> int - (trait method) foo does bar";
+    }
+
+    #[test]
+    fn test_match_show() {
+        let a = @Constrained(~"A", ~[]);
+        let d = @Definition { name: ~"push", path: ~"core::vec",
+            anchor: ~"", desc: ~"adds x",
+            args: ~[@Ptr(RefMut(None), @Vec(a)), a],
+            ret: @Basic(~"()"), signature: ~"fn push(&mut self, x: T)",
+            trait_name: None, crate_name: ~"core", receiver: true };
+        assert d.call() == ~"[A].push(A)";
+        let m = Match { def: d, score: 0, coercions: ~[], receiver: false,
+                        resolved: ~[], how: ~"exact" };
+        assert m.show() ==
+            ~"core::vec::push - fn push(&mut self, x: T) - adds x";
+        assert Match { receiver: true, ..m }.show() ==
+            ~"core::vec::push - [A].push(A) - fn push(&mut self, x: T) - \
+              adds x";
+        assert Match { coercions: ~[~"String to str"], ..m }.show() ==
+            ~"core::vec::push - fn push(&mut self, x: T) - adds x \
+              (coerced String to str)";
+        assert Match { resolved: ~[~"std::io::Error"], ..m }.show() ==
+            ~"core::vec::push - fn push(&mut self, x: T) - adds x \
+              (Error is std::io::Error)";
+        match Match { how: ~"generalized", ..m }.to_json() {
+            Object(obj) => assert obj.get(&~"match") ==
+                String(~"generalized"),
+            _ => fail
+        }
+    }
+
+    #[test]
+    fn test_arg_to_json() {
+        let a = @Parametric(@Basic(~"Option"),
+                            ~[@Constrained(~"A", ~[Constraint(~"Ord")])]);
+        assert json::to_str(&a.to_json()) ==
+            json::to_str(&json::from_str(
+                ~"{\"kind\": \"parametric\", \
+                   \"base\": {\"kind\": \"basic\", \"name\": \"Option\"}, \
+                   \"params\": [{\"kind\": \"var\", \"name\": \"A\", \
+                                 \"bounds\": [\"Ord\"]}]}").get());
+    }
+
+    #[test]
+    fn test_parse_error_show() {
+        let e = ParseError { pos: 3, msg: ~"unclosed <" };
+        assert e.show(&~"Vec<int -> int") ==
+            ~"Vec<int -> int\n   ^ unclosed <";
+    }
+
+    #[test]
+    fn test_allows() {
+        let d =
+            Definition { name: ~"foo", path: ~"core::foo", anchor: ~"fun-foo",
+                         desc: ~"", args: ~[], ret: @Basic(~"int"),
+                         signature: ~"fn foo() -> int", trait_name: None,
+                         crate_name: ~"core", receiver: false };
+        assert default_opts().allows(&d);
+        assert SearchOpts { crates: ~[~"core"], ..default_opts() }.allows(&d);
+        assert !SearchOpts { crates: ~[~"std"], ..default_opts() }.allows(&d);
+    }
+
+    #[test]
+    fn test_head_name() {
+        assert head_name(@Basic(~"int")) == Some(~"int");
+        assert head_name(@Basic(~"std::io::Error")) ==
+            Some(~"std::io::Error");
+        assert head_name(@Parametric(@Basic(~"Option"),
+                                     ~[@Basic(~"int")])) == Some(~"Option");
+        assert head_name(@Constrained(~"A", ~[])) == None;
+        assert head_name(@Ptr(RefMut(None), @Basic(~"int"))) == Some(~"int");
+    }
+
+    #[test]
+    fn test_pointer() {
+        assert Ref(Some(~"'a")).same_kind(&Ref(None));
+        assert Ref(Some(~"'a")) != Ref(None);
+        assert !Ref(None).same_kind(&RefMut(None));
+        assert !RawConst.same_kind(&RawMut);
+        assert @Ptr(Owned, @Basic(~"str")) != @Basic(~"str");
+        assert @Ptr(Ref(None), @Basic(~"a")) <=
+            @Ptr(RefMut(None), @Basic(~"a"));
+        // only one way round, so that sorting is consistent
+        let a = @Ptr(Ref(Some(~"'a")), @Basic(~"a"));
+        let b = @Ptr(Ref(None), @Basic(~"a"));
+        let c = @Ptr(Ref(Some(~"'b")), @Basic(~"a"));
+        assert b <= a && !(a <= b);
+        assert a <= c && !(c <= a);
     }
 
     #[test]
     fn test_letters() {
         assert letters(1) == @~"B";
         assert letters(25) == @~"Z";
+        assert letters(26) == @~"AA";
+        assert letters(27) == @~"AB";
+        assert letters(701) == @~"ZZ";
+        assert letters(702) == @~"AAA";
     }
 }
\ No newline at end of file
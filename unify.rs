@@ -0,0 +1,1025 @@
+//! This file contains the unifier used to match queries against definitions
+
+use types::*;
+use coerce::*;
+
+// a Subst records what type variables have been bound to. it is kept as an
+// association list rather than a map because the matcher backtracks a lot,
+// and copying a short vec is cheaper (and simpler) than copying a map.
+pub type Subst = ~[(~str, @Arg)];
+
+// lookup finds the binding of a variable, if it has one
+fn lookup(s: &Subst, n: &~str) -> Option<@Arg> {
+    for s.each |p| {
+        let (name, a) = copy *p;
+        if name == *n {
+            return Some(a);
+        }
+    }
+    return None;
+}
+
+// resolve follows variable bindings until it reaches either an unbound
+// variable or a type that isn't a variable
+pub fn resolve(a: @Arg, s: &Subst) -> @Arg {
+    match a {
+        @Constrained(ref n, _) => {
+            match lookup(s, n) {
+                Some(b) => resolve(b, s),
+                None => a
+            }
+        }
+        _ => a
+    }
+}
+
+// occurs checks whether the variable n appears anywhere inside of a (once
+// bindings are followed). binding a variable to a type containing itself
+// would give an infinite type, so we refuse to.
+fn occurs(n: &~str, a: @Arg, s: &Subst) -> bool {
+    let mut found = false;
+    traverse_constrained(a, |m| {
+        if *m == *n {
+            found = true;
+        } else {
+            match lookup(s, m) {
+                Some(b) => if occurs(n, b, s) { found = true },
+                None => {}
+            }
+        }
+    });
+    return found;
+}
+
+// bind records that the variable n stands for the type a
+fn bind(n: &~str, a: @Arg, s: &Subst) -> Option<Subst> {
+    if occurs(n, a, s) {
+        return None;
+    }
+    let mut s2 = copy *s;
+    s2.push((copy *n, a));
+    return Some(s2);
+}
+
+// unify tries to make two types equal by binding type variables on either
+// side, returning the extended substitution if it is possible
+pub fn unify(a: @Arg, b: @Arg, s: &Subst) -> Option<Subst> {
+    let a = resolve(a, s);
+    let b = resolve(b, s);
+    match (a, b) {
+        (@Hole, _) | (_, @Hole) => Some(copy *s),
+        (@Constrained(ref n1, _), @Constrained(ref n2, _)) if n1 == n2 => {
+            Some(copy *s)
+        }
+        (@Constrained(ref n, _), _) => bind(n, b, s),
+        (_, @Constrained(ref n, _)) => bind(n, a, s),
+        (@Basic(ref n1), @Basic(ref n2)) => {
+            if same_name(n1, n2) { Some(copy *s) } else { None }
+        }
+        (@Vec(i1), @Vec(i2)) => unify(i1, i2, s),
+        (@Tuple(a1), @Tuple(a2)) => unify_all(a1, a2, s),
+        (@Parametric(b1, a1), @Parametric(b2, a2)) => {
+            match unify(b1, b2, s) {
+                Some(s2) => unify_all(a1, a2, &s2),
+                None => None
+            }
+        }
+        (@Function(a1, r1), @Function(a2, r2)) => unify_fn(a1, r1, a2, r2, s),
+        // lifetimes are never matched on, only the kind of pointer
+        (@Ptr(p1, i1), @Ptr(p2, i2)) => {
+            if p1.same_kind(&p2) { unify(i1, i2, s) } else { None }
+        }
+        (@Array(t1, l1), @Array(t2, l2)) => {
+            match unify(t1, t2, s) {
+                Some(s2) => unify(l1, l2, &s2),
+                None => None
+            }
+        }
+        (@Dyn(c1, b1), @Dyn(c2, b2)) | (@Impl(c1, b1), @Impl(c2, b2)) => {
+            if same_traits(c1, c2) { unify_bindings(b1, b2, s) } else { None }
+        }
+        (@Closure(t1, a1, r1), @Closure(t2, a2, r2)) if t1 == t2 => {
+            unify_fn(a1, r1, a2, r2, s)
+        }
+        // a plain function can be called through any of the closure traits
+        (@Closure(_, a1, r1), @Function(a2, r2)) |
+        (@Function(a1, r1), @Closure(_, a2, r2)) => {
+            unify_fn(a1, r1, a2, r2, s)
+        }
+        (@QualifiedFn(q1, a1, r1), @QualifiedFn(q2, a2, r2)) if q1 == q2 => {
+            unify_fn(a1, r1, a2, r2, s)
+        }
+        (@Never, @Never) => Some(copy *s),
+        // an associated type with no trait given could be from any of them
+        (@Projection(b1, t1, i1), @Projection(b2, t2, i2)) if i1 == i2 &&
+            (t1.is_none() || t2.is_none() || t1 == t2) => unify(b1, b2, s),
+        (@ConstArg(c1), @ConstArg(c2)) => {
+            if c1 == c2 { Some(copy *s) } else { None }
+        }
+        _ => None
+    }
+}
+
+// unify_fn unifies the arguments and return types of two function types
+fn unify_fn(a1: &[@Arg], r1: @Arg, a2: &[@Arg], r2: @Arg, s: &Subst)
+        -> Option<Subst> {
+    match unify(r1, r2, s) {
+        Some(s2) => unify_all(a1, a2, &s2),
+        None => None
+    }
+}
+
+// same_name says whether two type names could be the same type. a name
+// can be written with some or all of its path (io::Error) or without it
+// (Error), as it is in the module it is declared in, so the shorter path
+// only has to be the end of the longer.
+pub fn same_name(n1: &~str, n2: &~str) -> bool {
+    if *n1 == *n2 {
+        return true;
+    }
+    let s1 = str::split_str(*n1, "::");
+    let s2 = str::split_str(*n2, "::");
+    let (short, long) = if s1.len() <= s2.len() { (s1, s2) } else { (s2, s1) };
+    return vec::slice(long, long.len() - short.len(), long.len()) == short;
+}
+
+// same_traits says whether two lists of traits have the same traits in
+// them, in any order
+fn same_traits(c1: &[Constraint], c2: &[Constraint]) -> bool {
+    c1.len() == c2.len() && c1.all(|c| { vec::contains(c2, c) })
+}
+
+// unify_bindings unifies the types that two sets of traits bind the same
+// associated type to. one bound on only one side is left alone, as the
+// other side's is some type, which could be it.
+fn unify_bindings(b1: &[Binding], b2: &[Binding], s: &Subst)
+        -> Option<Subst> {
+    let mut cur = copy *s;
+    for b1.each |x| {
+        for b2.each |y| {
+            if x.tr == y.tr && x.item == y.item {
+                match unify(x.ty, y.ty, &cur) {
+                    Some(s2) => cur = s2,
+                    None => return None
+                }
+            }
+        }
+    }
+    return Some(cur);
+}
+
+// unify_all unifies two lists of types pairwise, in order
+pub fn unify_all(xs: &[@Arg], ys: &[@Arg], s: &Subst) -> Option<Subst> {
+    if xs.len() != ys.len() {
+        return None;
+    }
+    let mut cur = copy *s;
+    let mut i = 0;
+    while i < xs.len() {
+        match unify(xs[i], ys[i], &cur) {
+            Some(s2) => cur = s2,
+            None => return None
+        }
+        i += 1;
+    }
+    return Some(cur);
+}
+
+// unify_unordered unifies every query argument with a distinct definition
+// argument, in any order. this is what gives us order-independent matching
+// of arguments (only at the top level - inner types are still ordered).
+// it gives the substitution along with how many pairs of arguments ended
+// up the other way around, and where each query argument went (see
+// place_args).
+pub fn unify_unordered(qs: &[@Arg], ds: &[@Arg], s: &Subst)
+        -> Option<(Subst, uint, ~[uint])> {
+    place_args(qs, ds, s, |q, d, s| { unify(q, d, s) })
+}
+
+// the most pairs of arguments place_args tries to match while looking for
+// the best placement. definitions with many arguments have far too many
+// ways of placing a query's arguments to try them all, so once this many
+// have been tried the best placement found so far is used (or, if none
+// was, one found without searching - see fallback).
+const MAX_TRIES: uint = 2000;
+
+// place_args matches every query argument with a distinct definition
+// argument, using f to match each pair and to carry along what it has
+// found (a substitution, say). of all the ways of doing it, the one that
+// keeps the arguments closest to the order they were given in is used:
+// the fewest pairs of query arguments that match definition arguments the
+// other way around. that count is given along with what f found, so that
+// an argument that is just swapped with its neighbour counts for less than
+// one moved from the end to the front, and so is the placement itself:
+// which definition argument each query argument went to.
+pub fn place_args<T: Copy>(qs: &[@Arg], ds: &[@Arg], start: &T,
+                           f: fn(@Arg, @Arg, &T) -> Option<T>)
+        -> Option<(T, uint, ~[uint])> {
+    place_within(qs, ds, start, MAX_TRIES, f)
+}
+
+// place_within is place_args, trying at most tries pairs of arguments
+fn place_within<T: Copy>(qs: &[@Arg], ds: &[@Arg], start: &T, tries: uint,
+                         f: fn(@Arg, @Arg, &T) -> Option<T>)
+        -> Option<(T, uint, ~[uint])> {
+    // place goes through the query arguments in order, trying each unused
+    // definition argument it could go to for the next one. used is which
+    // definition argument each query argument so far went to.
+    fn place<T: Copy>(qs: &[@Arg], cands: &~[~[uint]], ds: &[@Arg], cur: &T,
+                      used: &mut ~[uint], swaps: uint,
+                      best: &mut Option<(T, uint, ~[uint])>,
+                      tries: &mut uint, f: fn(@Arg, @Arg, &T) -> Option<T>) {
+        // swaps only go up from here, so this can't beat the best so far
+        match *best {
+            Some((_, n, _)) if n <= swaps => return,
+            _ => {}
+        }
+        if used.len() == qs.len() {
+            *best = Some((copy *cur, swaps, copy *used));
+            return;
+        }
+        for cands[used.len()].each |i| {
+            let i = *i;
+            if vec::contains(*used, &i) {
+                loop;
+            }
+            if *tries == 0 {
+                return;
+            }
+            *tries -= 1;
+            match f(qs[used.len()], ds[i], cur) {
+                Some(next) => {
+                    // every query argument before this one that went
+                    // after it is a pair the other way around
+                    let mut after = 0;
+                    for used.each |j| {
+                        if *j > i {
+                            after += 1;
+                        }
+                    }
+                    used.push(i);
+                    place(qs, cands, ds, &next, used, swaps + after, best,
+                          tries, f);
+                    used.pop();
+                }
+                None => {}
+            }
+        }
+    }
+    // matching a pair only gets harder as more is found, so the definition
+    // arguments each query argument matches on its own are the only ones
+    // it can go to. when they can't all go to different ones, there is no
+    // need to search at all.
+    let mut cands = ~[];
+    for qs.each |q| {
+        let mut cs = ~[];
+        let mut i = 0;
+        while i < ds.len() {
+            if f(*q, ds[i], start).is_some() {
+                cs.push(i);
+            }
+            i += 1;
+        }
+        cands.push(cs);
+    }
+    let placed = match can_place(&cands, ds.len()) {
+        Some(p) => p,
+        None => return None
+    };
+    let mut best = None;
+    let mut used = ~[];
+    let mut tries = tries;
+    place(qs, &cands, ds, start, &mut used, 0, &mut best, &mut tries, f);
+    if best.is_none() && tries == 0 {
+        best = fallback(qs, &cands, ds, start, &placed, f);
+    }
+    return best;
+}
+
+// fallback places the arguments when the search ran out of tries before
+// finding any way of doing it, which doesn't mean there is none. each query
+// argument in turn goes to the first definition argument left that it
+// matches, and if that gets stuck, the placement can_place found is
+// checked instead. neither goes back on what it has done, so they take
+// few tries, though they may not keep the arguments in the best order.
+fn fallback<T: Copy>(qs: &[@Arg], cands: &~[~[uint]], ds: &[@Arg],
+                     start: &T, placed: &~[uint],
+                     f: fn(@Arg, @Arg, &T) -> Option<T>)
+        -> Option<(T, uint, ~[uint])> {
+    let mut cur = copy *start;
+    let mut used = ~[];
+    while used.len() < qs.len() {
+        let q = used.len();
+        for cands[q].each |i| {
+            if vec::contains(used, i) {
+                loop;
+            }
+            match f(qs[q], ds[*i], &cur) {
+                Some(next) => {
+                    cur = next;
+                    used.push(*i);
+                    break;
+                }
+                None => {}
+            }
+        }
+        if used.len() == q {
+            break;
+        }
+    }
+    if used.len() == qs.len() {
+        return Some((cur, count_swaps(&used), used));
+    }
+    let mut cur = copy *start;
+    let mut q = 0;
+    while q < qs.len() {
+        match f(qs[q], ds[placed[q]], &cur) {
+            Some(next) => cur = next,
+            None => return None
+        }
+        q += 1;
+    }
+    return Some((cur, count_swaps(placed), copy *placed));
+}
+
+// count_swaps counts the pairs of query arguments in a placement that went
+// to definition arguments the other way around
+fn count_swaps(places: &~[uint]) -> uint {
+    let mut swaps = 0;
+    let mut i = 0;
+    while i < places.len() {
+        let mut j = i + 1;
+        while j < places.len() {
+            if places[i] > places[j] {
+                swaps += 1;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    return swaps;
+}
+
+// can_place finds a way for every query argument to go to a different one
+// of the definition arguments it could go to (cands, for each query
+// argument), giving where each went, if there is one. this is a matching
+// in a bipartite graph, which is found by augmenting paths.
+fn can_place(cands: &~[~[uint]], nds: uint) -> Option<~[uint]> {
+    // augment finds a definition argument for query argument q, moving the
+    // ones already placed (owner says where) to others if needed
+    fn augment(q: uint, cands: &~[~[uint]], owner: &mut ~[Option<uint>],
+               seen: &mut ~[bool]) -> bool {
+        for cands[q].each |d| {
+            let d = *d;
+            if (*seen)[d] {
+                loop;
+            }
+            (*seen)[d] = true;
+            let free = match (*owner)[d] {
+                None => true,
+                Some(q2) => augment(q2, cands, owner, seen)
+            };
+            if free {
+                (*owner)[d] = Some(q);
+                return true;
+            }
+        }
+        return false;
+    }
+    let mut owner = vec::from_elem(nds, None);
+    let mut q = 0;
+    while q < cands.len() {
+        let mut seen = vec::from_elem(nds, false);
+        if !augment(q, cands, &mut owner, &mut seen) {
+            return None;
+        }
+        q += 1;
+    }
+    let mut places = vec::from_elem(cands.len(), 0);
+    let mut d = 0;
+    while d < nds {
+        match owner[d] {
+            Some(q) => places[q] = d,
+            None => {}
+        }
+        d += 1;
+    }
+    return Some(places);
+}
+
+// rename_apart renames every type variable in a definition so that it can
+// never collide with the (identically canonicalized) variables of a query
+pub fn rename_apart(a: @Arg) -> @Arg {
+    map_constrained(a, |n, cs| { @Constrained(fmt!("'%s", *n), copy *cs) })
+}
+
+// strip_refs takes every pointer off of a type, however deep inside of it
+// they are, for matching modulo references
+pub fn strip_refs(a: @Arg) -> @Arg {
+    match *a {
+        Ptr(_, inner) => strip_refs(inner),
+        Vec(inner) => @Vec(strip_refs(inner)),
+        Tuple(ref args) => @Tuple(vec::map(*args, |a| { strip_refs(*a) })),
+        Parametric(base, ref args) => {
+            @Parametric(strip_refs(base),
+                        vec::map(*args, |a| { strip_refs(*a) }))
+        }
+        Function(ref args, ret) => {
+            @Function(vec::map(*args, |a| { strip_refs(*a) }),
+                      strip_refs(ret))
+        }
+        Array(elem, len) => @Array(strip_refs(elem), len),
+        Closure(ref tr, ref args, ret) => {
+            @Closure(copy *tr, vec::map(*args, |a| { strip_refs(*a) }),
+                     strip_refs(ret))
+        }
+        Projection(base, ref tr, ref item) => {
+            @Projection(strip_refs(base), copy *tr, copy *item)
+        }
+        QualifiedFn(ref qs, ref args, ret) => {
+            @QualifiedFn(copy *qs, vec::map(*args, |a| { strip_refs(*a) }),
+                         strip_refs(ret))
+        }
+        Dyn(ref cs, ref bs) => {
+            @Dyn(copy *cs, vec::map(*bs, |b| { b.map(strip_refs) }))
+        }
+        Impl(ref cs, ref bs) => {
+            @Impl(copy *cs, vec::map(*bs, |b| { b.map(strip_refs) }))
+        }
+        Basic(_) | Constrained(_, _) | Never | ConstArg(_) | Hole => a
+    }
+}
+
+// refs gives a type as it is matched: with its pointers, only if they have
+// to match exactly
+fn refs(a: @Arg, opts: &SearchOpts) -> @Arg {
+    if opts.strict_refs { a } else { strip_refs(a) }
+}
+
+// is_kind says whether a constraint is one of the built in kinds. they are
+// satisfied by (nearly) everything we index, and aren't recorded as trait
+// implementations, so they are never checked
+pure fn is_kind(c: &Constraint) -> bool {
+    match **c {
+        ~"Copy" | ~"Send" | ~"Owned" | ~"Const" | ~"Durable" | ~"Sized" => true,
+        _ => false
+    }
+}
+
+// satisfies checks whether a (non variable) type implements a trait,
+// according to the table of implementations, or the conversion traits (see
+// coerce.rs) that are known without it. the names of types there only
+// have to be the same as far as both of their paths go (see same_name),
+// so an impl recorded for Error counts for std::io::Error, but not one
+// for std::fmt::Error. if we have never seen an
+// implementation of the trait at all, we don't know enough to say no.
+pub fn satisfies(a: @Arg, c: &Constraint, impls: &Impls) -> bool {
+    if is_kind(c) {
+        return true;
+    }
+    let n = match head_name(a) {
+        Some(n) => n,
+        None => return true
+    };
+    if converts_through(&n, &**c) {
+        return true;
+    }
+    match impls.find(copy **c) {
+        Some(tys) => vec::any(tys, |t| { same_name(t, &n) }),
+        None => true
+    }
+}
+
+// check_constraints makes sure that every constrained variable in the
+// types given that was bound to a concrete type is bound to one that
+// satisfies its constraints. a definition variable bound to a query
+// variable (either way round) can't be checked like that, so the traits it
+// needs that the query variable doesn't have are counted instead, and the
+// count is returned. unifying ignores constraints, so this is done
+// afterwards.
+fn check_constraints(xs: &[@Arg], s: &Subst, impls: &Impls)
+        -> Option<uint> {
+    let mut ok = true;
+    // the (query variable, trait) pairs counted so far, since variables
+    // appear more than once
+    let mut unbounded: ~[(~str, Constraint)] = ~[];
+    for xs.each |x| {
+        map_constrained(*x, |name, constraints| {
+            let v = @Constrained(copy *name, copy *constraints);
+            match resolve(v, s) {
+                @Constrained(ref m, ref ms) => {
+                    let (qv, needed, has) =
+                        if is_def_var(name) && !is_def_var(m) {
+                            (copy *m, copy *constraints, copy *ms)
+                        } else if !is_def_var(name) && is_def_var(m) {
+                            (copy *name, copy *ms, copy *constraints)
+                        } else {
+                            (~"", ~[], ~[])
+                        };
+                    for needed.each |c| {
+                        let p = (copy qv, copy *c);
+                        if !is_kind(c) && !vec::contains(has, c) &&
+                            !vec::contains(unbounded, &p) {
+                            unbounded.push(p);
+                        }
+                    }
+                }
+                t => {
+                    for constraints.each |c| {
+                        if !satisfies(t, c, impls) { ok = false; }
+                    }
+                }
+            }
+            v
+        });
+    }
+    return if ok { Some(unbounded.len()) } else { None };
+}
+
+// Unified is the result of successfully matching a definition: the bindings
+// that were needed, how many pairs of arguments had to be swapped around
+// to get them, how many of the definition's arguments the query left out, the
+// coercions that were used (see coerce.rs), whether the definition is
+// a method whose receiver the query's first argument matched, and how many
+// traits the definition's variables need that the query's variables bound
+// to them weren't given (see check_constraints)
+pub struct Unified { subst: Subst, swaps: uint, missing: uint,
+                     coercions: ~[~str], receiver: bool, unbounded: uint }
+
+// match_def checks whether a definition can be instantiated to the query
+// (or the query to the definition), returning the bindings needed if so.
+// with opts.partial (or a .. in the query), the query's arguments only need
+// to match some of the definition's arguments. unless opts.strict_refs is
+// set, pointers are taken off of both sides first, so &mut T matches T (and
+// ~T, etc). only when the types don't match as they are is coercing them
+// tried: the query's arguments to the definition's, and the definition's
+// return type to the query's.
+pub fn match_def(q: &Query, d: &Definition, opts: &SearchOpts,
+                 impls: &Impls) -> Option<Unified> {
+    if q.args.len() > d.args.len() ||
+        (!opts.partial && !q.rest && q.args.len() != d.args.len()) {
+        return None;
+    }
+    let missing = d.args.len() - q.args.len();
+    let qargs = vec::map(q.args, |a| { refs(*a, opts) });
+    let qret = refs(q.ret, opts);
+    let dargs = vec::map(d.args, |a| { rename_apart(refs(*a, opts)) });
+    let dret = rename_apart(refs(d.ret, opts));
+    // the return type is the cheapest thing to reject on, so do it first
+    let ret = match unify(qret, dret, &~[]) {
+        Some(s) => Some(Coerced { subst: s, used: ~[] }),
+        None => coerce_to(dret, qret, &Coerced { subst: ~[], used: ~[] },
+                          opts, impls)
+    };
+    // a method is matched as one when the query's first argument went to
+    // its receiver, as it would in a method call
+    let in_order = d.receiver && qargs.len() > 0;
+    fn on_receiver(d: &Definition, places: &~[uint]) -> bool {
+        d.receiver && places.len() > 0 && places[0] == 0
+    }
+    let u = match ret {
+        Some(c) => {
+            // prefer the arguments in the order they were given
+            match unify_all(qargs, dargs, &c.subst) {
+                Some(s2) => Some(Unified { subst: s2, swaps: 0, missing: 0,
+                                           coercions: copy c.used,
+                                           receiver: in_order,
+                                           unbounded: 0 }),
+                // unify_unordered doesn't need to use every definition
+                // argument, so this also handles partial queries
+                None => match unify_unordered(qargs, dargs, &c.subst) {
+                    Some((s2, swaps, places)) => {
+                        Some(Unified { subst: s2, swaps: swaps,
+                                       missing: missing,
+                                       coercions: copy c.used,
+                                       receiver: on_receiver(d, &places),
+                                       unbounded: 0 })
+                    }
+                    None => match coerce_all(qargs, dargs, &c, opts, impls) {
+                        Some(c2) => Some(Unified { subst: copy c2.subst,
+                                                   swaps: 0, missing: 0,
+                                                   coercions: copy c2.used,
+                                                   receiver: in_order,
+                                                   unbounded: 0 }),
+                        None => {
+                            match coerce_unordered(qargs, dargs, &c, opts,
+                                                   impls) {
+                                Some((c2, swaps, places)) => {
+                                    Some(Unified {
+                                        subst: copy c2.subst,
+                                        swaps: swaps,
+                                        missing: missing,
+                                        coercions: copy c2.used,
+                                        receiver: on_receiver(d, &places),
+                                        unbounded: 0
+                                    })
+                                }
+                                None => None
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        None => None
+    };
+    match u {
+        Some(un) => {
+            let all = vec::append(vec::append(qargs, dargs),
+                                  ~[qret, dret]);
+            return match check_constraints(all, &un.subst, impls) {
+                Some(n) => Some(Unified { unbounded: n, ..un }),
+                None => None
+            };
+        }
+        None => return None
+    }
+}
+
+// unmatched counts the parts of a query that a definition has nothing to
+// match on its own: the return type, each argument, and each argument of
+// the definition left over. it is how close a definition that doesn't
+// match is, so that it can still be ranked.
+pub fn unmatched(q: &Query, d: &Definition, opts: &SearchOpts) -> uint {
+    let dargs = vec::map(d.args, |a| { rename_apart(refs(*a, opts)) });
+    let mut n = 0;
+    if unify(refs(q.ret, opts), rename_apart(refs(d.ret, opts)),
+             &~[]).is_none() {
+        n += 1;
+    }
+    for q.args.each |a| {
+        let qa = refs(*a, opts);
+        if !vec::any(dargs, |da| { unify(qa, *da, &~[]).is_some() }) {
+            n += 1;
+        }
+    }
+    if d.args.len() > q.args.len() && !opts.partial && !q.rest {
+        n += d.args.len() - q.args.len();
+    }
+    return n;
+}
+
+// is_def_var says whether a variable name came from a definition (as
+// opposed to the query), ie, whether it was renamed by rename_apart
+pub pure fn is_def_var(n: &~str) -> bool {
+    str::starts_with(*n, "'")
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn test_unify_basic() {
+        assert unify(@Basic(~"int"), @Basic(~"int"), &~[]).is_some();
+        assert unify(@Basic(~"int"), @Basic(~"uint"), &~[]).is_none();
+        let s = unify(@Basic(~"int"), @Constrained(~"A", ~[]), &~[]);
+        assert s == Some(~[(~"A", @Basic(~"int"))]);
+        // paths only have to agree as far as they are given
+        assert unify(@Basic(~"io::Error"), @Basic(~"Error"), &~[]).is_some();
+        assert unify(@Basic(~"std::io::Error"), @Basic(~"io::Error"),
+                     &~[]).is_some();
+        assert unify(@Basic(~"io::Error"), @Basic(~"fmt::Error"),
+                     &~[]).is_none();
+        assert unify(@Basic(~"Error"), @Basic(~"IoError"), &~[]).is_none();
+    }
+
+    #[test]
+    fn test_unify_consistent() {
+        // Option<int> -> int is an instance of Option<A> -> A
+        let s = unify(@Parametric(@Basic(~"Option"), ~[@Basic(~"int")]),
+                      @Parametric(@Basic(~"Option"),
+                                  ~[@Constrained(~"A", ~[])]),
+                      &~[]).get();
+        assert unify(@Basic(~"int"), @Constrained(~"A", ~[]), &s).is_some();
+        assert unify(@Basic(~"uint"), @Constrained(~"A", ~[]), &s).is_none();
+    }
+
+    #[test]
+    fn test_unify_occurs() {
+        assert unify(@Constrained(~"A", ~[]),
+                     @Vec(@Constrained(~"A", ~[])), &~[]).is_none();
+    }
+
+    #[test]
+    fn test_unify_unordered() {
+        let qs = ~[@Basic(~"uint"), @Basic(~"str")];
+        let ds = ~[@Basic(~"str"), @Constrained(~"A", ~[])];
+        assert unify_unordered(qs, ds, &~[]).is_some();
+        // each pair of arguments matched the other way around is counted
+        let a = @Constrained(~"A", ~[]);
+        let (s, swaps, _) = unify_unordered(~[@Basic(~"str"), @Basic(~"int")],
+                                         ~[a, @Basic(~"int")], &~[]).get();
+        assert swaps == 0;
+        assert s == ~[(~"A", @Basic(~"str"))];
+        let (_, swaps2, places) = unify_unordered(qs, ~[@Basic(~"str"), a],
+                                                  &~[]).get();
+        assert swaps2 == 1;
+        assert places == ~[1, 0];
+        // moving an argument further counts for more
+        let (_, swaps3, _) = unify_unordered(
+            ~[@Basic(~"int"), @Basic(~"uint"), @Basic(~"str")],
+            ~[@Basic(~"str"), @Basic(~"int"), @Basic(~"uint")], &~[]).get();
+        assert swaps3 == 2;
+        // duplicated types are each matched with an argument of their own
+        assert unify_unordered(~[a, a], ~[@Basic(~"int")], &~[]).is_none();
+        let ds2 = ~[@Basic(~"str"), @Basic(~"str")];
+        assert unify_unordered(qs, ds2, &~[]).is_none();
+    }
+
+    #[test]
+    fn test_place_args() {
+        let a = @Constrained(~"A", ~[]);
+        // no way of placing the arguments is found without searching
+        assert can_place(&~[~[0, 1], ~[0], ~[1]], 3).is_none();
+        assert can_place(&~[~[0, 1], ~[0], ~[2]], 3).get() == ~[1, 0, 2];
+        // definitions with many arguments are still searched quickly
+        let many = vec::from_elem(12, a);
+        let qs = vec::from_elem(8, @Basic(~"int"));
+        let (_, swaps, places) = unify_unordered(qs, many, &~[]).get();
+        assert swaps == 0;
+        assert places == ~[0, 1, 2, 3, 4, 5, 6, 7];
+        let strs = vec::append_one(copy qs, @Basic(~"str"));
+        assert unify_unordered(strs, many, &~[]).is_none();
+        // when the search gives up before placing the arguments at all,
+        // a placement is still found without it
+        let (b, c) = (@Constrained(~"B", ~[]), @Constrained(~"C", ~[]));
+        let ds = ~[a, a, b, b, c, c];
+        let (i, s) = (@Basic(~"int"), @Basic(~"str"));
+        let qs2 = ~[i, s, i, s, @Basic(~"bool"), @Basic(~"bool")];
+        fn unify_pair(q: @Arg, d: @Arg, s: &Subst) -> Option<Subst> {
+            unify(q, d, s)
+        }
+        let (_, swaps2, places2) = place_args(qs2, ds, &~[], unify_pair).get();
+        assert swaps2 == 1;
+        assert places2 == ~[0, 2, 1, 3, 4, 5];
+        let (sub, _, places3) = place_within(qs2, ds, &~[], 2,
+                                             unify_pair).get();
+        assert places3 == ~[0, 2, 1, 3, 4, 5];
+        assert resolve(a, &sub) == i;
+        assert resolve(b, &sub) == s;
+    }
+
+    #[test]
+    fn test_match_def() {
+        let def = Definition { name: ~"unwrap_left", path: ~"core::either",
+            desc: ~"", anchor: ~"function-unwrap_left",
+            args: ~[@Parametric(@Basic(~"Either"),
+                                ~[@Constrained(~"A", ~[]),
+                                  @Constrained(~"B", ~[])])],
+            ret: @Constrained(~"A", ~[]),
+            signature: ~"fn unwrap_left<T, U>(eith: Either<T, U>) -> T",
+            trait_name: None, crate_name: ~"core", receiver: false };
+        let q = Query { args: ~[@Parametric(@Basic(~"Either"),
+                                            ~[@Basic(~"int"),
+                                              @Basic(~"uint")])],
+                        ret: @Basic(~"int"), rest: false };
+        let opts = default_opts();
+        assert match_def(&q, &def, &opts, &HashMap()).get().swaps == 0;
+        let q2 = Query { ret: @Basic(~"uint"), ..q };
+        assert match_def(&q2, &def, &opts, &HashMap()).is_none();
+    }
+
+    #[test]
+    fn test_match_def_reordered() {
+        let def = Definition { name: ~"foo", path: ~"foo", desc: ~"",
+            anchor: ~"function-foo",
+            args: ~[@Basic(~"uint"), @Basic(~"str")],
+            ret: @Basic(~"bool"), signature: ~"fn foo(uint, str) -> bool",
+            trait_name: None, crate_name: ~"foo", receiver: false };
+        let q = Query { args: ~[@Basic(~"str"), @Basic(~"uint")],
+                        ret: @Basic(~"bool"), rest: false };
+        assert match_def(&q, &def, &default_opts(),
+                         &HashMap()).get().swaps == 1;
+    }
+
+    #[test]
+    fn test_match_def_partial() {
+        let def = Definition { name: ~"foo", path: ~"foo", desc: ~"",
+            anchor: ~"function-foo",
+            args: ~[@Basic(~"uint"), @Basic(~"str"), @Basic(~"uint")],
+            ret: @Basic(~"bool"),
+            signature: ~"fn foo(uint, str, uint) -> bool", trait_name: None,
+            crate_name: ~"foo", receiver: false };
+        let q = Query { args: ~[@Basic(~"str")], ret: @Basic(~"bool"),
+                        rest: false };
+        assert match_def(&q, &def, &default_opts(), &HashMap()).is_none();
+        let opts = SearchOpts { partial: true, ..default_opts() };
+        let u = match_def(&q, &def, &opts, &HashMap()).get();
+        assert u.missing == 2;
+        assert u.swaps == 0;
+        let q2 = Query { args: ~[@Basic(~"str"), @Basic(~"str")], ..q };
+        assert match_def(&q2, &def, &opts, &HashMap()).is_none();
+    }
+
+    #[test]
+    fn test_match_def_constraints() {
+        let ord = ~[Constraint(~"Ord")];
+        let def = Definition { name: ~"max", path: ~"core::vec", desc: ~"",
+            anchor: ~"function-max",
+            args: ~[@Vec(@Constrained(~"A", copy ord))],
+            ret: @Constrained(~"A", copy ord),
+            signature: ~"fn max<T: Ord>(v: &[T]) -> T", trait_name: None,
+            crate_name: ~"core", receiver: false };
+        let impls = HashMap();
+        impls.insert(~"Ord", ~[~"int"]);
+        let opts = default_opts();
+        let q = Query { args: ~[@Vec(@Basic(~"int"))], ret: @Basic(~"int"),
+                        rest: false };
+        assert match_def(&q, &def, &opts, &impls).is_some();
+        let q2 = Query { args: ~[@Vec(@Basic(~"fn"))], ret: @Basic(~"fn"),
+                         ..q };
+        assert match_def(&q2, &def, &opts, &impls).is_none();
+        // a query's own constraints are checked against the definition
+        let q3 = Query { args: ~[@Vec(@Constrained(~"A", copy ord))],
+                         ret: @Constrained(~"A", copy ord), ..q };
+        let def2 = Definition { args: ~[@Vec(@Basic(~"bool"))],
+                                ret: @Basic(~"bool"), ..def };
+        assert match_def(&q3, &def2, &opts, &impls).is_none();
+        // a query variable without the definition's traits still matches,
+        // but counts what it is missing
+        let a = @Constrained(~"A", ~[]);
+        let q4 = Query { args: ~[@Vec(a)], ret: a, ..q };
+        assert match_def(&q4, &def, &opts, &impls).get().unbounded == 1;
+        assert match_def(&q3, &def, &opts, &impls).get().unbounded == 0;
+    }
+
+    #[test]
+    fn test_match_def_refs() {
+        let v = @Parametric(@Basic(~"Vec"), ~[@Constrained(~"A", ~[])]);
+        let def = Definition { name: ~"push", path: ~"std::vec", desc: ~"",
+            anchor: ~"method.push",
+            args: ~[@Ptr(RefMut(Some(~"'a")), v), @Constrained(~"A", ~[])],
+            ret: @Basic(~"()"),
+            signature: ~"fn push(&mut self, value: T)", trait_name: None,
+            crate_name: ~"std", receiver: false };
+        let q = Query { args: ~[v, @Constrained(~"A", ~[])],
+                        ret: @Basic(~"()"), rest: false };
+        let q_mut = Query { args: ~[@Ptr(RefMut(None), v),
+                                    @Constrained(~"A", ~[])], ..q };
+        let q_ref = Query { args: ~[@Ptr(Ref(None), v),
+                                    @Constrained(~"A", ~[])], ..q };
+        let strict = SearchOpts { strict_refs: true, ..default_opts() };
+        // modulo references, all of them match
+        assert match_def(&q, &def, &default_opts(), &HashMap()).is_some();
+        assert match_def(&q_ref, &def, &default_opts(), &HashMap()).is_some();
+        // strictly, only the mutable reference does
+        assert match_def(&q_mut, &def, &strict, &HashMap()).is_some();
+        assert match_def(&q_ref, &def, &strict, &HashMap()).is_none();
+        assert match_def(&q, &def, &strict, &HashMap()).is_none();
+    }
+
+    #[test]
+    fn test_unify_modern() {
+        fn p(s: &str) -> @Arg {
+            parse::declare(parse::parse_arg(&str::from_slice(s)).get(),
+                           &~[~"A", ~"B", ~"T", ~"N"])
+        }
+        assert unify(p("[T; N]"), p("[u8; 4]"), &~[]).is_some();
+        assert unify(p("[u8; 3]"), p("[u8; 4]"), &~[]).is_none();
+        assert unify(p("dyn Send + Debug"), p("dyn Debug + Send"),
+                     &~[]).is_some();
+        assert unify(p("dyn Debug"), p("impl Debug"), &~[]).is_none();
+        // the associated types they bind have to match too
+        assert unify(p("impl Iterator<Item = u8>"),
+                     p("impl Iterator<Item = String>"), &~[]).is_none();
+        assert unify(p("impl Iterator<Item = u8>"),
+                     p("impl Iterator<Item = A>"), &~[]).get() ==
+            ~[(~"A", @Basic(~"u8"))];
+        assert unify(p("impl Iterator<Item = u8>"), p("impl Iterator"),
+                     &~[]).is_some();
+        assert unify(p("Fn(A) -> B"), p("Fn(int) -> str"), &~[]).is_some();
+        assert unify(p("Fn(A) -> B"), p("FnMut(A) -> B"), &~[]).is_none();
+        // a fn can be called as any kind of closure
+        assert unify(p("FnOnce(A) -> B"), p("fn(A) -> B"), &~[]).is_some();
+        assert unify(p("unsafe fn(A)"), p("fn(A)"), &~[]).is_none();
+        assert unify(p("T::Item"), p("<A as Iterator>::Item"),
+                     &~[]).is_some();
+        assert unify(p("<T as Deref>::Target"), p("<A as Iterator>::Item"),
+                     &~[]).is_none();
+        assert unify(p("!"), p("!"), &~[]).is_some();
+    }
+
+    #[test]
+    fn test_unify_holes() {
+        let a = @Constrained(~"A", ~[]);
+        // a hole matches anything, and binds nothing
+        assert unify(@Hole, @Basic(~"int"), &~[]) == Some(~[]);
+        assert unify(@Vec(a), @Hole, &~[]) == Some(~[]);
+        assert unify_all(~[@Hole, @Hole], ~[@Basic(~"int"), @Basic(~"str")],
+                         &~[]) == Some(~[]);
+        // unlike a variable, which can only be one type
+        assert unify_all(~[a, a], ~[@Basic(~"int"), @Basic(~"str")],
+                         &~[]).is_none();
+    }
+
+    #[test]
+    fn test_match_def_rest() {
+        let def = Definition { name: ~"foo", path: ~"foo", desc: ~"",
+            anchor: ~"function-foo",
+            args: ~[@Basic(~"uint"), @Basic(~"str"), @Basic(~"uint")],
+            ret: @Basic(~"bool"),
+            signature: ~"fn foo(uint, str, uint) -> bool", trait_name: None,
+            crate_name: ~"foo", receiver: false };
+        let q = Query { args: ~[@Hole, @Basic(~"str")], ret: @Basic(~"bool"),
+                        rest: false };
+        assert match_def(&q, &def, &default_opts(), &HashMap()).is_none();
+        // with .., the query doesn't need to give every argument
+        let u = match_def(&Query { rest: true, ..q }, &def, &default_opts(),
+                          &HashMap()).get();
+        assert u.missing == 1;
+        assert u.subst == ~[];
+    }
+
+    #[test]
+    fn test_match_def_coerced() {
+        // fn len(s: &str) -> uint
+        let def = Definition { name: ~"len", path: ~"core::str", desc: ~"",
+            anchor: ~"function-len", args: ~[@Ptr(Ref(None), @Basic(~"str"))],
+            ret: @Basic(~"uint"), signature: ~"fn len(s: &str) -> uint",
+            trait_name: None, crate_name: ~"core", receiver: false };
+        let q = Query { args: ~[@Basic(~"String")], ret: @Basic(~"uint"),
+                        rest: false };
+        let u = match_def(&q, &def, &default_opts(), &HashMap()).get();
+        assert u.coercions == ~[~"String to str"];
+        let exact = Query { args: ~[@Basic(~"str")], ..q };
+        assert match_def(&exact, &def, &default_opts(),
+                         &HashMap()).get().coercions == ~[];
+        // with strict references, only what is behind one is coerced
+        let strict = SearchOpts { strict_refs: true, ..default_opts() };
+        assert match_def(&q, &def, &strict, &HashMap()).is_none();
+        let q_ref = Query { args: ~[@Ptr(Ref(None), @Basic(~"String"))], ..q };
+        assert match_def(&q_ref, &def, &strict, &HashMap()).get().coercions ==
+            ~[~"&String to &str"];
+    }
+
+    #[test]
+    fn test_match_def_receiver() {
+        // fn contains(&self, needle: &str) -> bool, on str
+        let def = Definition { name: ~"contains", path: ~"core::str",
+            desc: ~"", anchor: ~"method-contains",
+            args: ~[@Basic(~"str"), @Basic(~"str")], ret: @Basic(~"bool"),
+            signature: ~"fn contains(&self, needle: &str) -> bool",
+            trait_name: None, crate_name: ~"core", receiver: true };
+        let q = Query { args: ~[@Basic(~"str")], ret: @Basic(~"bool"),
+                        rest: true };
+        assert match_def(&q, &def, &default_opts(), &HashMap()).get().receiver;
+        // a free function has no receiver to match
+        let f = Definition { receiver: false, ..def };
+        assert !match_def(&q, &f, &default_opts(), &HashMap()).get().receiver;
+        let q2 = Query { args: ~[@Basic(~"int")], ..q };
+        let def2 = Definition { args: ~[@Basic(~"str"), @Basic(~"int")],
+                                ..def };
+        assert !match_def(&q2, &def2, &default_opts(),
+                          &HashMap()).get().receiver;
+        // the first argument only coerces to the receiver, while it goes
+        // to the other argument as it is
+        let q3 = Query { args: ~[@Basic(~"String"), @Basic(~"str")],
+                         rest: false, ..q };
+        let def3 = Definition { args: ~[@Basic(~"str"), @Basic(~"String")],
+                                ..def };
+        let u = match_def(&q3, &def3, &default_opts(), &HashMap()).get();
+        assert u.swaps == 1;
+        assert !u.receiver;
+    }
+
+    #[test]
+    fn test_unmatched() {
+        let def = Definition { name: ~"foo", path: ~"foo", desc: ~"",
+            anchor: ~"function-foo",
+            args: ~[@Basic(~"uint"), @Basic(~"str")],
+            ret: @Basic(~"bool"),
+            signature: ~"fn foo(uint, str) -> bool", trait_name: None,
+            crate_name: ~"foo", receiver: false };
+        let q = Query { args: ~[@Basic(~"str"), @Basic(~"uint")],
+                        ret: @Basic(~"bool"), rest: false };
+        assert unmatched(&q, &def, &default_opts()) == 0;
+        let q2 = Query { args: ~[@Basic(~"int")], ret: @Basic(~"int"), ..q };
+        // the return type, the argument, and the argument left over
+        assert unmatched(&q2, &def, &default_opts()) == 3;
+        assert unmatched(&Query { rest: true, ..q2 }, &def,
+                         &default_opts()) == 2;
+    }
+
+    #[test]
+    fn test_strip_refs() {
+        assert strip_refs(@Parametric(@Basic(~"Option"),
+                                      ~[@Ptr(Ref(None), @Basic(~"str"))])) ==
+            @Parametric(@Basic(~"Option"), ~[@Basic(~"str")]);
+        assert strip_refs(@Ptr(Owned, @Ptr(Managed, @Basic(~"int")))) ==
+            @Basic(~"int");
+    }
+
+    #[test]
+    fn test_satisfies() {
+        let impls = HashMap();
+        impls.insert(~"Ord", ~[~"int"]);
+        assert satisfies(@Basic(~"int"), &Constraint(~"Ord"), &impls);
+        assert !satisfies(@Basic(~"bool"), &Constraint(~"Ord"), &impls);
+        assert satisfies(@Basic(~"bool"), &Constraint(~"Copy"), &impls);
+        assert satisfies(@Basic(~"bool"), &Constraint(~"Hash"), &impls);
+        // the conversion traits are known without any impls of them
+        impls.insert(~"AsRef", ~[~"int"]);
+        assert satisfies(@Basic(~"PathBuf"), &Constraint(~"AsRef"), &impls);
+        assert !satisfies(@Basic(~"bool"), &Constraint(~"AsRef"), &impls);
+    }
+}
@@ -1,85 +1,242 @@
+//! This file contains the web frontend: the search page and json api that
+//! `rustle serve` answers requests with (see server.rs)
+
 use std::net::url;
 
-use io::WriterUtil;
+use types::*;
+use server::Response;
+use html::{Text, Html};
 
-use types::Definition;
+// the templates for the page, and for a single result on it (see html.rs).
+// the score is kept on each result so that scripts scraping the page can
+// get at it.
+fn page_template() -> ~str {
+    ~"<html><body><p>This is rustle. Check the code at <a \
+      href='http://github.com/dbp/rustle'>github.com/dbp/rustle</a>.</p>\
+      <p>Query form: (arg1,arg2) -> ret, or name :: (arg1,arg2) -> ret.</p>\
+      <pre><code>Examples: ([A]) -> A, (Option&lt;A&gt;) -> A, \
+      ([A], fn(A)->B) -> [B], find :: [A] -> Option&lt;A&gt;</code></pre>\
+      <form><input type='text' name='q' size='50' value='{{q}}'/><input \
+      type='submit' value='Rustle Up'/><label><input type='checkbox' \
+      name='partial'/> partial</label><label><input type='checkbox' \
+      name='strict_refs'/> strict references</label><label><input \
+      type='checkbox' name='no_aliases'/> no aliases</label></form>\
+      {{heading}}<hr/>\
+      <div>{{results}}</div></body></html>"
+}
 
-fn strip_brackets(s:~str) -> ~str {
-    str::replace(str::replace(s, ~"<", ~"&lt;"), ~">", ~"&gt;")
+fn result_template() -> ~str {
+    ~"<pre data-score='{{score}}'><code>\n\
+      <a href='http://dl.rust-lang.org/doc/{{doc}}.html#{{anchor}}' \
+      target='blank'>{{path}}::{{name}}</a> - {{signature}}{{coerced}}\
+      {{resolved}} - \
+      {{desc}}\n\
+      </code></pre>\n"
 }
 
-fn format_def(d: @Definition) -> ~str {
-    fmt!("<a href='http://dl.rust-lang.org/doc/%s.html#%s'\
-        target='blank'>%s::%s</a> - %s - %s",
-        str::replace(d.path, ~"::", ~"/"),d.anchor, d.path,
-             d.name, strip_brackets(d.signature), strip_brackets(d.desc))
+// format_match renders a single result, noting any coercions it needed
+// and which types it was found for, of those the query's names could be
+fn format_match(m: &Match) -> ~str {
+    let coerced = if m.coercions.len() == 0 {
+        ~""
+    } else {
+        fmt!(" (coerced %s)", str::connect(m.coercions, ", "))
+    };
+    let resolved = if m.resolved.len() == 0 { ~"" }
+                   else { fmt!(" (%s)", m.which()) };
+    html::render(result_template(),
+                 &~[(~"score", Text(fmt!("%u", m.score))),
+                    (~"doc", Text(str::replace(m.def.path, ~"::", ~"/"))),
+                    (~"anchor", Text(copy m.def.anchor)),
+                    (~"path", Text(copy m.def.path)),
+                    (~"name", Text(copy m.def.name)),
+                    (~"signature", Text(m.signature())),
+                    (~"coerced", Text(coerced)),
+                    (~"resolved", Text(resolved)),
+                    (~"desc", Text(copy m.def.desc))])
 }
 
-fn main() {
-    let ctx = match zmq::init(1) {
-        Ok(ctx) => ctx,
-        Err(e) => fail e.to_str(),
+// respond answers a request for a target (a path and query string): either
+// the search page, or the json api
+pub fn respond(data: &Data, target: &~str) -> Response {
+    let (path, query) = match str::find_char(*target, '?') {
+        Some(i) => (str::slice(*target, 0, i),
+                    str::slice(*target, i + 1, target.len())),
+        None => (copy *target, ~"")
     };
+    match path {
+        ~"/" => server::html(page(data, &query)),
+        ~"/api/search" => api_search(data, &query),
+        _ => server::not_found()
+    }
+}
 
-    let conn = mongrel2::connect(ctx,
-        Some(~"d5e521f5-c1bf-481c-a815-bc66905526ae"),
-        ~[~"tcp://127.0.0.1:9998"],
-        ~[~"tcp://127.0.0.1:9999"]);
+// api_search answers /api/search?q=... with the results as json. the
+// same queries as on the page work (partial=1, strict_refs=1, no_aliases=1
+// and crate: included), and problems with the request give an error object
+// instead of results. names in the query that could be more than one type
+// are given under ambiguous, each with the full paths it could be.
+fn api_search(data: &Data, query_string: &~str) -> Response {
+    let form = url::decode_form_urlencoded(str::to_bytes(*query_string));
+    let raw = match form.find(~"q") {
+        Some(qs) => copy *qs[0],
+        None => return api_error(~"missing query parameter q")
+    };
+    let opts = SearchOpts {
+        partial: form.find(~"partial").is_some(),
+        crates: ~[],
+        strict_refs: form.find(~"strict_refs").is_some(),
+        aliases: form.find(~"no_aliases").is_none()
+    };
+    let (q, opts) = query::with_crates(&raw, &opts);
+    if q.len() == 0 {
+        return api_error(~"empty query");
+    }
+    let (kind, results) = match query::search(&q, data, &opts) {
+        Ok(r) => r,
+        Err(e) => {
+            // with_crates only takes crate: off the front (and spaces off
+            // the ends), so that is how far into the raw query q starts
+            let lead = str::trim_right(raw).len() - q.len();
+            let e = ParseError { pos: e.pos + lead, ..e };
+            return server::json(400, &load::record(~[
+                (~"error", String(fmt!("can't parse type query: %s", e.msg))),
+                (~"pos", Number(e.pos as float)),
+                (~"diagnostic", String(e.show(&raw)))]));
+        }
+    };
+    let ambiguous = load::record(vec::map(query::ambiguities(&q, data), |a| {
+        (a.first(), List(vec::map(a.second(), |p| { String(copy *p) })))
+    }));
+    server::json(200, &load::record(~[
+        (~"query", String(copy raw)),
+        (~"kind", String(kind)),
+        (~"ambiguous", ambiguous),
+        (~"results", List(vec::map(results, |m| { m.to_json() })))]))
+}
 
-    let data = load::load(path::from_str("rustle.data"));
+fn api_error(msg: ~str) -> Response {
+    server::json(400, &load::record(~[(~"error", String(msg))]))
+}
 
-    loop {
-        let request = result::unwrap(conn.recv());
-        let query_raw = match request.headers.find_ref(&~"QUERY") {
-            Some(qs) => Some(str::to_bytes(qs[0])),
-            None => None
-        };
-        let mq = option::chain(query_raw, |que| {
-            url::decode_form_urlencoded(que).find(~"q").map(|qs| { *qs[0] })
-        });
+// page renders the search page, with the results for the query string
+fn page(data: &Data, query_string: &~str) -> ~str {
+    let form = url::decode_form_urlencoded(str::to_bytes(*query_string));
+    let mq = form.find(~"q").map(|qs| { *qs[0] });
+    let opts = SearchOpts {
+        partial: form.find(~"partial").is_some(),
+        crates: ~[],
+        strict_refs: form.find(~"strict_refs").is_some(),
+        aliases: form.find(~"no_aliases").is_none()
+    };
 
-        let resp = match mq {
-            Some(q) => {
-                do io::with_str_writer |w| {
-                    // do search
-                    if q.contains(~"->") || q.contains(~",") {
-                        // this is a search by type, for functions
-                        // build query
-                        let queries = query::query(copy q);
-                        // search
-                        for query::search_type(queries, &data).each |d| {
-                            w.write_line("<pre><code>");
-                            w.write_line(format_def(*d));
-                            w.write_line("</code></pre>")
-                        }
-                    } else {
-                        // this is a search by name
-                        for query::search_name(q, &data).each |d| {
-                            w.write_line("<pre><code/>");
-                            w.write_line(format_def(*d));
-                            w.write_line("</code></pre>");
-                        }
+    // names in the query that could be more than one type are pointed out
+    // under it
+    let mut notes = ~[];
+    let results = match mq {
+        Some(ref raw) => {
+            let (q, opts) = query::with_crates(raw, &opts);
+            match query::search(&q, data, &opts) {
+                Ok((_kind, ms)) => {
+                    for query::ambiguities(&q, data).each |a| {
+                        let (name, paths) = copy *a;
+                        notes.push(paths::explain(&name, &paths));
                     }
+                    str::concat(ms.map(|m| { format_match(m) }))
+                }
+                Err(e) => {
+                    html::render("<p>couldn't parse query:</p>\
+                                  <pre>{{diagnostic}}</pre>",
+                                 &~[(~"diagnostic", Text(e.show(&q)))])
                 }
-            },
-            None => ~""
-        };
+            }
+        },
+        None => ~""
+    };
+
+    let q = option::get_default(&mq, ~"");
+    let mut heading = if q.len() > 0 { ~"query: " + q } else { ~"" };
+    for notes.each |n| {
+        heading += ~"; " + *n;
+    }
+    html::render(page_template(), &~[(~"q", Text(q)),
+                                     (~"heading", Text(heading)),
+                                     (~"results", Html(results))])
+}
 
+#[cfg(test)]
+mod tests {
 
-        // fancy templating :)
-        let page =
-            fmt!("<html><body><p>This is rustle. Check the code at <a \
-                href='http://github.com/dbp/rustle'>github.com/dbp/rustle</a>\
-                .</p><p>Query form: (arg1,arg2) -> ret.</p><pre><code>Examples: ([A]) -> A, \
-                 (Option&lt;A&gt;) -> A, ([A], fn(A)->B) -> [B]</code></pre>\
-                 <form><input type='text' name='q' size='50'/><input \
-                type='submit' value='Rustle Up'/></form>%s<hr/><div>%s</div></body></html>", if resp.len() > 0
-                {~"query: " + mq.get()} else { ~"" },  resp);
+    #[test]
+    fn test_respond() {
+        let data = empty_data();
+        assert respond(&data, &~"/favicon.ico").status == 404;
+        let r = respond(&data, &~"/?q=each");
+        assert r.status == 200;
+        assert str::contains(r.body, "<form>");
+        // the query is escaped wherever it is put in the page
+        let r2 = respond(&data, &~"/?q=%3Cscript%3E");
+        assert !str::contains(r2.body, "<script>");
+        assert str::contains(r2.body, "query: &lt;script&gt;");
+    }
 
-        conn.reply_http(&request,
-            200u,
-            "OK",
-            mongrel2::Headers(),
-            str::to_bytes(page));
+    #[test]
+    fn test_format_match() {
+        let d = @Definition { name: ~"foo", path: ~"core::foo",
+            anchor: ~"function-foo", desc: ~"a & b", args: ~[],
+            ret: @Basic(~"int"), signature: ~"fn foo<'a>() -> int",
+            trait_name: None, crate_name: ~"core", receiver: false };
+        let m = Match { def: d, score: 3, coercions: ~[], receiver: false,
+                        resolved: ~[], how: ~"exact" };
+        let html = format_match(&m);
+        assert str::contains(html, "data-score='3'");
+        assert str::contains(html, "href='http://dl.rust-lang.org/doc/\
+                                     core/foo.html#function-foo'");
+        assert str::contains(html, "fn foo&lt;&#39;a&gt;() -&gt; int - a &amp; b");
+        let coerced = format_match(&Match { coercions: ~[~"String to str"],
+                                            ..m });
+        assert str::contains(coerced,
+                             "int (coerced String to str) - a &amp; b");
+        let resolved = format_match(&Match { resolved: ~[~"std::io::Error"],
+                                             ..m });
+        assert str::contains(resolved,
+                             "int (Error is std::io::Error) - a &amp; b");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_api_search() {
+        let data = empty_data();
+        let r = respond(&data, &~"/api/search?q=each");
+        assert r.status == 200;
+        assert r.content_type == ~"application/json";
+        let j = json::from_str(r.body).get();
+        match j {
+            Object(obj) => {
+                assert obj.get(&~"kind") == String(~"name");
+                assert obj.get(&~"results") == List(~[]);
+                assert obj.get(&~"ambiguous") == load::record(~[]);
+            }
+            _ => fail
+        }
+        let r2 = respond(&data, &~"/api/search?q=find+%3A%3A+%5BA%5D+-%3E+A");
+        match json::from_str(r2.body).get() {
+            Object(obj) => assert obj.get(&~"kind") == String(~"combined"),
+            _ => fail
+        }
+        assert respond(&data, &~"/api/search").status == 400;
+        assert respond(&data, &~"/api/search?q=crate%3Astd").status == 400;
+        let bad = respond(&data, &~"/api/search?q=%5Bint+-%3E+int");
+        assert bad.status == 400;
+        match json::from_str(bad.body).get() {
+            Object(obj) => assert obj.get(&~"pos") == Number(0f),
+            _ => fail
+        }
+        // the position is in the query as it was given, crate: and all
+        let bad2 = respond(&data,
+                           &~"/api/search?q=crate%3Astd+%5Bint+-%3E+int");
+        match json::from_str(bad2.body).get() {
+            Object(obj) => assert obj.get(&~"pos") == Number(10f),
+            _ => fail
+        }
+    }
+}